@@ -1,19 +1,28 @@
 use std::collections::HashMap;
-use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
 use enigo::{Axis, Button, Coordinate, Direction, InputError, Key};
 use enigo::{Enigo, Keyboard, Mouse, Settings};
 use serde::{Deserialize, Serialize};
 
+use crate::action_schema::{lookup_action, ActionKind, ActionSpec};
+use crate::clipboard::{Clipboard, TextEntry};
 use crate::key_parser::parse_key_from_str;
+use crate::motion::{MotionProfile, TypingCadence};
+use crate::plan::{ActionPlan, PlanReport, PlanStep, PostCondition, StepOutcome};
+use crate::scroll::{default_length, RegionCapture, ScreenProbe, ScrollSettings, ScrollTarget, ScrollUnit};
+use crate::verify::{watched_point, VerifyConfig};
+use crate::kill_switch::{ActionCancelled, CancelToken};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "type", content = "data")]
 pub enum InputAction {
     KeyClick(Key),
     WriteText(String),
+    SetClipboard(String),
+    /// the text is kept for `ActionControl::take_clipboard_text`
+    GetClipboard,
 
     MouseMove { x: i32, y: i32 },
     MouseLeftClick { x: i32, y: i32 },
@@ -22,11 +31,42 @@ pub enum InputAction {
     MouseMiddleClick { x: i32, y: i32 },
 
     Drag { x1: i32, y1: i32, x2: i32, y2: i32 },
+    /// click at the anchor, shift-click at the end; see `SelectMode`
     Select { x1: i32, y1: i32, x2: i32, y2: i32 },
-    Scroll { x: i32, y: i32, length: i32, direction: Axis },
+    /// selects the line or paragraph under the point
+    TripleClick { x: i32, y: i32 },
+    /// the platform's select all hotkey
+    SelectAll,
+    /// `length` is signed, negative scrolls up or left
+    Scroll {
+        x: i32,
+        y: i32,
+        length: i32,
+        direction: Axis,
+        #[serde(default)]
+        unit: ScrollUnit,
+    },
+    /// scrolls `step` notches at a time until `until` is met, at most `max_scrolls` times
+    ScrollUntil {
+        x: i32,
+        y: i32,
+        step: i32,
+        direction: Axis,
+        until: ScrollTarget,
+        max_scrolls: u32,
+    },
 
     Hotkey { hot_keys: Vec<Key> },
 
+    /// held until `KeyUp`, `release_all` or the control is dropped
+    KeyDown(Key),
+    KeyUp(Key),
+    KeyHold { key: Key, ms: u64 },
+    ButtonDown { x: i32, y: i32, button: Button },
+    ButtonUp(Button),
+    /// `count` clicks with `modifiers` held, e.g. shift-click or a triple click
+    Click { x: i32, y: i32, button: Button, modifiers: Vec<Key>, count: u32 },
+
     Wait { milliseconds: u64 },
 }
 impl InputAction {
@@ -51,87 +91,571 @@ impl InputAction {
         Ok(keys)
     }
 
-    fn parse_box(box_name: &str, action_inputs: &HashMap<String, String>) -> Result<(i32, i32)> {
-        let start_box = action_inputs.get(box_name).ok_or_else(|| anyhow!("missing start_box in inputs: {:?}", action_inputs))?;
-        let start_box_values = serde_json::from_str::<Vec<f32>>(start_box).context("parse value failed,invalid start_box json value")?;
-        if start_box_values.len() < 2 {
-            return Err(anyhow!("invalid start_box value: {:?}", start_box_values));
+    fn parse_button(button: &str) -> Result<Button> {
+        match button.trim().to_lowercase().as_str() {
+            "left" => Ok(Button::Left),
+            "right" => Ok(Button::Right),
+            "middle" => Ok(Button::Middle),
+            other => Err(anyhow!("invalid mouse button: {}", other)),
         }
-        let x = start_box_values[0] as i32;
-        let y = start_box_values[1] as i32;
+    }
+
+    fn parse_box(spec: &ActionSpec, box_name: &str, action_inputs: &HashMap<String, String>) -> Result<(i32, i32)> {
+        let value = spec.value(box_name, action_inputs)?;
+        let box_values = serde_json::from_str::<Vec<f32>>(value).with_context(|| format!("parse value failed,invalid {} json value", box_name))?;
+        if box_values.len() < 2 {
+            return Err(anyhow!("invalid {} value: {:?}", box_name, box_values));
+        }
+        let x = box_values[0] as i32;
+        let y = box_values[1] as i32;
         Ok((x, y))
     }
 
+    /// The same action with every screen point moved by (`dx`, `dy`).
+    pub fn translate(&self, dx: i32, dy: i32) -> InputAction {
+        let mut action = self.clone();
+        match &mut action {
+            InputAction::MouseMove { x, y }
+            | InputAction::MouseLeftClick { x, y }
+            | InputAction::MouseLeftDoubleClick { x, y }
+            | InputAction::MouseRightClick { x, y }
+            | InputAction::MouseMiddleClick { x, y }
+            | InputAction::TripleClick { x, y }
+            | InputAction::Scroll { x, y, .. }
+            | InputAction::ScrollUntil { x, y, .. }
+            | InputAction::ButtonDown { x, y, .. }
+            | InputAction::Click { x, y, .. } => {
+                *x += dx;
+                *y += dy;
+            }
+            InputAction::Drag { x1, y1, x2, y2 } | InputAction::Select { x1, y1, x2, y2 } => {
+                *x1 += dx;
+                *y1 += dy;
+                *x2 += dx;
+                *y2 += dy;
+            }
+            _ => {}
+        }
+        action
+    }
+
+    /// Builds the action from the `ACTION_SCHEMA` entry matching `action_type`.
     pub fn parse_from_action_type_and_inputs(action_type: String, action_inputs: HashMap<String, String>) -> Result<InputAction> {
-        match action_type.as_str() {
-            "click" | "click_left" => {
-                let (x, y) = Self::parse_box("start_box", &action_inputs)?;
+        let spec = lookup_action(&action_type).ok_or_else(|| anyhow!("invalid action type: {}", action_type))?;
+        match spec.kind {
+            ActionKind::MouseLeftClick => {
+                let (x, y) = Self::parse_box(spec, "start_box", &action_inputs)?;
                 Ok(InputAction::MouseLeftClick { x, y })
             }
-            "left_double" | "left_double_click" | "double_click" => {
-                let (x, y) = Self::parse_box("start_box", &action_inputs)?;
+            ActionKind::MouseLeftDoubleClick => {
+                let (x, y) = Self::parse_box(spec, "start_box", &action_inputs)?;
                 Ok(InputAction::MouseLeftDoubleClick { x, y })
             }
-            "right_single" | "right_click" => {
-                let (x, y) = Self::parse_box("start_box", &action_inputs)?;
+            ActionKind::MouseRightClick => {
+                let (x, y) = Self::parse_box(spec, "start_box", &action_inputs)?;
                 Ok(InputAction::MouseRightClick { x, y })
             }
-            "mouse_move" => {
-                let (x, y) = Self::parse_box("start_box", &action_inputs)?;
+            ActionKind::MouseMiddleClick => {
+                let (x, y) = Self::parse_box(spec, "start_box", &action_inputs)?;
+                Ok(InputAction::MouseMiddleClick { x, y })
+            }
+            ActionKind::MouseMove => {
+                let (x, y) = Self::parse_box(spec, "start_box", &action_inputs)?;
                 Ok(InputAction::MouseMove { x, y })
             }
-            "drag" => {
-                let (x1, y1) = Self::parse_box("start_box", &action_inputs)?;
-                let (x2, y2) = Self::parse_box("end_box", &action_inputs)?;
+            ActionKind::Drag => {
+                let (x1, y1) = Self::parse_box(spec, "start_box", &action_inputs)?;
+                let (x2, y2) = Self::parse_box(spec, "end_box", &action_inputs)?;
                 Ok(InputAction::Drag { x1, y1, x2, y2 })
             }
-            "scroll" => {
-                let (x, y) = Self::parse_box("start_box", &action_inputs)?;
-                let direction = action_inputs.get("direction").ok_or_else(|| anyhow!("missing direction in inputs: {:?}", action_inputs))?;
-                let length = action_inputs.get("length").and_then(|v| v.parse::<i32>().ok()).unwrap_or(100);
-                let (axis, length_with_sign) = Self::parse_direction(direction, length)?;
+            ActionKind::Select => {
+                let (x1, y1) = Self::parse_box(spec, "start_box", &action_inputs)?;
+                let (x2, y2) = Self::parse_box(spec, "end_box", &action_inputs)?;
+                Ok(InputAction::Select { x1, y1, x2, y2 })
+            }
+            ActionKind::TripleClick => {
+                let (x, y) = Self::parse_box(spec, "start_box", &action_inputs)?;
+                Ok(InputAction::TripleClick { x, y })
+            }
+            ActionKind::SelectAll => Ok(InputAction::SelectAll),
+            ActionKind::Scroll => {
+                let (x, y) = Self::parse_box(spec, "start_box", &action_inputs)?;
+                let direction = spec.value("direction", &action_inputs)?;
+                let unit = spec.value("unit", &action_inputs)?.parse::<ScrollUnit>()?;
+                let (axis, sign) = Self::parse_direction(direction, 1)?;
+                // "auto" or anything unparsable scrolls the direction's default
+                let length = match spec.value("length", &action_inputs)?.parse::<i32>() {
+                    Ok(length) if length != 0 => length.abs(),
+                    _ => {
+                        if unit != ScrollUnit::Notches {
+                            return Err(anyhow!("scroll in {:?} needs a length", unit));
+                        }
+                        default_length(axis)
+                    }
+                };
                 Ok(InputAction::Scroll {
                     x,
                     y,
-                    length: length_with_sign,
+                    length: length * sign,
                     direction: axis,
+                    unit,
                 })
             }
-            "hotkey" => {
-                let hot_keys = action_inputs.get("key").ok_or_else(|| anyhow!("missing key in inputs: {:?}", action_inputs))?;
-                let hot_keys = Self::parse_hotkeys(hot_keys)?;
+            ActionKind::ScrollUntil => {
+                let (x, y) = Self::parse_box(spec, "start_box", &action_inputs)?;
+                let (axis, sign) = Self::parse_direction(spec.value("direction", &action_inputs)?, 1)?;
+                let max_scrolls = spec.value("max_scrolls", &action_inputs)?.parse::<u32>().context("parse value failed,invalid max_scrolls value")?;
+                Ok(InputAction::ScrollUntil {
+                    x,
+                    y,
+                    step: default_length(axis) * sign,
+                    direction: axis,
+                    until: ScrollTarget::parse(spec.value("target", &action_inputs)?),
+                    max_scrolls,
+                })
+            }
+            ActionKind::Hotkey => {
+                let hot_keys = Self::parse_hotkeys(spec.value("key", &action_inputs)?)?;
                 Ok(InputAction::Hotkey { hot_keys })
             }
-            "wait" => {
-                let milliseconds = action_inputs.get("milliseconds").map(|s| s.as_str()).unwrap_or("5000");
+            ActionKind::KeyDown => Ok(InputAction::KeyDown(parse_key_from_str(spec.value("key", &action_inputs)?))),
+            ActionKind::KeyUp => Ok(InputAction::KeyUp(parse_key_from_str(spec.value("key", &action_inputs)?))),
+            ActionKind::KeyHold => {
+                let key = parse_key_from_str(spec.value("key", &action_inputs)?);
+                let ms = spec.value("milliseconds", &action_inputs)?.parse::<u64>().context("parse value failed,invalid milliseconds value")?;
+                Ok(InputAction::KeyHold { key, ms })
+            }
+            ActionKind::ButtonDown => {
+                let (x, y) = Self::parse_box(spec, "start_box", &action_inputs)?;
+                let button = Self::parse_button(spec.value("button", &action_inputs)?)?;
+                Ok(InputAction::ButtonDown { x, y, button })
+            }
+            ActionKind::ButtonUp => Ok(InputAction::ButtonUp(Self::parse_button(spec.value("button", &action_inputs)?)?)),
+            ActionKind::Wait => {
+                let milliseconds = spec.value("milliseconds", &action_inputs)?;
                 Ok(InputAction::Wait {
                     milliseconds: milliseconds.parse::<u64>().context("parse value failed,invalid milliseconds value")?,
                 })
             }
-            "type" => {
-                let text = action_inputs.get("content").ok_or_else(|| anyhow!("missing text in inputs: {:?}", action_inputs))?;
+            ActionKind::WriteText => {
+                let text = spec.value("content", &action_inputs)?;
                 Ok(InputAction::WriteText(text.to_string()))
             }
-            "key_click" => {
-                let key = action_inputs.get("key").ok_or_else(|| anyhow!("missing key in inputs: {:?}", action_inputs))?;
-                let parse_key = parse_key_from_str(key);
+            ActionKind::SetClipboard => {
+                let text = spec.value("content", &action_inputs)?;
+                Ok(InputAction::SetClipboard(text.to_string()))
+            }
+            ActionKind::GetClipboard => Ok(InputAction::GetClipboard),
+            ActionKind::KeyClick => {
+                let parse_key = parse_key_from_str(spec.value("key", &action_inputs)?);
                 Ok(InputAction::KeyClick(parse_key))
             }
-            _ => Err(anyhow!("invalid action type: {}", action_type)),
+            ActionKind::Finished | ActionKind::CallUser => Err(anyhow!("{} ends the task and has no input action", spec.name)),
         }
     }
 }
 
+/// How `Select` extends the selection from its anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SelectMode {
+    /// anchor click then shift-click, falls back to a drag when the screen probe sees no selection appear
+    #[default]
+    ShiftClick,
+    /// press at the anchor and drag, for canvases and apps that ignore shift-click
+    Drag,
+}
+
+// how often a post-condition is checked until it holds
+const EXPECT_POLL: Duration = Duration::from_millis(100);
+
+/// Meta on macOS, Control elsewhere
+fn command_key() -> Key {
+    if cfg!(target_os = "macos") {
+        Key::Meta
+    } else {
+        Key::Control
+    }
+}
+
 pub struct ActionControl {
     pub enigo: Enigo,
+    pub cancel_token: CancelToken,
+    pub text_entry: TextEntry,
+    /// cursor path for moves, clicks and drags
+    pub motion: MotionProfile,
+    /// delay between typed characters, pasted text is not affected
+    pub typing: TypingCadence,
+    pub select_mode: SelectMode,
+    pub scroll: ScrollSettings,
+    /// checks that plan clicks without their own post-condition change the screen, needs a screen probe.
+    /// Its thresholds, or the defaults, also decide whether a shift-click selection worked.
+    pub verify: Option<VerifyConfig>,
+    screen_probe: Option<Box<dyn ScreenProbe>>,
+    located: Option<(i32, i32)>,
+    clipboard: Clipboard,
+    clipboard_text: Option<String>,
+    held_keys: Vec<Key>,
+    held_buttons: Vec<Button>,
 }
 
 impl ActionControl {
     pub fn new(settings: &Settings) -> Self {
-        Self { enigo: Enigo::new(settings).unwrap() }
+        Self::with_cancel_token(settings, CancelToken::new())
+    }
+
+    pub fn with_cancel_token(settings: &Settings, cancel_token: CancelToken) -> Self {
+        Self {
+            enigo: Enigo::new(settings).unwrap(),
+            cancel_token,
+            text_entry: TextEntry::default(),
+            motion: MotionProfile::default(),
+            typing: TypingCadence::default(),
+            select_mode: SelectMode::default(),
+            scroll: ScrollSettings::default(),
+            verify: None,
+            screen_probe: None,
+            located: None,
+            clipboard: Clipboard::default(),
+            clipboard_text: None,
+            held_keys: Vec::new(),
+            held_buttons: Vec::new(),
+        }
+    }
+
+    /// Keys pressed by `KeyDown` and not released yet, in press order.
+    pub fn held_keys(&self) -> &[Key] {
+        &self.held_keys
+    }
+
+    pub fn held_buttons(&self) -> &[Button] {
+        &self.held_buttons
+    }
+
+    /// Releases every held key and button, newest first. Tries all of them before reporting an error.
+    pub fn release_all(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        while let Some(button) = self.held_buttons.pop() {
+            if let Err(e) = self.enigo.button(button, Direction::Release) {
+                result = Err(anyhow!("failed to release {:?}: {}", button, e));
+            }
+        }
+        while let Some(key) = self.held_keys.pop() {
+            if let Err(e) = self.enigo.key(key, Direction::Release) {
+                result = Err(anyhow!("failed to release {:?}: {}", key, e));
+            }
+        }
+        result
+    }
+
+    fn press_key(&mut self, key: Key) -> Result<()> {
+        self.enigo.key(key, Direction::Press)?;
+        if !self.held_keys.contains(&key) {
+            self.held_keys.push(key);
+        }
+        Ok(())
+    }
+
+    fn release_key(&mut self, key: Key) -> Result<()> {
+        self.held_keys.retain(|held| *held != key);
+        self.enigo.key(key, Direction::Release)?;
+        Ok(())
+    }
+
+    /// Lets `ScrollUntil` look at the screen, without a probe it fails.
+    pub fn set_screen_probe(&mut self, probe: Box<dyn ScreenProbe>) {
+        self.screen_probe = Some(probe);
+    }
+
+    pub fn has_screen_probe(&self) -> bool {
+        self.screen_probe.is_some()
+    }
+
+    /// Runs the steps in order, stops at the first failure. Held inputs are released when it stops
+    /// early, and at the end if the plan asks for it.
+    pub fn run_plan(&mut self, plan: &ActionPlan) -> PlanReport {
+        let mut report = PlanReport::default();
+        let mut aborted = false;
+        for step in &plan.steps {
+            let started = Instant::now();
+            if aborted {
+                report.push(&step.action, StepOutcome::Skipped, started);
+                continue;
+            }
+            let outcome = self.run_step(step);
+            aborted = outcome != StepOutcome::Done;
+            report.push(&step.action, outcome, started);
+        }
+        if aborted || plan.release_at_end {
+            if let Err(e) = self.release_all() {
+                log::warn!("{}", e);
+                report.release_error = Some(e.to_string());
+            }
+        }
+        report
+    }
+
+    fn run_step(&mut self, step: &PlanStep) -> StepOutcome {
+        let verify = match (self.verify, &step.expect) {
+            (Some(config), None) => watched_point(&step.action).map(|point| (config, point)),
+            _ => None,
+        };
+        if let Some((config, point)) = verify {
+            return match self.cancel_token.sleep(step.delay).and_then(|_| self.run_verified(&step.action, point, &config)) {
+                Ok(true) => StepOutcome::Done,
+                Ok(false) => StepOutcome::NoVisibleEffect,
+                Err(e) if e.is::<ActionCancelled>() => StepOutcome::Cancelled,
+                Err(e) => StepOutcome::Failed(e.to_string()),
+            };
+        }
+        let before = self.cancel_token.sleep(step.delay).and_then(|_| {
+            let before = match &step.expect {
+                Some(PostCondition::ScreenChanged { x, y }) => Some(self.probe_fingerprint((*x, *y))?),
+                _ => None,
+            };
+            self.handle_action(step.action.clone())?;
+            Ok(before)
+        });
+        let before = match before {
+            Ok(before) => before,
+            Err(e) if e.is::<ActionCancelled>() => return StepOutcome::Cancelled,
+            Err(e) => return StepOutcome::Failed(e.to_string()),
+        };
+        let Some(condition) = &step.expect else {
+            return StepOutcome::Done;
+        };
+        match self.wait_for(condition, before, step.expect_timeout) {
+            Ok(true) => StepOutcome::Done,
+            Ok(false) => StepOutcome::Unmet(format!("{:?} not met within {:?}", condition, step.expect_timeout)),
+            Err(e) if e.is::<ActionCancelled>() => StepOutcome::Cancelled,
+            Err(e) => StepOutcome::Failed(e.to_string()),
+        }
+    }
+
+    fn wait_for(&mut self, condition: &PostCondition, before: Option<u64>, timeout: Duration) -> Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let holds = match condition {
+                PostCondition::ScreenChanged { x, y } => {
+                    let now = self.probe_fingerprint((*x, *y))?;
+                    (now ^ before.unwrap_or(now)).count_ones() > self.scroll.stable_distance
+                }
+                PostCondition::Visible(target) => self.screen_probe.as_mut().ok_or_else(|| anyhow!("{:?} needs a screen probe", condition))?.locate(target)?.is_some(),
+                PostCondition::ClipboardContains(text) => self.clipboard.get_text()?.is_some_and(|t| t.contains(text.as_str())),
+            };
+            if holds {
+                return Ok(true);
+            }
+            if Instant::now() >= deadline {
+                return Ok(false);
+            }
+            self.cancel_token.sleep(EXPECT_POLL)?;
+        }
+    }
+
+    /// Clicks until the area around the point changes, nudging the click after each miss.
+    /// The cursor is moved there before the first capture so hover effects don't count as a reaction.
+    fn run_verified(&mut self, action: &InputAction, point: (i32, i32), config: &VerifyConfig) -> Result<bool> {
+        for attempt in 0..=config.retries {
+            let (dx, dy) = config.offset(attempt);
+            let (x, y) = (point.0 + dx, point.1 + dy);
+            self.move_to(x, y)?;
+            self.cancel_token.sleep(EXPECT_POLL)?;
+            let before = self.probe_region((x, y), config.radius)?;
+            self.handle_action(action.translate(dx, dy))?;
+            if self.wait_for_change(&before, config)? {
+                return Ok(true);
+            }
+            log::info!("{:?} at ({}, {}) had no visible effect", action, x, y);
+        }
+        Ok(false)
+    }
+
+    /// Polls until `config.min_changed` of the region looks different, `false` after `config.settle`.
+    fn wait_for_change(&mut self, before: &RegionCapture, config: &VerifyConfig) -> Result<bool> {
+        let deadline = Instant::now() + config.settle;
+        loop {
+            self.cancel_token.sleep(EXPECT_POLL)?;
+            let changed = self.screen_probe.as_mut().ok_or_else(|| anyhow!("verifying clicks needs a screen probe"))?.changed_since(before, config.pixel_threshold)?;
+            if changed >= config.min_changed {
+                return Ok(true);
+            }
+            if Instant::now() >= deadline {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn probe_region(&mut self, point: (i32, i32), radius: u32) -> Result<RegionCapture> {
+        self.screen_probe.as_mut().ok_or_else(|| anyhow!("verifying clicks needs a screen probe"))?.capture_region(point, radius)
+    }
+
+    fn probe_fingerprint(&mut self, point: (i32, i32)) -> Result<u64> {
+        self.screen_probe.as_mut().ok_or_else(|| anyhow!("checking the screen needs a screen probe"))?.fingerprint(point)
+    }
+
+    /// Where the screen probe sees `target` now.
+    pub fn locate(&mut self, target: &ScrollTarget) -> Result<Option<(i32, i32)>> {
+        self.screen_probe.as_mut().ok_or_else(|| anyhow!("locating {:?} needs a screen probe", target))?.locate(target)
+    }
+
+    /// Where the last `ScrollUntil` found its target.
+    pub fn take_located(&mut self) -> Option<(i32, i32)> {
+        self.located.take()
+    }
+
+    /// The text read by the last `GetClipboard`, empty when the clipboard held no text.
+    pub fn take_clipboard_text(&mut self) -> Option<String> {
+        self.clipboard_text.take()
+    }
+
+    /// Moves along the motion profile's path, teleports when it has no steps or the cursor position is unknown.
+    fn move_to(&mut self, x: i32, y: i32) -> Result<()> {
+        let from = match self.enigo.location() {
+            Ok(from) if self.motion.steps > 0 => from,
+            _ => return self.move_mouse(x, y),
+        };
+        for (px, py) in self.motion.path(from, (x, y)) {
+            self.move_mouse(px, py)?;
+            self.cancel_token.sleep(self.motion.step_delay)?;
+        }
+        Ok(())
+    }
+
+    /// One injected move, marked as ours only while it is sent so the corner failsafe stays armed in between.
+    fn move_mouse(&mut self, x: i32, y: i32) -> Result<()> {
+        let _injecting = self.cancel_token.injecting();
+        Ok(self.enigo.move_mouse(x, y, Coordinate::Abs)?)
+    }
+
+    fn type_text(&mut self, text: &str) -> Result<()> {
+        if self.typing.is_burst() {
+            self.enigo.text(text)?;
+            return Ok(());
+        }
+        let mut rng = rand::thread_rng();
+        let mut buffer = [0u8; 4];
+        for ch in text.chars() {
+            self.enigo.text(ch.encode_utf8(&mut buffer))?;
+            self.cancel_token.sleep(self.typing.next_delay(&mut rng))?;
+        }
+        Ok(())
+    }
+
+    fn scroll_notches(&mut self, notches: i32, axis: Axis) -> Result<()> {
+        let chunks = self.scroll.chunks(notches);
+        for (i, chunk) in chunks.iter().enumerate() {
+            if i > 0 {
+                self.cancel_token.sleep(self.scroll.chunk_delay)?;
+            }
+            self.enigo.scroll(*chunk, axis)?;
+        }
+        Ok(())
+    }
+
+    fn scroll_until(&mut self, probe: &mut dyn ScreenProbe, point: (i32, i32), step: i32, axis: Axis, until: &ScrollTarget, max_scrolls: u32) -> Result<()> {
+        let mut last = probe.fingerprint(point)?;
+        for _ in 0..max_scrolls {
+            if *until != ScrollTarget::Stable {
+                if let Some(found) = probe.locate(until)? {
+                    self.located = Some(found);
+                    return Ok(());
+                }
+            }
+            self.scroll_notches(self.scroll.to_notches(step, ScrollUnit::Notches), axis)?;
+            self.cancel_token.sleep(self.scroll.settle)?;
+            let current = probe.fingerprint(point)?;
+            if (last ^ current).count_ones() <= self.scroll.stable_distance {
+                return match until {
+                    ScrollTarget::Stable => Ok(()),
+                    _ => self.locate_or_fail(probe, until, "the view stopped moving"),
+                };
+            }
+            last = current;
+        }
+        match until {
+            ScrollTarget::Stable => {
+                log::warn!("still scrolling after {} scrolls", max_scrolls);
+                Ok(())
+            }
+            _ => self.locate_or_fail(probe, until, "gave up scrolling"),
+        }
     }
 
+    fn locate_or_fail(&mut self, probe: &mut dyn ScreenProbe, until: &ScrollTarget, reason: &str) -> Result<()> {
+        match probe.locate(until)? {
+            Some(found) => {
+                self.located = Some(found);
+                Ok(())
+            }
+            None => Err(anyhow!("{:?} not found, {}", until, reason)),
+        }
+    }
+
+    fn drag(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> Result<()> {
+        self.move_to(x1, y1)?;
+        self.enigo.button(Button::Left, Direction::Press)?;
+        // 添加延迟确保拖动操作可靠性
+        let moved = self.cancel_token.sleep(Duration::from_millis(50)).and_then(|_| self.move_to(x2, y2));
+        self.enigo.button(Button::Left, Direction::Release)?;
+        moved
+    }
+
+    /// `false` when the screen probe saw nothing change around the end point, without a probe the shift-click is trusted.
+    fn shift_click_select(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> Result<bool> {
+        let check = self.verify.unwrap_or_default();
+        self.move_to(x1, y1)?;
+        self.enigo.button(Button::Left, Direction::Click)?;
+        self.move_to(x2, y2)?;
+        self.cancel_token.sleep(EXPECT_POLL)?;
+        let before = match self.screen_probe {
+            Some(_) => self.probe_region((x2, y2), check.radius).map_err(|e| log::debug!("can't check the selection: {:#}", e)).ok(),
+            None => None,
+        };
+        // shift already held by `KeyDown` stays held
+        let press_shift = !self.held_keys.contains(&Key::Shift);
+        if press_shift {
+            self.enigo.key(Key::Shift, Direction::Press)?;
+        }
+        let clicked = self.enigo.button(Button::Left, Direction::Click);
+        if press_shift {
+            self.enigo.key(Key::Shift, Direction::Release)?;
+        }
+        clicked?;
+        let Some(before) = before else {
+            return Ok(true);
+        };
+        let selected = self.wait_for_change(&before, &check)?;
+        if !selected {
+            log::warn!("shift-click selected nothing at ({}, {}), dragging instead", x2, y2);
+        }
+        Ok(selected)
+    }
+
+    /// Pastes `text` and puts the previous clipboard text or image back. Other content can't be saved,
+    /// so rather than lose it the text is typed.
+    fn paste_text(&mut self, text: &str) -> Result<()> {
+        // `None` when the clipboard was empty or held something we can't keep, cleared afterwards
+        let previous = self.clipboard.save()?;
+        self.clipboard.set_text(text)?;
+        let modifier = command_key();
+        // give the clipboard owner a moment, then let the target read it before restoring
+        let pasted = self.cancel_token.sleep(Duration::from_millis(50)).and_then(|_| {
+            self.enigo.key(modifier, Direction::Press)?;
+            let clicked = self.enigo.key(Key::Unicode('v'), Direction::Click);
+            self.enigo.key(modifier, Direction::Release)?;
+            clicked?;
+            self.cancel_token.sleep(Duration::from_millis(150))
+        });
+        match previous {
+            Some(previous) => self.clipboard.restore(previous)?,
+            None => self.clipboard.clear()?,
+        }
+        pasted
+    }
+
+    /// Fails with `ActionCancelled` when the token is cancelled before or during the action.
     pub fn handle_action(&mut self, action: InputAction) -> Result<()> {
+        self.cancel_token.check()?;
         match action {
             InputAction::KeyClick(key) => {
                 self.enigo.key(key, Direction::Click)?;
@@ -139,43 +663,74 @@ impl ActionControl {
             InputAction::WriteText(text) => {
                 let stripped = text.trim_end_matches("\\n").trim_end_matches('\n');
                 if !stripped.is_empty() {
-                    self.enigo.text(&stripped)?;
+                    if self.text_entry.uses_paste(stripped) {
+                        self.paste_text(stripped)?;
+                    } else {
+                        self.type_text(stripped)?;
+                    }
                 }
                 if text.ends_with("\\n") || text.ends_with('\n') {
                     self.enigo.key(Key::Return, Direction::Click)?;
                 }
             }
+            InputAction::SetClipboard(text) => {
+                self.clipboard.set_text(&text)?;
+            }
+            InputAction::GetClipboard => {
+                self.clipboard_text = Some(self.clipboard.get_text()?.unwrap_or_default());
+            }
             InputAction::MouseMove { x, y } => {
-                self.enigo.move_mouse(x, y, Coordinate::Abs)?;
+                self.move_to(x, y)?;
             }
             InputAction::MouseLeftClick { x, y } => {
-                self.enigo.move_mouse(x, y, Coordinate::Abs)?;
+                self.move_to(x, y)?;
                 self.enigo.button(Button::Left, Direction::Click)?;
             }
             InputAction::MouseLeftDoubleClick { x, y } => {
-                self.enigo.move_mouse(x, y, Coordinate::Abs)?;
+                self.move_to(x, y)?;
                 self.enigo.button(Button::Left, Direction::Click)?;
                 self.enigo.button(Button::Left, Direction::Click)?;
             }
             InputAction::MouseRightClick { x, y } => {
-                self.enigo.move_mouse(x, y, Coordinate::Abs)?;
+                self.move_to(x, y)?;
                 self.enigo.button(Button::Right, Direction::Click)?;
             }
             InputAction::MouseMiddleClick { x, y } => {
-                self.enigo.move_mouse(x, y, Coordinate::Abs)?;
+                self.move_to(x, y)?;
                 self.enigo.button(Button::Middle, Direction::Click)?;
             }
-            InputAction::Drag { x1, y1, x2, y2 } | InputAction::Select { x1, y1, x2, y2 } => {
-                self.enigo.move_mouse(x1, y1, Coordinate::Abs)?;
-                self.enigo.button(Button::Left, Direction::Press)?;
-                // 添加延迟确保拖动操作可靠性
-                sleep(Duration::from_millis(50));
-                self.enigo.move_mouse(x2, y2, Coordinate::Abs)?;
-                self.enigo.button(Button::Left, Direction::Release)?;
+            InputAction::Drag { x1, y1, x2, y2 } => {
+                self.drag(x1, y1, x2, y2)?;
             }
-            InputAction::Scroll { x, y, length, direction } => {
-                self.enigo.move_mouse(x, y, Coordinate::Abs)?;
-                self.enigo.scroll(length, direction)?;
+            InputAction::Select { x1, y1, x2, y2 } => {
+                let selected = self.select_mode == SelectMode::ShiftClick && self.shift_click_select(x1, y1, x2, y2)?;
+                if !selected {
+                    self.drag(x1, y1, x2, y2)?;
+                }
+            }
+            InputAction::TripleClick { x, y } => {
+                self.move_to(x, y)?;
+                for _ in 0..3 {
+                    self.enigo.button(Button::Left, Direction::Click)?;
+                }
+            }
+            InputAction::SelectAll => {
+                let modifier = command_key();
+                self.enigo.key(modifier, Direction::Press)?;
+                let clicked = self.enigo.key(Key::Unicode('a'), Direction::Click);
+                self.enigo.key(modifier, Direction::Release)?;
+                clicked?;
+            }
+            InputAction::Scroll { x, y, length, direction, unit } => {
+                self.move_to(x, y)?;
+                self.scroll_notches(self.scroll.to_notches(length, unit), direction)?;
+            }
+            InputAction::ScrollUntil { x, y, step, direction, until, max_scrolls } => {
+                self.move_to(x, y)?;
+                let mut probe = self.screen_probe.take().ok_or_else(|| anyhow!("scroll_until needs a screen probe"))?;
+                let result = self.scroll_until(probe.as_mut(), (x, y), step, direction, &until, max_scrolls);
+                self.screen_probe = Some(probe);
+                result?;
             }
             InputAction::Hotkey { hot_keys } => {
                 for key in &hot_keys {
@@ -185,10 +740,54 @@ impl ActionControl {
                     self.enigo.key(*key, Direction::Release)?;
                 }
             }
+            InputAction::KeyDown(key) => {
+                self.press_key(key)?;
+            }
+            InputAction::KeyUp(key) => {
+                self.release_key(key)?;
+            }
+            InputAction::KeyHold { key, ms } => {
+                self.press_key(key)?;
+                let held = self.cancel_token.sleep(Duration::from_millis(ms));
+                self.release_key(key)?;
+                held?;
+            }
+            InputAction::ButtonDown { x, y, button } => {
+                self.move_to(x, y)?;
+                self.enigo.button(button, Direction::Press)?;
+                if !self.held_buttons.contains(&button) {
+                    self.held_buttons.push(button);
+                }
+            }
+            InputAction::ButtonUp(button) => {
+                self.held_buttons.retain(|held| *held != button);
+                self.enigo.button(button, Direction::Release)?;
+            }
+            InputAction::Click { x, y, button, modifiers, count } => {
+                self.move_to(x, y)?;
+                // modifiers already held by `KeyDown` stay held afterwards
+                let pressed: Vec<Key> = modifiers.into_iter().filter(|key| !self.held_keys.contains(key)).collect();
+                for key in &pressed {
+                    self.press_key(*key)?;
+                }
+                let clicked = (0..count.max(1)).try_for_each(|_| self.enigo.button(button, Direction::Click));
+                for key in pressed.iter().rev() {
+                    self.release_key(*key)?;
+                }
+                clicked?;
+            }
             InputAction::Wait { milliseconds } => {
-                sleep(Duration::from_millis(milliseconds));
+                self.cancel_token.sleep(Duration::from_millis(milliseconds))?;
             }
         };
         Ok(())
     }
 }
+
+impl Drop for ActionControl {
+    fn drop(&mut self) {
+        if let Err(e) = self.release_all() {
+            log::warn!("{}", e);
+        }
+    }
+}
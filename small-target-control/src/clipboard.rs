@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Text longer than this is pasted in `Auto` mode, typing it key by key is slow.
+pub const PASTE_MIN_LEN: usize = 200;
+
+/// How `WriteText` enters text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TextEntry {
+    /// `enigo.text`, unreliable for emoji, CJK and IME driven input
+    Keystrokes,
+    /// put the text on the clipboard, paste it, then restore the previous clipboard. A clipboard holding
+    /// neither text nor an image is cleared afterwards.
+    Paste,
+    /// paste non-ASCII and long text, type the rest
+    #[default]
+    Auto,
+}
+
+impl TextEntry {
+    pub fn uses_paste(&self, text: &str) -> bool {
+        match self {
+            TextEntry::Keystrokes => false,
+            TextEntry::Paste => true,
+            TextEntry::Auto => !text.is_ascii() || text.chars().count() > PASTE_MIN_LEN,
+        }
+    }
+}
+
+/// What `Clipboard::save` can put back.
+pub enum ClipboardContent {
+    Text(String),
+    Image(arboard::ImageData<'static>),
+}
+
+/// System clipboard, opened on first use so headless setups without one still work for other actions.
+/// The handle is kept open: on X11 the content is served by its owner and vanishes when it is dropped.
+#[derive(Default)]
+pub struct Clipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    fn open(&mut self) -> Result<&mut arboard::Clipboard> {
+        if self.inner.is_none() {
+            self.inner = Some(arboard::Clipboard::new().map_err(|e| anyhow!("failed to open the clipboard: {}", e))?);
+        }
+        Ok(self.inner.as_mut().unwrap())
+    }
+
+    /// `None` when the clipboard is empty or holds something other than text.
+    pub fn get_text(&mut self) -> Result<Option<String>> {
+        match self.open()?.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(anyhow!("failed to read the clipboard: {}", e)),
+        }
+    }
+
+    pub fn set_text(&mut self, text: &str) -> Result<()> {
+        self.open()?.set_text(text).map_err(|e| anyhow!("failed to write the clipboard: {}", e))
+    }
+
+    /// The text or image on the clipboard, `None` when it is empty or holds another format, e.g. copied files.
+    pub fn save(&mut self) -> Result<Option<ClipboardContent>> {
+        if let Some(text) = self.get_text()? {
+            return Ok(Some(ClipboardContent::Text(text)));
+        }
+        match self.open()?.get_image() {
+            Ok(image) => Ok(Some(ClipboardContent::Image(image))),
+            Err(arboard::Error::ContentNotAvailable | arboard::Error::ConversionFailure) => Ok(None),
+            Err(e) => Err(anyhow!("failed to read the clipboard: {}", e)),
+        }
+    }
+
+    pub fn restore(&mut self, content: ClipboardContent) -> Result<()> {
+        match content {
+            ClipboardContent::Text(text) => self.set_text(&text),
+            ClipboardContent::Image(image) => self.open()?.set_image(image).map_err(|e| anyhow!("failed to write the clipboard: {}", e)),
+        }
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.open()?.clear().map_err(|e| anyhow!("failed to clear the clipboard: {}", e))
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use rdev::{listen, Event, EventType};
+
+pub use rdev::Key as ListenKey;
+
+/// how often cancellable sleeps look at the token
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// hooked events arrive a little after they were injected, moves this soon after injecting are still ours
+const INJECT_GRACE: Duration = Duration::from_millis(150);
+
+/// Returned (inside `anyhow::Error`) by actions interrupted through a `CancelToken`.
+#[derive(Debug)]
+pub struct ActionCancelled;
+
+impl fmt::Display for ActionCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "action cancelled by kill switch")
+    }
+}
+
+impl Error for ActionCancelled {}
+
+/// Shared stop flag between the kill switch, `ActionControl` and the agent loop. It also tells
+/// the kill switch when `ActionControl` is moving the mouse itself, see `injecting`.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    injecting: Arc<Mutex<Injecting>>,
+}
+
+#[derive(Debug, Default)]
+struct Injecting {
+    active: usize,
+    last_end: Option<Instant>,
+}
+
+/// Marks input as injected until dropped, nests.
+#[derive(Debug)]
+pub struct InjectGuard {
+    injecting: Arc<Mutex<Injecting>>,
+}
+
+impl Drop for InjectGuard {
+    fn drop(&mut self) {
+        let mut injecting = self.injecting.lock().unwrap();
+        injecting.active -= 1;
+        injecting.last_end = Some(Instant::now());
+    }
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// re-arm the token before starting a new run
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(ActionCancelled.into());
+        }
+        Ok(())
+    }
+
+    /// Blocking sleep that wakes up early on cancel, `Err(ActionCancelled)` if it did.
+    pub fn sleep(&self, duration: Duration) -> Result<()> {
+        let deadline = Instant::now() + duration;
+        loop {
+            self.check()?;
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(());
+            }
+            sleep(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// Held while sending a mouse move, so the corner failsafe doesn't take our own moves for the user's.
+    /// Keep it short: the failsafe is off while any guard is alive.
+    pub fn injecting(&self) -> InjectGuard {
+        self.injecting.lock().unwrap().active += 1;
+        InjectGuard {
+            injecting: self.injecting.clone(),
+        }
+    }
+
+    /// true while an `injecting` guard is alive and for `INJECT_GRACE` after the last one dropped
+    pub fn is_injecting(&self) -> bool {
+        let injecting = self.injecting.lock().unwrap();
+        injecting.active > 0 || injecting.last_end.is_some_and(|end| end.elapsed() < INJECT_GRACE)
+    }
+
+    /// Resolves once the token is cancelled, for `tokio::select!` in async code.
+    pub async fn cancelled(&self) {
+        while !self.is_cancelled() {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KillSwitchConfig {
+    /// all keys held together trigger the stop, empty disables the hotkey
+    pub hotkey: Vec<ListenKey>,
+    /// stop when the user slams the cursor into a screen corner, like pyautogui's failsafe;
+    /// moves made by `ActionControl` don't count
+    pub corner_failsafe: bool,
+    /// distance in pixels from the corner that still counts as the corner
+    pub corner_margin: f64,
+}
+
+impl Default for KillSwitchConfig {
+    fn default() -> Self {
+        Self {
+            hotkey: vec![ListenKey::ControlLeft, ListenKey::ShiftLeft, ListenKey::Escape],
+            corner_failsafe: true,
+            corner_margin: 2.0,
+        }
+    }
+}
+
+/// Tracks pressed keys and reports when the whole hotkey is down.
+#[derive(Debug, Default)]
+pub struct HotkeyMatcher {
+    hotkey: Vec<ListenKey>,
+    pressed: HashSet<ListenKey>,
+}
+
+impl HotkeyMatcher {
+    pub fn new(hotkey: Vec<ListenKey>) -> Self {
+        Self { hotkey, pressed: HashSet::new() }
+    }
+
+    /// feed an event, returns true when it completes the hotkey
+    pub fn on_event(&mut self, event_type: &EventType) -> bool {
+        match event_type {
+            EventType::KeyPress(key) => {
+                self.pressed.insert(*key);
+                !self.hotkey.is_empty() && self.hotkey.iter().all(|k| self.pressed.contains(k))
+            }
+            EventType::KeyRelease(key) => {
+                self.pressed.remove(key);
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn is_in_corner(x: f64, y: f64, screen: (u64, u64), margin: f64) -> bool {
+    let (width, height) = (screen.0 as f64, screen.1 as f64);
+    let near_x = x <= margin || x >= width - 1.0 - margin;
+    let near_y = y <= margin || y >= height - 1.0 - margin;
+    near_x && near_y
+}
+
+type Subscriber = Box<dyn FnMut(&Event) + Send>;
+
+static SUBSCRIBERS: Mutex<Vec<(u64, Subscriber)>> = Mutex::new(Vec::new());
+static NEXT_SUBSCRIBER: AtomicU64 = AtomicU64::new(0);
+static LISTEN_ERROR: Mutex<Option<String>> = Mutex::new(None);
+static LISTENER: Once = Once::new();
+
+/// Calls `callback` with every global input event until the subscription is dropped.
+/// `rdev` keeps a single process wide callback and its hook can't be removed, so one listener
+/// thread is started on first use and fans events out to every subscriber. Callbacks run on
+/// that thread and must not subscribe or unsubscribe themselves.
+pub fn subscribe(callback: impl FnMut(&Event) + Send + 'static) -> Result<Subscription> {
+    LISTENER.call_once(|| {
+        std::thread::spawn(|| {
+            let result = listen(|event: Event| {
+                for (_, subscriber) in SUBSCRIBERS.lock().unwrap().iter_mut() {
+                    subscriber(&event);
+                }
+            });
+            if let Err(e) = result {
+                log::error!("input listener failed: {:?}", e);
+                *LISTEN_ERROR.lock().unwrap() = Some(format!("{:?}", e));
+            }
+        });
+    });
+    if let Some(e) = LISTEN_ERROR.lock().unwrap().as_ref() {
+        return Err(anyhow!("input listener failed: {}", e));
+    }
+    let id = NEXT_SUBSCRIBER.fetch_add(1, Ordering::SeqCst);
+    SUBSCRIBERS.lock().unwrap().push((id, Box::new(callback)));
+    Ok(Subscription { id })
+}
+
+/// Keeps a `subscribe` callback registered, dropping it unsubscribes.
+#[derive(Debug)]
+pub struct Subscription {
+    id: u64,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        SUBSCRIBERS.lock().unwrap().retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Global input listener that cancels the token on the hotkey or the corner gesture.
+/// `disarm` makes it ignore events for a while, dropping it unsubscribes.
+pub struct KillSwitch {
+    token: CancelToken,
+    armed: Arc<AtomicBool>,
+    _subscription: Subscription,
+}
+
+impl KillSwitch {
+    pub fn start(config: KillSwitchConfig, token: CancelToken) -> Result<Self> {
+        let screen = if config.corner_failsafe {
+            Some(rdev::display_size().map_err(|e| anyhow!("failed to get display size: {:?}", e))?)
+        } else {
+            None
+        };
+        let armed = Arc::new(AtomicBool::new(true));
+
+        let listen_token = token.clone();
+        let listen_armed = armed.clone();
+        let mut matcher = HotkeyMatcher::new(config.hotkey);
+        let subscription = subscribe(move |event: &Event| {
+            if !listen_armed.load(Ordering::SeqCst) || listen_token.is_cancelled() {
+                return;
+            }
+            let corner_hit = match (event.event_type, screen) {
+                (EventType::MouseMove { x, y }, Some(screen)) => !listen_token.is_injecting() && is_in_corner(x, y, screen, config.corner_margin),
+                _ => false,
+            };
+            if corner_hit || matcher.on_event(&event.event_type) {
+                log::warn!("kill switch triggered by {:?}", event.event_type);
+                listen_token.cancel();
+            }
+        })?;
+
+        Ok(Self {
+            token,
+            armed,
+            _subscription: subscription,
+        })
+    }
+
+    pub fn token(&self) -> CancelToken {
+        self.token.clone()
+    }
+
+    pub fn disarm(&self) {
+        self.armed.store(false, Ordering::SeqCst);
+    }
+
+    pub fn arm(&self) {
+        self.armed.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for KillSwitch {
+    fn drop(&mut self) {
+        self.disarm();
+    }
+}
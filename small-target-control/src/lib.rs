@@ -1,5 +1,39 @@
 pub mod action;
-pub use action::{ActionControl, InputAction};
+pub use action::{ActionControl, InputAction, SelectMode};
+
+// the schema lives in its own crate so the model side can parse and prompt from it too
+pub use action_schema::{action_space_prompt, lookup_action, ActionKind, ActionSpec, ACTION_SCHEMA};
+pub use small_target_schema::action_schema;
+
+pub mod clipboard;
+pub use clipboard::{Clipboard, ClipboardContent, TextEntry};
+
+pub mod motion;
+pub use motion::{Easing, MotionProfile, TypingCadence};
+
+pub mod playback;
+pub use playback::{substitute, Anchor, Macro, MacroStep, PlaybackOptions};
+
+pub mod plan;
+pub use plan::{ActionPlan, PlanReport, PlanStep, PostCondition, StepOutcome, StepResult};
+
+pub mod recorder;
+pub use recorder::{coalesce, CoalesceConfig, RawEvent, RecordedAction, Recorder, RecorderConfig, Recording, ScreenshotHook};
+
+pub mod scroll;
+pub use scroll::{RegionCapture, ScreenProbe, ScrollSettings, ScrollTarget, ScrollUnit};
+
+pub mod validate;
+pub use validate::{correction_prompt, parse_error_prompt, ActionValidator, ScreenRect, ValidationLimits, Violation};
+
+pub mod verify;
+pub use verify::{watched_point, VerifyConfig};
 
 pub mod key_parser;
 pub use key_parser::parse_key_from_str;
+
+pub mod policy;
+pub use policy::{ActionContext, ActionPolicy, Approver, PolicyGate};
+
+pub mod kill_switch;
+pub use kill_switch::{subscribe, ActionCancelled, CancelToken, InjectGuard, KillSwitch, KillSwitchConfig, Subscription};
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    Linear,
+    /// slow start and end, fast in the middle
+    EaseInOut,
+}
+
+impl Easing {
+    fn apply(&self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// How the cursor travels to a target. Zero `steps` teleports it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MotionProfile {
+    pub easing: Easing,
+    /// intermediate points, each one a separate mousemove event
+    pub steps: u32,
+    pub step_delay: Duration,
+}
+
+impl Default for MotionProfile {
+    fn default() -> Self {
+        Self::instant()
+    }
+}
+
+impl MotionProfile {
+    pub fn instant() -> Self {
+        Self {
+            easing: Easing::Linear,
+            steps: 0,
+            step_delay: Duration::ZERO,
+        }
+    }
+
+    /// about 200 ms per move, enough for drag and hover handlers that need mousemove events
+    pub fn human() -> Self {
+        Self {
+            easing: Easing::EaseInOut,
+            steps: 24,
+            step_delay: Duration::from_millis(8),
+        }
+    }
+
+    /// Points after `from` up to and including `to`, without repeats.
+    pub fn path(&self, from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut points: Vec<(i32, i32)> = Vec::new();
+        for i in 1..=self.steps + 1 {
+            let t = self.easing.apply(i as f64 / (self.steps + 1) as f64);
+            let point = (
+                from.0 + ((to.0 - from.0) as f64 * t).round() as i32,
+                from.1 + ((to.1 - from.1) as f64 * t).round() as i32,
+            );
+            if points.last() != Some(&point) && point != from {
+                points.push(point);
+            }
+        }
+        if points.last() != Some(&to) {
+            points.push(to);
+        }
+        points
+    }
+}
+
+/// Delay between typed characters. All zero types the text in one burst.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct TypingCadence {
+    pub delay: Duration,
+    /// up to this much is added or taken off each delay at random
+    pub jitter: Duration,
+}
+
+impl TypingCadence {
+    pub fn human() -> Self {
+        Self {
+            delay: Duration::from_millis(60),
+            jitter: Duration::from_millis(30),
+        }
+    }
+
+    pub fn is_burst(&self) -> bool {
+        self.delay.is_zero() && self.jitter.is_zero()
+    }
+
+    pub fn next_delay(&self, rng: &mut impl Rng) -> Duration {
+        if self.jitter.is_zero() {
+            return self.delay;
+        }
+        let jitter = self.jitter.as_micros() as i64;
+        let micros = self.delay.as_micros() as i64 + rng.gen_range(-jitter..=jitter);
+        Duration::from_micros(micros.max(0) as u64)
+    }
+}
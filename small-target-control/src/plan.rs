@@ -0,0 +1,178 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::action::InputAction;
+use crate::scroll::ScrollTarget;
+
+/// Checked after an action, the plan aborts when it doesn't hold within the step's timeout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PostCondition {
+    /// the screen around the point differs from before the action, needs a screen probe
+    ScreenChanged { x: i32, y: i32 },
+    /// the target is on screen, needs a screen probe
+    Visible(ScrollTarget),
+    /// the clipboard text contains this
+    ClipboardContains(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub action: InputAction,
+    /// waited before the action
+    #[serde(default)]
+    pub delay: Duration,
+    #[serde(default)]
+    pub expect: Option<PostCondition>,
+    /// how long `expect` may take to hold
+    #[serde(default = "default_expect_timeout")]
+    pub expect_timeout: Duration,
+}
+
+fn default_expect_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+impl PlanStep {
+    pub fn new(action: InputAction) -> Self {
+        Self {
+            action,
+            delay: Duration::ZERO,
+            expect: None,
+            expect_timeout: default_expect_timeout(),
+        }
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn expect(mut self, condition: PostCondition, timeout: Duration) -> Self {
+        self.expect = Some(condition);
+        self.expect_timeout = timeout;
+        self
+    }
+}
+
+/// Actions run in order by `ActionControl::run_plan`. The first failure skips the rest
+/// and releases every held key and button.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionPlan {
+    pub steps: Vec<PlanStep>,
+    /// also release held inputs when every step succeeded, off to keep a `KeyDown` for a later plan
+    pub release_at_end: bool,
+}
+
+impl Default for ActionPlan {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActionPlan {
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            release_at_end: true,
+        }
+    }
+
+    /// `delay` before every action but the first
+    pub fn from_actions(actions: impl IntoIterator<Item = InputAction>, delay: Duration) -> Self {
+        let mut plan = Self::new();
+        for (i, action) in actions.into_iter().enumerate() {
+            let step = PlanStep::new(action);
+            plan.steps.push(if i == 0 { step } else { step.with_delay(delay) });
+        }
+        plan
+    }
+
+    pub fn then(self, action: InputAction) -> Self {
+        self.then_step(PlanStep::new(action))
+    }
+
+    pub fn then_step(mut self, step: PlanStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    Done,
+    /// the action returned an error, or its post-condition couldn't be checked, e.g. without a screen probe
+    Failed(String),
+    /// the action ran but its post-condition didn't hold before the timeout
+    Unmet(String),
+    /// a verified click left the screen around it unchanged, after every retry
+    NoVisibleEffect,
+    Cancelled,
+    /// not run because an earlier step failed or was cancelled
+    Skipped,
+}
+
+impl fmt::Display for StepOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepOutcome::Done => write!(f, "done"),
+            StepOutcome::Failed(e) => write!(f, "failed: {}", e),
+            StepOutcome::Unmet(e) => write!(f, "had no effect: {}", e),
+            StepOutcome::NoVisibleEffect => write!(f, "had no visible effect"),
+            StepOutcome::Cancelled => write!(f, "cancelled"),
+            StepOutcome::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub action: InputAction,
+    pub outcome: StepOutcome,
+    /// delay, action and post-condition together
+    pub elapsed: Duration,
+}
+
+/// One result per plan step, in order.
+#[derive(Debug, Clone, Default)]
+pub struct PlanReport {
+    pub results: Vec<StepResult>,
+    /// set when releasing held inputs after the plan failed
+    pub release_error: Option<String>,
+}
+
+impl PlanReport {
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|r| r.outcome == StepOutcome::Done)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.results.iter().any(|r| r.outcome == StepOutcome::Cancelled)
+    }
+
+    /// the step that stopped the plan and its index
+    pub fn failure(&self) -> Option<(usize, &StepResult)> {
+        self.results.iter().enumerate().find(|(_, r)| !matches!(r.outcome, StepOutcome::Done | StepOutcome::Skipped))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.results.iter().filter(|r| r.outcome == StepOutcome::Skipped).count()
+    }
+
+    pub(crate) fn push(&mut self, action: &InputAction, outcome: StepOutcome, started: Instant) {
+        self.results.push(StepResult {
+            action: action.clone(),
+            outcome,
+            elapsed: started.elapsed(),
+        });
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActionControl, InputAction};
+use crate::plan::{ActionPlan, PlanReport, PlanStep, StepOutcome};
+use crate::recorder::Recording;
+use crate::scroll::ScrollTarget;
+
+/// Moves a step's points by how far a template moved since the macro was made.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Anchor {
+    /// image file, relative paths start at the macro file's directory
+    pub template: PathBuf,
+    /// the template's center when the macro was made
+    pub recorded_at: (i32, i32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MacroStep {
+    pub action: InputAction,
+    /// waited before the action, scaled by the playback speed
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<Anchor>,
+}
+
+/// A replayable list of actions. `{{name}}` in `WriteText` and `SetClipboard` is replaced by a parameter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Macro {
+    #[serde(default)]
+    pub name: String,
+    /// declared parameters and their defaults, `None` must be given when playing
+    #[serde(default)]
+    pub params: HashMap<String, Option<String>>,
+    pub steps: Vec<MacroStep>,
+}
+
+impl Macro {
+    /// `.ron` files are RON, anything else JSON. Relative anchor templates are resolved against the file's directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("failed to read macro {:?}", path))?;
+        let mut mac: Macro = if path.extension().is_some_and(|ext| ext == "ron") {
+            ron::from_str(&text).with_context(|| format!("invalid macro {:?}", path))?
+        } else {
+            serde_json::from_str(&text).with_context(|| format!("invalid macro {:?}", path))?
+        };
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for anchor in mac.steps.iter_mut().filter_map(|step| step.anchor.as_mut()) {
+            if anchor.template.is_relative() {
+                anchor.template = dir.join(&anchor.template);
+            }
+        }
+        Ok(mac)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = if path.extension().is_some_and(|ext| ext == "ron") {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        std::fs::write(path, text).with_context(|| format!("failed to write macro {:?}", path))
+    }
+
+    /// Keeps the recorded pauses, without anchors or parameters.
+    pub fn from_recording(name: &str, recording: &Recording) -> Self {
+        let plan = recording.to_plan();
+        Self {
+            name: name.to_string(),
+            params: HashMap::new(),
+            steps: plan
+                .steps
+                .into_iter()
+                .map(|step| MacroStep {
+                    action: step.action,
+                    delay_ms: step.delay.as_millis() as u64,
+                    anchor: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Declared defaults overridden by `given`, fails on missing or undeclared parameters.
+    pub fn resolve_params(&self, given: &HashMap<String, String>) -> Result<HashMap<String, String>> {
+        if let Some(unknown) = given.keys().find(|name| !self.params.contains_key(*name)) {
+            return Err(anyhow!("macro {:?} has no parameter {}", self.name, unknown));
+        }
+        let mut params = HashMap::new();
+        for (name, default) in &self.params {
+            let value = given.get(name).or(default.as_ref()).ok_or_else(|| anyhow!("macro {:?} needs parameter {}", self.name, name))?;
+            params.insert(name.clone(), value.clone());
+        }
+        // a placeholder nobody declared would be typed literally
+        for step in &self.steps {
+            if let Some(text) = step_text(&step.action) {
+                substitute(text, &params)?;
+            }
+        }
+        Ok(params)
+    }
+}
+
+fn step_text(action: &InputAction) -> Option<&str> {
+    match action {
+        InputAction::WriteText(text) | InputAction::SetClipboard(text) => Some(text),
+        _ => None,
+    }
+}
+
+/// Replaces every `{{name}}`, spaces inside the braces are ignored.
+pub fn substitute(text: &str, params: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find("}}").ok_or_else(|| anyhow!("unclosed {{{{ in {:?}", text))? + start;
+        let name = rest[start + 2..end].trim();
+        out.push_str(params.get(name).ok_or_else(|| anyhow!("unknown parameter {} in {:?}", name, text))?);
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaybackOptions {
+    pub params: HashMap<String, String>,
+    /// 2.0 plays twice as fast, only delays are scaled, not the actions
+    pub speed: f64,
+    pub loops: u32,
+    pub loop_delay: Duration,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            params: HashMap::new(),
+            speed: 1.0,
+            loops: 1,
+            loop_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl ActionControl {
+    /// Plays `mac` `loops` times, stops at the first failed step and releases held inputs at the end.
+    /// Anchored steps look for their template with the screen probe right before they run.
+    pub fn play_macro(&mut self, mac: &Macro, options: &PlaybackOptions) -> Result<PlanReport> {
+        if options.speed <= 0.0 {
+            return Err(anyhow!("invalid playback speed {}", options.speed));
+        }
+        let params = mac.resolve_params(&options.params)?;
+        let mut report = PlanReport::default();
+        'loops: for round in 0..options.loops {
+            for (index, step) in mac.steps.iter().enumerate() {
+                let mut delay = Duration::from_millis(step.delay_ms).div_f64(options.speed);
+                if round > 0 && index == 0 {
+                    delay += options.loop_delay;
+                }
+                let started = Instant::now();
+                let action = match self.prepare_step(step, &params) {
+                    Ok(action) => action,
+                    Err(e) => {
+                        report.push(&step.action, StepOutcome::Failed(e.to_string()), started);
+                        break 'loops;
+                    }
+                };
+                let mut plan = ActionPlan::new().then_step(PlanStep::new(action).with_delay(delay));
+                plan.release_at_end = false;
+                let step_report = self.run_plan(&plan);
+                report.results.extend(step_report.results);
+                if !report.is_success() {
+                    break 'loops;
+                }
+            }
+        }
+        if let Err(e) = self.release_all() {
+            report.release_error = Some(e.to_string());
+        }
+        Ok(report)
+    }
+
+    fn prepare_step(&mut self, step: &MacroStep, params: &HashMap<String, String>) -> Result<InputAction> {
+        let action = match &step.action {
+            InputAction::WriteText(text) => InputAction::WriteText(substitute(text, params)?),
+            InputAction::SetClipboard(text) => InputAction::SetClipboard(substitute(text, params)?),
+            other => other.clone(),
+        };
+        let Some(anchor) = &step.anchor else {
+            return Ok(action);
+        };
+        let (x, y) = self.locate(&ScrollTarget::Template(anchor.template.clone()))?.ok_or_else(|| anyhow!("anchor {:?} is not on screen", anchor.template))?;
+        Ok(action.translate(x - anchor.recorded_at.0, y - anchor.recorded_at.1))
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use enigo::Key;
+use regex::Regex;
+
+use crate::action::InputAction;
+use crate::key_parser::parse_key_from_str;
+
+/// Window under (or focused for) the action and the keys already held, supplied by the caller.
+#[derive(Debug, Clone, Default)]
+pub struct ActionContext {
+    pub app_name: Option<String>,
+    pub window_title: Option<String>,
+    /// pressed by earlier `KeyDown`s and not released yet, see `ActionControl::held_keys`
+    pub held_keys: Vec<Key>,
+    /// the window couldn't be read, every `ClickInWindow` rule matches so approval and deny rules fail closed
+    pub unknown_window: bool,
+}
+
+impl ActionContext {
+    pub fn new(app_name: &str, window_title: &str) -> Self {
+        Self {
+            app_name: Some(app_name.to_string()),
+            window_title: Some(window_title.to_string()),
+            held_keys: Vec::new(),
+            unknown_window: false,
+        }
+    }
+
+    pub fn unknown_window() -> Self {
+        Self {
+            unknown_window: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_held_keys(mut self, held_keys: &[Key]) -> Self {
+        self.held_keys = held_keys.to_vec();
+        self
+    }
+
+    /// Follows `KeyDown`/`KeyUp` of an action that will run before the next one is classified.
+    pub fn track(&mut self, action: &InputAction) {
+        match action {
+            InputAction::KeyDown(key) if !self.held_keys.contains(key) => self.held_keys.push(*key),
+            InputAction::KeyUp(key) => self.held_keys.retain(|held| held != key),
+            _ => {}
+        }
+    }
+}
+
+/// Match windows by app name or title substring, like `WindowFilters` but an empty include list matches nothing.
+#[derive(Debug, Clone)]
+pub struct WindowRule {
+    ignore_set: HashSet<String>,
+    include_set: HashSet<String>,
+}
+
+impl WindowRule {
+    pub fn new(ignore_list: &[String], include_list: &[String]) -> Self {
+        Self {
+            ignore_set: ignore_list.iter().map(|s| s.to_lowercase()).collect(),
+            include_set: include_list.iter().map(|s| s.to_lowercase()).collect(),
+        }
+    }
+
+    pub fn matches(&self, app_name: &str, title: &str) -> bool {
+        let app_name_lower = app_name.to_lowercase();
+        let title_lower = title.to_lowercase();
+        let hit = |set: &HashSet<String>| set.iter().any(|s| app_name_lower.contains(s) || title_lower.contains(s));
+
+        if hit(&self.ignore_set) {
+            return false;
+        }
+        hit(&self.include_set)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleVerdict {
+    RequireApproval,
+    Deny,
+}
+
+#[derive(Debug, Clone)]
+pub enum RiskMatcher {
+    /// `WriteText` content matching the regex
+    TextPattern(Regex),
+    /// all these keys down at once: a key action's own keys, a click's modifiers and the held keys together
+    Hotkey(Vec<Key>),
+    /// any mouse action while the context window matches the rule, or can't be read
+    ClickInWindow(WindowRule),
+}
+
+#[derive(Debug, Clone)]
+pub struct RiskRule {
+    pub name: String,
+    pub matcher: RiskMatcher,
+    pub verdict: RuleVerdict,
+}
+
+impl RiskRule {
+    pub fn text_pattern(name: &str, pattern: &str, verdict: RuleVerdict) -> Result<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            matcher: RiskMatcher::TextPattern(Regex::new(pattern)?),
+            verdict,
+        })
+    }
+
+    /// `hot_keys` uses the model syntax, e.g. `alt+f4`
+    pub fn hotkey(name: &str, hot_keys: &str, verdict: RuleVerdict) -> Self {
+        let keys = hot_keys.split('+').map(|k| parse_key_from_str(k.to_lowercase().trim())).collect();
+        Self {
+            name: name.to_string(),
+            matcher: RiskMatcher::Hotkey(keys),
+            verdict,
+        }
+    }
+
+    pub fn click_in_window(name: &str, rule: WindowRule, verdict: RuleVerdict) -> Self {
+        Self {
+            name: name.to_string(),
+            matcher: RiskMatcher::ClickInWindow(rule),
+            verdict,
+        }
+    }
+
+    fn matches(&self, action: &InputAction, context: &ActionContext) -> bool {
+        match (&self.matcher, action) {
+            (RiskMatcher::TextPattern(re), InputAction::WriteText(text) | InputAction::SetClipboard(text)) => re.is_match(text),
+            (RiskMatcher::Hotkey(keys), action) => match pressed_keys(action) {
+                Some(pressed) => all_down(keys, &pressed, &context.held_keys),
+                None => false,
+            },
+            (RiskMatcher::ClickInWindow(_), action) if is_pointer_action(action) && context.unknown_window => true,
+            (RiskMatcher::ClickInWindow(rule), action) if is_pointer_action(action) => rule.matches(
+                context.app_name.as_deref().unwrap_or_default(),
+                context.window_title.as_deref().unwrap_or_default(),
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Keys the action presses, `None` for actions that press none
+fn pressed_keys(action: &InputAction) -> Option<Vec<Key>> {
+    match action {
+        InputAction::Hotkey { hot_keys } => Some(hot_keys.clone()),
+        InputAction::KeyClick(key) | InputAction::KeyDown(key) | InputAction::KeyHold { key, .. } => Some(vec![*key]),
+        InputAction::Click { modifiers, .. } if !modifiers.is_empty() => Some(modifiers.clone()),
+        _ => None,
+    }
+}
+
+// keys `parse_key_from_str` can't resolve all become `Key::Other(0)`, never match on those
+fn all_down(expected: &[Key], pressed: &[Key], held: &[Key]) -> bool {
+    !expected.contains(&Key::Other(0)) && expected.iter().all(|k| pressed.contains(k) || held.contains(k))
+}
+
+fn is_pointer_action(action: &InputAction) -> bool {
+    matches!(
+        action,
+        InputAction::MouseLeftClick { .. }
+            | InputAction::MouseLeftDoubleClick { .. }
+            | InputAction::MouseRightClick { .. }
+            | InputAction::MouseMiddleClick { .. }
+            | InputAction::Drag { .. }
+            | InputAction::Select { .. }
+            | InputAction::TripleClick { .. }
+            | InputAction::Click { .. }
+            | InputAction::ButtonDown { .. }
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Allow,
+    RequireApproval { rule: String },
+    Deny { rule: String },
+}
+
+/// Classifies actions before they reach `ActionControl::handle_action`.
+/// Rules are checked in order, a `Deny` always wins over `RequireApproval`.
+#[derive(Debug, Clone, Default)]
+pub struct ActionPolicy {
+    pub rules: Vec<RiskRule>,
+}
+
+impl ActionPolicy {
+    pub fn new(rules: Vec<RiskRule>) -> Self {
+        Self { rules }
+    }
+
+    /// A conservative default: approval for window-closing hotkeys, clicks in terminals and obvious shell commands.
+    pub fn default_rules() -> Self {
+        let mut rules = vec![
+            RiskRule::hotkey("close window", "alt+f4", RuleVerdict::RequireApproval),
+            RiskRule::hotkey("secure attention", "control+alt+delete", RuleVerdict::RequireApproval),
+            RiskRule::click_in_window(
+                "terminal",
+                WindowRule::new(&[], &["terminal".to_string(), "powershell".to_string(), "cmd.exe".to_string(), "iterm".to_string()]),
+                RuleVerdict::RequireApproval,
+            ),
+        ];
+        if let Ok(rule) = RiskRule::text_pattern("destructive shell command", r"(?i)\b(rm\s+-rf|sudo|mkfs|shutdown|format\s+[a-z]:)", RuleVerdict::RequireApproval) {
+            rules.push(rule);
+        }
+        Self { rules }
+    }
+
+    pub fn classify(&self, action: &InputAction, context: &ActionContext) -> PolicyDecision {
+        let mut decision = PolicyDecision::Allow;
+        for rule in self.rules.iter().filter(|r| r.matches(action, context)) {
+            match rule.verdict {
+                RuleVerdict::Deny => return PolicyDecision::Deny { rule: rule.name.clone() },
+                RuleVerdict::RequireApproval if decision == PolicyDecision::Allow => {
+                    decision = PolicyDecision::RequireApproval { rule: rule.name.clone() };
+                }
+                RuleVerdict::RequireApproval => {}
+            }
+        }
+        decision
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApprovalRequest {
+    pub action: InputAction,
+    pub context: ActionContext,
+    pub rule: String,
+}
+
+/// Asks a human (or a stand-in) whether a risky action may run. May block until they answer,
+/// async callers go through `PolicyGate::authorize_async`.
+pub trait Approver: Send + Sync {
+    fn approve(&self, request: &ApprovalRequest) -> Result<bool>;
+}
+
+/// Always answers the same, `AutoApprover::deny()` is what tests want.
+pub struct AutoApprover {
+    approve: bool,
+}
+
+impl AutoApprover {
+    pub fn allow() -> Self {
+        Self { approve: true }
+    }
+
+    pub fn deny() -> Self {
+        Self { approve: false }
+    }
+}
+
+impl Approver for AutoApprover {
+    fn approve(&self, _request: &ApprovalRequest) -> Result<bool> {
+        Ok(self.approve)
+    }
+}
+
+/// Prompts on stdin/stderr, anything other than `y`/`yes` is a denial.
+pub struct CliApprover;
+
+impl Approver for CliApprover {
+    fn approve(&self, request: &ApprovalRequest) -> Result<bool> {
+        let mut stderr = std::io::stderr();
+        write!(stderr, "[{}] allow action {:?}? [y/N] ", request.rule, request.action)?;
+        stderr.flush()?;
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+}
+
+/// Forwards requests to another thread (e.g. a Tauri dialog) and blocks until it answers.
+pub struct ChannelApprover {
+    requests: Mutex<Sender<(ApprovalRequest, Sender<bool>)>>,
+}
+
+impl ChannelApprover {
+    pub fn new() -> (Self, Receiver<(ApprovalRequest, Sender<bool>)>) {
+        let (tx, rx) = channel();
+        (Self { requests: Mutex::new(tx) }, rx)
+    }
+}
+
+impl Approver for ChannelApprover {
+    fn approve(&self, request: &ApprovalRequest) -> Result<bool> {
+        let (answer_tx, answer_rx) = channel();
+        self.requests
+            .lock()
+            .map_err(|_| anyhow!("approval channel poisoned"))?
+            .send((request.clone(), answer_tx))
+            .map_err(|_| anyhow!("approval receiver dropped"))?;
+        answer_rx.recv().map_err(|_| anyhow!("approval dropped without an answer"))
+    }
+}
+
+/// Policy plus approver, sits between the parsed action and `handle_action`.
+pub struct PolicyGate {
+    pub policy: ActionPolicy,
+    approver: Arc<dyn Approver>,
+}
+
+enum GateDecision {
+    Decided(bool),
+    Ask(ApprovalRequest),
+}
+
+impl PolicyGate {
+    pub fn new(policy: ActionPolicy, approver: Box<dyn Approver>) -> Self {
+        Self {
+            policy,
+            approver: Arc::from(approver),
+        }
+    }
+
+    /// Returns whether the action may run, every decision is logged. Blocks while the approver waits.
+    pub fn authorize(&self, action: &InputAction, context: &ActionContext) -> Result<bool> {
+        match self.decide(action, context) {
+            GateDecision::Decided(allowed) => Ok(allowed),
+            GateDecision::Ask(request) => {
+                let approved = self.approver.approve(&request)?;
+                log_approval(&request, approved);
+                Ok(approved)
+            }
+        }
+    }
+
+    /// `authorize` for async code, the approver runs on the blocking pool so waiting for a human doesn't stall the runtime.
+    pub async fn authorize_async(&self, action: &InputAction, context: &ActionContext) -> Result<bool> {
+        match self.decide(action, context) {
+            GateDecision::Decided(allowed) => Ok(allowed),
+            GateDecision::Ask(request) => {
+                let approver = self.approver.clone();
+                let (request, approved) = tokio::task::spawn_blocking(move || {
+                    let approved = approver.approve(&request);
+                    (request, approved)
+                })
+                .await
+                .map_err(|e| anyhow!("approval task failed: {}", e))?;
+                let approved = approved?;
+                log_approval(&request, approved);
+                Ok(approved)
+            }
+        }
+    }
+
+    fn decide(&self, action: &InputAction, context: &ActionContext) -> GateDecision {
+        match self.policy.classify(action, context) {
+            PolicyDecision::Allow => {
+                log::debug!("policy allow: {:?}", action);
+                GateDecision::Decided(true)
+            }
+            PolicyDecision::Deny { rule } => {
+                log::warn!("policy deny by rule [{}]: {:?} in {:?}", rule, action, context);
+                GateDecision::Decided(false)
+            }
+            PolicyDecision::RequireApproval { rule } => GateDecision::Ask(ApprovalRequest {
+                action: action.clone(),
+                context: context.clone(),
+                rule,
+            }),
+        }
+    }
+}
+
+fn log_approval(request: &ApprovalRequest, approved: bool) {
+    log::info!(
+        "policy approval by rule [{}]: {} {:?} in {:?}",
+        request.rule,
+        if approved { "approved" } else { "rejected" },
+        request.action,
+        request.context
+    );
+}
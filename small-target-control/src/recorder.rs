@@ -0,0 +1,643 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use enigo::{Axis, Key};
+use rdev::{Button as ListenButton, Event, EventType};
+use serde::{Deserialize, Serialize};
+
+use crate::action::InputAction;
+use crate::kill_switch::{subscribe, HotkeyMatcher, ListenKey, Subscription};
+use crate::plan::{ActionPlan, PlanStep};
+use crate::scroll::ScrollUnit;
+
+/// An input event as the recorder saw it, `at` counts from the start of the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawEvent {
+    pub at: Duration,
+    pub event_type: EventType,
+    /// the text the key produces in the current layout
+    pub name: Option<String>,
+}
+
+/// How raw events are merged into actions.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CoalesceConfig {
+    /// a press and release further apart than this is a drag
+    pub drag_threshold: f64,
+    pub multi_click_interval: Duration,
+    /// a typing pause this long starts a new `WriteText`
+    pub text_gap: Duration,
+    /// the pointer resting this long becomes a `MouseMove`, for hover menus
+    pub hover_delay: Duration,
+    /// wheel events closer than this are one scroll
+    pub wheel_gap: Duration,
+}
+
+impl Default for CoalesceConfig {
+    fn default() -> Self {
+        Self {
+            drag_threshold: 5.0,
+            multi_click_interval: Duration::from_millis(400),
+            text_gap: Duration::from_secs(2),
+            hover_delay: Duration::from_millis(800),
+            wheel_gap: Duration::from_millis(300),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedAction {
+    pub action: InputAction,
+    /// when its first event happened
+    pub at: Duration,
+    /// until its last event, e.g. the end of a drag or of typed text
+    pub duration: Duration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<PathBuf>,
+}
+
+/// A demonstration, stored as JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub actions: Vec<RecordedAction>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("failed to read recording {:?}", path))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?).with_context(|| format!("failed to write recording {:?}", path))
+    }
+
+    pub fn actions(&self) -> Vec<InputAction> {
+        self.actions.iter().map(|a| a.action.clone()).collect()
+    }
+
+    /// Replays with the recorded pauses between actions.
+    pub fn to_plan(&self) -> ActionPlan {
+        let mut plan = ActionPlan::new();
+        let mut previous_end = None;
+        for recorded in &self.actions {
+            let delay = previous_end.map(|end| recorded.at.saturating_sub(end)).unwrap_or_default();
+            plan = plan.then_step(PlanStep::new(recorded.action.clone()).with_delay(delay));
+            previous_end = Some(recorded.at + recorded.duration);
+        }
+        plan
+    }
+}
+
+/// Merges raw events into actions: typed characters into `WriteText`, press and release into
+/// clicks, multi-clicks and drags, wheel events into one `Scroll`, and modifier chords into `Hotkey`.
+pub fn coalesce(events: &[RawEvent], config: &CoalesceConfig) -> Vec<RecordedAction> {
+    let mut coalescer = Coalescer::new(*config);
+    for event in events {
+        coalescer.feed(event);
+    }
+    coalescer.finish()
+}
+
+struct PendingText {
+    text: String,
+    start: Duration,
+    last: Duration,
+}
+
+struct PendingWheel {
+    point: (i32, i32),
+    axis: Axis,
+    length: i32,
+    start: Duration,
+    last: Duration,
+}
+
+struct Coalescer {
+    config: CoalesceConfig,
+    out: Vec<RecordedAction>,
+    position: (f64, f64),
+    /// when the pointer last moved without a button down
+    rested_since: Option<Duration>,
+    modifiers: Vec<Key>,
+    text: Option<PendingText>,
+    wheel: Option<PendingWheel>,
+    press: Option<(ListenButton, (f64, f64), Duration)>,
+    /// index in `out`, release time, point and count of the last plain left click
+    last_click: Option<(usize, Duration, (f64, f64), u32)>,
+}
+
+impl Coalescer {
+    fn new(config: CoalesceConfig) -> Self {
+        Self {
+            config,
+            out: Vec::new(),
+            position: (0.0, 0.0),
+            rested_since: None,
+            modifiers: Vec::new(),
+            text: None,
+            wheel: None,
+            press: None,
+            last_click: None,
+        }
+    }
+
+    fn point(position: (f64, f64)) -> (i32, i32) {
+        (position.0.round() as i32, position.1.round() as i32)
+    }
+
+    fn push(&mut self, action: InputAction, at: Duration, end: Duration) {
+        self.out.push(RecordedAction {
+            action,
+            at,
+            duration: end.saturating_sub(at),
+            screenshot: None,
+        });
+    }
+
+    fn feed(&mut self, event: &RawEvent) {
+        if let EventType::MouseMove { x, y } = event.event_type {
+            self.position = (x, y);
+            if self.press.is_none() {
+                self.rested_since = Some(event.at);
+            }
+            return;
+        }
+        if let Some(since) = self.rested_since.take() {
+            // a click or scroll right where the pointer stopped moves there anyway
+            let pressing = matches!(event.event_type, EventType::ButtonPress(_) | EventType::Wheel { .. });
+            if !pressing && event.at.saturating_sub(since) >= self.config.hover_delay {
+                self.flush_text();
+                self.flush_wheel();
+                let (x, y) = Self::point(self.position);
+                self.push(InputAction::MouseMove { x, y }, since, since);
+            }
+        }
+        match event.event_type {
+            EventType::ButtonPress(button) => {
+                self.flush_text();
+                self.flush_wheel();
+                self.press = Some((button, self.position, event.at));
+            }
+            EventType::ButtonRelease(button) => match self.press.take() {
+                Some((pressed, start, at)) if pressed == button => self.release(button, start, at, event.at),
+                other => self.press = other,
+            },
+            EventType::Wheel { delta_x, delta_y } => {
+                self.flush_text();
+                self.wheel(delta_x, delta_y, event.at);
+            }
+            EventType::KeyPress(key) => self.key_press(key, event.name.as_deref(), event.at),
+            EventType::KeyRelease(key) => {
+                if let Some(modifier) = modifier_key(key) {
+                    self.modifiers.retain(|m| *m != modifier);
+                }
+            }
+            EventType::MouseMove { .. } => {}
+        }
+    }
+
+    fn release(&mut self, button: ListenButton, start: (f64, f64), at: Duration, end: Duration) {
+        let (x1, y1) = Self::point(start);
+        let (x2, y2) = Self::point(self.position);
+        let distance = (self.position.0 - start.0).hypot(self.position.1 - start.1);
+        let Some(enigo_button) = to_enigo_button(button) else {
+            return;
+        };
+        if distance > self.config.drag_threshold {
+            if button == ListenButton::Left && self.modifiers.is_empty() {
+                self.push(InputAction::Drag { x1, y1, x2, y2 }, at, end);
+            } else {
+                self.push(InputAction::ButtonDown { x: x1, y: y1, button: enigo_button }, at, at);
+                self.push(InputAction::MouseMove { x: x2, y: y2 }, at, end);
+                self.push(InputAction::ButtonUp(enigo_button), end, end);
+            }
+            self.last_click = None;
+            return;
+        }
+        if !self.modifiers.is_empty() {
+            let action = InputAction::Click {
+                x: x1,
+                y: y1,
+                button: enigo_button,
+                modifiers: self.modifiers.clone(),
+                count: 1,
+            };
+            self.push(action, at, end);
+            self.last_click = None;
+            return;
+        }
+        if button != ListenButton::Left {
+            let action = if button == ListenButton::Right {
+                InputAction::MouseRightClick { x: x1, y: y1 }
+            } else {
+                InputAction::MouseMiddleClick { x: x1, y: y1 }
+            };
+            self.push(action, at, end);
+            self.last_click = None;
+            return;
+        }
+        if let Some((index, released, point, count)) = self.last_click {
+            let close = (point.0 - start.0).hypot(point.1 - start.1) <= self.config.drag_threshold;
+            if index + 1 == self.out.len() && close && at.saturating_sub(released) <= self.config.multi_click_interval && count < 3 {
+                let (x, y) = Self::point(point);
+                let previous = &mut self.out[index];
+                previous.action = if count == 1 {
+                    InputAction::MouseLeftDoubleClick { x, y }
+                } else {
+                    InputAction::TripleClick { x, y }
+                };
+                previous.duration = end.saturating_sub(previous.at);
+                self.last_click = Some((index, end, point, count + 1));
+                return;
+            }
+        }
+        self.push(InputAction::MouseLeftClick { x: x1, y: y1 }, at, end);
+        self.last_click = Some((self.out.len() - 1, end, start, 1));
+    }
+
+    fn wheel(&mut self, delta_x: i64, delta_y: i64, at: Duration) {
+        // rdev counts up and left as positive and negative, enigo scrolls down for positive lengths
+        let (axis, length) = if delta_y != 0 { (Axis::Vertical, -delta_y as i32) } else { (Axis::Horizontal, delta_x as i32) };
+        if length == 0 {
+            return;
+        }
+        let point = Self::point(self.position);
+        if let Some(wheel) = &mut self.wheel {
+            let same = wheel.point == point && wheel.axis == axis && wheel.length.signum() == length.signum();
+            if same && at.saturating_sub(wheel.last) <= self.config.wheel_gap {
+                wheel.length += length;
+                wheel.last = at;
+                return;
+            }
+        }
+        self.flush_wheel();
+        self.wheel = Some(PendingWheel {
+            point,
+            axis,
+            length,
+            start: at,
+            last: at,
+        });
+    }
+
+    fn key_press(&mut self, key: rdev::Key, name: Option<&str>, at: Duration) {
+        if let Some(modifier) = modifier_key(key) {
+            if !self.modifiers.contains(&modifier) {
+                self.modifiers.push(modifier);
+            }
+            return;
+        }
+        self.flush_wheel();
+        let chord = self.modifiers.iter().any(|m| *m != Key::Shift);
+        if chord {
+            self.flush_text();
+            if let Some(key) = to_enigo_key(key) {
+                let mut hot_keys = self.modifiers.clone();
+                hot_keys.push(key);
+                self.push(InputAction::Hotkey { hot_keys }, at, at);
+            }
+            return;
+        }
+        match key {
+            rdev::Key::Return | rdev::Key::KpReturn if self.text.is_some() => {
+                self.append_text("\n", at);
+                self.flush_text();
+                return;
+            }
+            rdev::Key::Backspace => {
+                if let Some(pending) = &mut self.text {
+                    if pending.text.pop().is_some() {
+                        pending.last = at;
+                        return;
+                    }
+                }
+            }
+            _ => {}
+        }
+        match name.filter(|name| !name.is_empty() && !name.chars().any(char::is_control)) {
+            Some(text) => self.append_text(text, at),
+            None => {
+                self.flush_text();
+                if let Some(key) = to_enigo_key(key) {
+                    self.push(InputAction::KeyClick(key), at, at);
+                }
+            }
+        }
+    }
+
+    fn append_text(&mut self, text: &str, at: Duration) {
+        if let Some(pending) = &self.text {
+            if at.saturating_sub(pending.last) > self.config.text_gap {
+                self.flush_text();
+            }
+        }
+        let pending = self.text.get_or_insert(PendingText {
+            text: String::new(),
+            start: at,
+            last: at,
+        });
+        pending.text.push_str(text);
+        pending.last = at;
+    }
+
+    fn flush_text(&mut self) {
+        if let Some(pending) = self.text.take() {
+            if !pending.text.is_empty() {
+                self.push(InputAction::WriteText(pending.text), pending.start, pending.last);
+            }
+        }
+    }
+
+    fn flush_wheel(&mut self) {
+        if let Some(wheel) = self.wheel.take() {
+            let action = InputAction::Scroll {
+                x: wheel.point.0,
+                y: wheel.point.1,
+                length: wheel.length,
+                direction: wheel.axis,
+                unit: ScrollUnit::Notches,
+            };
+            self.push(action, wheel.start, wheel.last);
+        }
+    }
+
+    fn finish(mut self) -> Vec<RecordedAction> {
+        self.flush_text();
+        self.flush_wheel();
+        // still held when the recording stopped
+        if let Some((button, start, at)) = self.press.take() {
+            if let Some(button) = to_enigo_button(button) {
+                let (x, y) = Self::point(start);
+                self.push(InputAction::ButtonDown { x, y, button }, at, at);
+            }
+        }
+        self.out
+    }
+}
+
+fn modifier_key(key: rdev::Key) -> Option<Key> {
+    match key {
+        rdev::Key::ShiftLeft | rdev::Key::ShiftRight => Some(Key::Shift),
+        rdev::Key::ControlLeft | rdev::Key::ControlRight => Some(Key::Control),
+        rdev::Key::Alt | rdev::Key::AltGr => Some(Key::Alt),
+        rdev::Key::MetaLeft | rdev::Key::MetaRight => Some(Key::Meta),
+        _ => None,
+    }
+}
+
+fn to_enigo_button(button: ListenButton) -> Option<enigo::Button> {
+    match button {
+        ListenButton::Left => Some(enigo::Button::Left),
+        ListenButton::Right => Some(enigo::Button::Right),
+        ListenButton::Middle => Some(enigo::Button::Middle),
+        ListenButton::Unknown(_) => None,
+    }
+}
+
+/// The key on a US layout, `None` for keys `enigo` has no name for.
+fn to_enigo_key(key: rdev::Key) -> Option<Key> {
+    use rdev::Key as K;
+    let key = match key {
+        K::Backspace => Key::Backspace,
+        K::CapsLock => Key::CapsLock,
+        K::Delete | K::KpDelete => Key::Delete,
+        K::DownArrow => Key::DownArrow,
+        K::UpArrow => Key::UpArrow,
+        K::LeftArrow => Key::LeftArrow,
+        K::RightArrow => Key::RightArrow,
+        K::End => Key::End,
+        K::Home => Key::Home,
+        K::PageDown => Key::PageDown,
+        K::PageUp => Key::PageUp,
+        K::Escape => Key::Escape,
+        K::Return | K::KpReturn => Key::Return,
+        K::Space => Key::Space,
+        K::Tab => Key::Tab,
+        K::F1 => Key::F1,
+        K::F2 => Key::F2,
+        K::F3 => Key::F3,
+        K::F4 => Key::F4,
+        K::F5 => Key::F5,
+        K::F6 => Key::F6,
+        K::F7 => Key::F7,
+        K::F8 => Key::F8,
+        K::F9 => Key::F9,
+        K::F10 => Key::F10,
+        K::F11 => Key::F11,
+        K::F12 => Key::F12,
+        other => return key_char(other).map(Key::Unicode),
+    };
+    Some(key)
+}
+
+fn key_char(key: rdev::Key) -> Option<char> {
+    use rdev::Key as K;
+    const LETTERS: [(K, char); 26] = [
+        (K::KeyA, 'a'),
+        (K::KeyB, 'b'),
+        (K::KeyC, 'c'),
+        (K::KeyD, 'd'),
+        (K::KeyE, 'e'),
+        (K::KeyF, 'f'),
+        (K::KeyG, 'g'),
+        (K::KeyH, 'h'),
+        (K::KeyI, 'i'),
+        (K::KeyJ, 'j'),
+        (K::KeyK, 'k'),
+        (K::KeyL, 'l'),
+        (K::KeyM, 'm'),
+        (K::KeyN, 'n'),
+        (K::KeyO, 'o'),
+        (K::KeyP, 'p'),
+        (K::KeyQ, 'q'),
+        (K::KeyR, 'r'),
+        (K::KeyS, 's'),
+        (K::KeyT, 't'),
+        (K::KeyU, 'u'),
+        (K::KeyV, 'v'),
+        (K::KeyW, 'w'),
+        (K::KeyX, 'x'),
+        (K::KeyY, 'y'),
+        (K::KeyZ, 'z'),
+    ];
+    if let Some((_, c)) = LETTERS.iter().find(|(k, _)| *k == key) {
+        return Some(*c);
+    }
+    let c = match key {
+        K::Num0 | K::Kp0 => '0',
+        K::Num1 | K::Kp1 => '1',
+        K::Num2 | K::Kp2 => '2',
+        K::Num3 | K::Kp3 => '3',
+        K::Num4 | K::Kp4 => '4',
+        K::Num5 | K::Kp5 => '5',
+        K::Num6 | K::Kp6 => '6',
+        K::Num7 | K::Kp7 => '7',
+        K::Num8 | K::Kp8 => '8',
+        K::Num9 | K::Kp9 => '9',
+        K::Minus | K::KpMinus => '-',
+        K::Equal => '=',
+        K::KpPlus => '+',
+        K::KpMultiply => '*',
+        K::Slash | K::KpDivide => '/',
+        K::BackQuote => '`',
+        K::LeftBracket => '[',
+        K::RightBracket => ']',
+        K::SemiColon => ';',
+        K::Quote => '\'',
+        K::BackSlash | K::IntlBackslash => '\\',
+        K::Comma => ',',
+        K::Dot => '.',
+        _ => return None,
+    };
+    Some(c)
+}
+
+const SCREENSHOT_WAIT: Duration = Duration::from_secs(5);
+
+/// Saves a screenshot for the n-th capture and returns its path.
+pub type ScreenshotHook = Box<dyn FnMut(usize) -> Result<PathBuf> + Send>;
+
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    pub coalesce: CoalesceConfig,
+    /// ends the recording, its keys are left out
+    pub stop_hotkey: Vec<ListenKey>,
+    /// screenshots are taken on presses at most this often
+    pub screenshot_interval: Duration,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            coalesce: CoalesceConfig::default(),
+            stop_hotkey: vec![ListenKey::ControlLeft, ListenKey::ShiftLeft, ListenKey::KeyR],
+            screenshot_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Captured {
+    events: Vec<RawEvent>,
+    /// request time and file of each screenshot
+    screenshots: Vec<(Duration, PathBuf)>,
+    requested: usize,
+    /// taken or failed
+    finished: usize,
+}
+
+/// Records the user's mouse and keyboard through a global hook until `stop` or the stop hotkey.
+/// Shares the global hook with `KillSwitch` through `kill_switch::subscribe`.
+pub struct Recorder {
+    config: RecorderConfig,
+    captured: Arc<Mutex<Captured>>,
+    recording: Arc<AtomicBool>,
+    _subscription: Subscription,
+}
+
+impl Recorder {
+    pub fn start(config: RecorderConfig) -> Result<Self> {
+        Self::start_with(config, None)
+    }
+
+    /// Also calls `hook` on clicks, key presses and scrolls, each action keeps the screenshot taken when it began.
+    pub fn start_with_screenshots(config: RecorderConfig, hook: ScreenshotHook) -> Result<Self> {
+        Self::start_with(config, Some(hook))
+    }
+
+    fn start_with(config: RecorderConfig, hook: Option<ScreenshotHook>) -> Result<Self> {
+        let captured = Arc::new(Mutex::new(Captured::default()));
+        let recording = Arc::new(AtomicBool::new(true));
+        let started = Instant::now();
+
+        // taken off the listener thread so slow captures don't hold up input events
+        let screenshot_sender = hook.map(|mut hook| {
+            let (sender, receiver) = mpsc::channel::<Duration>();
+            let worker_captured = captured.clone();
+            std::thread::spawn(move || {
+                for (index, at) in receiver.into_iter().enumerate() {
+                    let result = hook(index);
+                    let mut captured = worker_captured.lock().unwrap();
+                    match result {
+                        Ok(path) => captured.screenshots.push((at, path)),
+                        Err(e) => log::warn!("recording screenshot failed: {}", e),
+                    }
+                    captured.finished += 1;
+                }
+            });
+            sender
+        });
+
+        let listen_captured = captured.clone();
+        let listen_recording = recording.clone();
+        let stop_hotkey = config.stop_hotkey.clone();
+        let interval = config.screenshot_interval;
+        let mut matcher = HotkeyMatcher::new(stop_hotkey.clone());
+        let mut last_screenshot: Option<Duration> = None;
+        let subscription = subscribe(move |event: &Event| {
+            if !listen_recording.load(Ordering::SeqCst) {
+                return;
+            }
+            let at = started.elapsed();
+            if matcher.on_event(&event.event_type) {
+                listen_recording.store(false, Ordering::SeqCst);
+                let mut captured = listen_captured.lock().unwrap();
+                while matches!(captured.events.last(), Some(RawEvent { event_type: EventType::KeyPress(key), .. }) if stop_hotkey.contains(key)) {
+                    captured.events.pop();
+                }
+                return;
+            }
+            let presses = matches!(event.event_type, EventType::ButtonPress(_) | EventType::KeyPress(_) | EventType::Wheel { .. });
+            if let (true, Some(sender)) = (presses, &screenshot_sender) {
+                if last_screenshot.is_none_or(|last| at.saturating_sub(last) >= interval) {
+                    last_screenshot = Some(at);
+                    if sender.send(at).is_ok() {
+                        listen_captured.lock().unwrap().requested += 1;
+                    }
+                }
+            }
+            listen_captured.lock().unwrap().events.push(RawEvent {
+                at,
+                event_type: event.event_type,
+                name: event.name.clone(),
+            });
+        })?;
+
+        Ok(Self {
+            config,
+            captured,
+            recording,
+            _subscription: subscription,
+        })
+    }
+
+    /// false once stopped, also by the stop hotkey
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::SeqCst)
+    }
+
+    /// Waits up to `SCREENSHOT_WAIT` for screenshots still being taken, then coalesces.
+    pub fn stop(self) -> Recording {
+        self.recording.store(false, Ordering::SeqCst);
+        let started = Instant::now();
+        while started.elapsed() < SCREENSHOT_WAIT {
+            let captured = self.captured.lock().unwrap();
+            if captured.finished >= captured.requested {
+                break;
+            }
+            drop(captured);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let captured = std::mem::take(&mut *self.captured.lock().unwrap());
+        let mut actions = coalesce(&captured.events, &self.config.coalesce);
+        for action in &mut actions {
+            action.screenshot = captured.screenshots.iter().rev().find(|(at, _)| *at <= action.at).map(|(_, path)| path.clone());
+        }
+        Recording { actions }
+    }
+}
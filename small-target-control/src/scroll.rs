@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use enigo::Axis;
+use serde::{Deserialize, Serialize};
+
+/// What `Scroll::length` counts. Everything ends up as wheel notches, the only unit `enigo` knows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScrollUnit {
+    #[default]
+    Notches,
+    Lines,
+    Pixels,
+    Pages,
+}
+
+impl FromStr for ScrollUnit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "notch" | "notches" | "click" | "clicks" | "" => Ok(ScrollUnit::Notches),
+            "line" | "lines" => Ok(ScrollUnit::Lines),
+            "pixel" | "pixels" | "px" => Ok(ScrollUnit::Pixels),
+            "page" | "pages" => Ok(ScrollUnit::Pages),
+            other => Err(anyhow!("invalid scroll unit: {}", other)),
+        }
+    }
+}
+
+/// Notches scrolled when the model gives no length, roughly half a page down or a few columns across.
+pub fn default_length(axis: Axis) -> i32 {
+    match axis {
+        Axis::Vertical => 5,
+        Axis::Horizontal => 3,
+    }
+}
+
+/// Unit conversion and pacing of wheel events. The ratios vary by platform and app, these are typical desktop values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScrollSettings {
+    pub lines_per_notch: u32,
+    pub pixels_per_notch: u32,
+    pub notches_per_page: u32,
+    /// no single scroll goes further than this, 0 for no limit
+    pub max_notches: u32,
+    /// notches per wheel event, 0 sends the whole amount in one event
+    pub chunk: u32,
+    pub chunk_delay: Duration,
+    /// wait after each `ScrollUntil` step before looking at the screen
+    pub settle: Duration,
+    /// fingerprints at most this far apart count as an unchanged screen
+    pub stable_distance: u32,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        Self {
+            lines_per_notch: 3,
+            pixels_per_notch: 50,
+            notches_per_page: 10,
+            max_notches: 50,
+            chunk: 1,
+            chunk_delay: Duration::from_millis(15),
+            settle: Duration::from_millis(300),
+            stable_distance: 1,
+        }
+    }
+}
+
+impl ScrollSettings {
+    /// the old behaviour, one event with every notch
+    pub fn instant() -> Self {
+        Self {
+            chunk: 0,
+            chunk_delay: Duration::ZERO,
+            ..Self::default()
+        }
+    }
+
+    /// Signed notches for `length` in `unit`, anything non-zero scrolls at least one notch.
+    pub fn to_notches(&self, length: i32, unit: ScrollUnit) -> i32 {
+        let per_notch = match unit {
+            ScrollUnit::Notches => 1.0,
+            ScrollUnit::Lines => self.lines_per_notch.max(1) as f64,
+            ScrollUnit::Pixels => self.pixels_per_notch.max(1) as f64,
+            ScrollUnit::Pages => 1.0 / self.notches_per_page.max(1) as f64,
+        };
+        let mut notches = (length.abs() as f64 / per_notch).round() as i32;
+        if length != 0 {
+            notches = notches.max(1);
+        }
+        if self.max_notches > 0 {
+            notches = notches.min(self.max_notches as i32);
+        }
+        notches * length.signum()
+    }
+
+    /// Splits `notches` into wheel events of at most `chunk` notches each.
+    pub fn chunks(&self, notches: i32) -> Vec<i32> {
+        if self.chunk == 0 || notches == 0 {
+            return vec![notches];
+        }
+        let chunk = self.chunk as i32;
+        let mut left = notches.abs();
+        let mut chunks = Vec::new();
+        while left > 0 {
+            let part = left.min(chunk);
+            chunks.push(part * notches.signum());
+            left -= part;
+        }
+        chunks
+    }
+}
+
+/// When `ScrollUntil` stops.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScrollTarget {
+    /// the screen around the scroll point stops changing, usually the end of the page
+    Stable,
+    /// visible text, only probes that can read the screen support this
+    Text(String),
+    /// an image file to find on screen
+    Template(PathBuf),
+}
+
+impl ScrollTarget {
+    /// The model's `target` argument: nothing scrolls to the end, image file names are templates, anything else is text.
+    pub fn parse(target: &str) -> Self {
+        let target = target.trim();
+        let is_image = Path::new(target)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ["png", "jpg", "jpeg", "bmp"].contains(&ext.to_lowercase().as_str()));
+        match target {
+            "" => ScrollTarget::Stable,
+            _ if is_image => ScrollTarget::Template(PathBuf::from(target)),
+            _ => ScrollTarget::Text(target.to_string()),
+        }
+    }
+}
+
+/// Looks at the screen for `ScrollUntil`, see `ActionControl::set_screen_probe`. Points are in the virtual desktop.
+pub trait ScreenProbe: Send {
+    /// A perceptual hash of the screen around `point`, close hashes mean nothing moved.
+    fn fingerprint(&mut self, point: (i32, i32)) -> Result<u64>;
+
+    /// The center of `target` on screen, `None` while it is not visible.
+    fn locate(&mut self, target: &ScrollTarget) -> Result<Option<(i32, i32)>>;
+
+    /// The pixels within `radius` of `point`, clipped to the screen, for `changed_since`.
+    fn capture_region(&mut self, _point: (i32, i32), _radius: u32) -> Result<RegionCapture> {
+        Err(anyhow!("this screen probe can't capture regions"))
+    }
+
+    /// Share of the captured region's pixels that look different now, see `VerifyConfig::pixel_threshold`.
+    fn changed_since(&mut self, _before: &RegionCapture, _threshold: u8) -> Result<f64> {
+        Err(anyhow!("this screen probe can't compare regions"))
+    }
+}
+
+/// RGBA pixels of a screen area, `x` and `y` are its top left corner in the virtual desktop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionCapture {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
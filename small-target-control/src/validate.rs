@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use enigo::Key;
+use serde::{Deserialize, Serialize};
+
+use crate::action::InputAction;
+
+/// A monitor in the virtual desktop, in the coordinates `enigo` moves the cursor in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScreenRect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && y >= self.y && (x - self.x) < self.width as i32 && (y - self.y) < self.height as i32
+    }
+}
+
+/// What a single action may ask for. The defaults catch model mistakes, not deliberate long actions.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ValidationLimits {
+    pub max_wait: Duration,
+    pub max_hold: Duration,
+    /// characters of `WriteText` and `SetClipboard`
+    pub max_text_len: usize,
+    /// drag and select endpoints closer than this are the same point
+    pub min_drag_distance: u32,
+    pub max_clicks: u32,
+    pub max_scrolls: u32,
+}
+
+impl Default for ValidationLimits {
+    fn default() -> Self {
+        Self {
+            max_wait: Duration::from_secs(60),
+            max_hold: Duration::from_secs(10),
+            max_text_len: 10_000,
+            min_drag_distance: 3,
+            max_clicks: 3,
+            max_scrolls: 100,
+        }
+    }
+}
+
+/// Why an action was refused, `Display` is phrased for the model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Violation {
+    OffScreen { x: i32, y: i32 },
+    SamePoint { x: i32, y: i32 },
+    WaitTooLong { ms: u64, max_ms: u64 },
+    HoldTooLong { ms: u64, max_ms: u64 },
+    TextTooLong { len: usize, max: usize },
+    TooManyClicks { count: u32, max: u32 },
+    TooManyScrolls { count: u32, max: u32 },
+    ZeroScroll,
+    /// a key name the parser didn't recognise
+    UnknownKey,
+    NoKeys,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::OffScreen { x, y } => write!(f, "({}, {}) is outside the screen", x, y),
+            Violation::SamePoint { x, y } => write!(f, "the start and end are both at ({}, {}), they must be different elements", x, y),
+            Violation::WaitTooLong { ms, max_ms } => write!(f, "waiting {} ms is longer than the allowed {} ms", ms, max_ms),
+            Violation::HoldTooLong { ms, max_ms } => write!(f, "holding a key for {} ms is longer than the allowed {} ms", ms, max_ms),
+            Violation::TextTooLong { len, max } => write!(f, "the text has {} characters, at most {} are allowed", len, max),
+            Violation::TooManyClicks { count, max } => write!(f, "{} clicks in a row, at most {} are allowed", count, max),
+            Violation::TooManyScrolls { count, max } => write!(f, "{} scrolls, at most {} are allowed", count, max),
+            Violation::ZeroScroll => write!(f, "the scroll length is zero"),
+            Violation::UnknownKey => write!(f, "a key name is not recognised"),
+            Violation::NoKeys => write!(f, "no key is given"),
+        }
+    }
+}
+
+/// Checks actions against the monitor layout and `ValidationLimits` before they run.
+#[derive(Debug, Clone)]
+pub struct ActionValidator {
+    pub screens: Vec<ScreenRect>,
+    pub limits: ValidationLimits,
+}
+
+impl ActionValidator {
+    /// No screens skips the on-screen check.
+    pub fn new(screens: Vec<ScreenRect>) -> Self {
+        Self {
+            screens,
+            limits: ValidationLimits::default(),
+        }
+    }
+
+    /// Every problem with `action`, empty when it may run.
+    pub fn validate(&self, action: &InputAction) -> Vec<Violation> {
+        let limits = &self.limits;
+        let mut violations = Vec::new();
+        match action {
+            InputAction::MouseMove { x, y }
+            | InputAction::MouseLeftClick { x, y }
+            | InputAction::MouseLeftDoubleClick { x, y }
+            | InputAction::MouseRightClick { x, y }
+            | InputAction::MouseMiddleClick { x, y }
+            | InputAction::TripleClick { x, y }
+            | InputAction::ButtonDown { x, y, .. } => self.check_point(*x, *y, &mut violations),
+            InputAction::Drag { x1, y1, x2, y2 } | InputAction::Select { x1, y1, x2, y2 } => {
+                self.check_point(*x1, *y1, &mut violations);
+                self.check_point(*x2, *y2, &mut violations);
+                let distance = ((x2 - x1) as f64).hypot((y2 - y1) as f64);
+                if distance < limits.min_drag_distance as f64 {
+                    violations.push(Violation::SamePoint { x: *x1, y: *y1 });
+                }
+            }
+            InputAction::Click { x, y, modifiers, count, .. } => {
+                self.check_point(*x, *y, &mut violations);
+                check_keys(modifiers, &mut violations);
+                if *count > limits.max_clicks {
+                    violations.push(Violation::TooManyClicks { count: *count, max: limits.max_clicks });
+                }
+            }
+            InputAction::Scroll { x, y, length, .. } => {
+                self.check_point(*x, *y, &mut violations);
+                if *length == 0 {
+                    violations.push(Violation::ZeroScroll);
+                }
+            }
+            InputAction::ScrollUntil { x, y, step, max_scrolls, .. } => {
+                self.check_point(*x, *y, &mut violations);
+                if *step == 0 {
+                    violations.push(Violation::ZeroScroll);
+                }
+                if *max_scrolls > limits.max_scrolls {
+                    violations.push(Violation::TooManyScrolls {
+                        count: *max_scrolls,
+                        max: limits.max_scrolls,
+                    });
+                }
+            }
+            InputAction::WriteText(text) | InputAction::SetClipboard(text) => {
+                let len = text.chars().count();
+                if len > limits.max_text_len {
+                    violations.push(Violation::TextTooLong { len, max: limits.max_text_len });
+                }
+            }
+            InputAction::Hotkey { hot_keys } => {
+                if hot_keys.is_empty() {
+                    violations.push(Violation::NoKeys);
+                }
+                check_keys(hot_keys, &mut violations);
+            }
+            InputAction::KeyClick(key) | InputAction::KeyDown(key) | InputAction::KeyUp(key) => check_keys(std::slice::from_ref(key), &mut violations),
+            InputAction::KeyHold { key, ms } => {
+                check_keys(std::slice::from_ref(key), &mut violations);
+                let max_ms = limits.max_hold.as_millis() as u64;
+                if *ms > max_ms {
+                    violations.push(Violation::HoldTooLong { ms: *ms, max_ms });
+                }
+            }
+            InputAction::Wait { milliseconds } => {
+                let max_ms = limits.max_wait.as_millis() as u64;
+                if *milliseconds > max_ms {
+                    violations.push(Violation::WaitTooLong { ms: *milliseconds, max_ms });
+                }
+            }
+            InputAction::GetClipboard | InputAction::SelectAll | InputAction::ButtonUp(_) => {}
+        }
+        violations
+    }
+
+    fn check_point(&self, x: i32, y: i32, violations: &mut Vec<Violation>) {
+        if !self.screens.is_empty() && !self.screens.iter().any(|screen| screen.contains(x, y)) {
+            violations.push(Violation::OffScreen { x, y });
+        }
+    }
+}
+
+// `parse_key_from_str` turns names it doesn't know into `Key::Other(0)`
+fn check_keys(keys: &[Key], violations: &mut Vec<Violation>) {
+    if keys.contains(&Key::Other(0)) {
+        violations.push(Violation::UnknownKey);
+    }
+}
+
+/// Tells the model what was wrong with its action so the next answer can fix it.
+pub fn correction_prompt(action: &InputAction, violations: &[Violation]) -> String {
+    let reasons: Vec<String> = violations.iter().map(|v| format!("- {}", v)).collect();
+    format!("The action {:?} was not executed:\n{}\nCorrect it and try again.", action, reasons.join("\n"))
+}
+
+/// Tells the model that its action couldn't even be built, e.g. an unknown action name or an unreadable box.
+pub fn parse_error_prompt(action_type: &str, inputs: &HashMap<String, String>, error: &anyhow::Error) -> String {
+    let mut args: Vec<String> = inputs.iter().map(|(name, value)| format!("{}='{}'", name, value)).collect();
+    args.sort();
+    format!("The action {}({}) was not executed:\n- {:#}\nCorrect it and try again.", action_type, args.join(", "), error)
+}
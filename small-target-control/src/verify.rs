@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::action::InputAction;
+
+/// Checks that clicks change the screen around them, see `ActionControl::verify`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VerifyConfig {
+    /// half the side of the square compared around the click
+    pub radius: u32,
+    /// a pixel changed when a color channel moved by more than this
+    pub pixel_threshold: u8,
+    /// share of the square that has to change, 0.0 to 1.0
+    pub min_changed: f64,
+    /// how long the screen gets to react
+    pub settle: Duration,
+    /// clicks after the first, each one nudged a little further from the original point. A click whose
+    /// effect shows up outside the region, like a dialog or a toggle, would fire twice, so it's off by default.
+    pub retries: u32,
+    /// pixels between nudges, 0 repeats the click on the same point
+    pub nudge: i32,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            radius: 100,
+            pixel_threshold: 24,
+            min_changed: 0.002,
+            settle: Duration::from_millis(500),
+            retries: 0,
+            nudge: 4,
+        }
+    }
+}
+
+impl VerifyConfig {
+    /// Offset of the given attempt, the first one is the original point, then right, down, left and up, growing each round.
+    pub fn offset(&self, attempt: u32) -> (i32, i32) {
+        if attempt == 0 {
+            return (0, 0);
+        }
+        let distance = self.nudge * ((attempt as i32 - 1) / 4 + 1);
+        match (attempt - 1) % 4 {
+            0 => (distance, 0),
+            1 => (0, distance),
+            2 => (-distance, 0),
+            _ => (0, -distance),
+        }
+    }
+}
+
+/// The point whose surroundings should change, only clicks are checked. Drags, scrolls and typing can
+/// legitimately leave the area under the cursor as it was.
+pub fn watched_point(action: &InputAction) -> Option<(i32, i32)> {
+    match action {
+        InputAction::MouseLeftClick { x, y }
+        | InputAction::MouseLeftDoubleClick { x, y }
+        | InputAction::MouseRightClick { x, y }
+        | InputAction::MouseMiddleClick { x, y }
+        | InputAction::TripleClick { x, y }
+        | InputAction::Click { x, y, .. } => Some((*x, *y)),
+        _ => None,
+    }
+}
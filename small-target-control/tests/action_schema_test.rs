@@ -0,0 +1,96 @@
+mod action_schema_test {
+    use std::collections::HashMap;
+
+    use small_target_control::action_schema::ParamKind;
+    use small_target_control::{action_space_prompt, lookup_action, ActionKind, ActionSpec, InputAction, ACTION_SCHEMA};
+
+    fn sample_inputs(spec: &ActionSpec) -> HashMap<String, String> {
+        spec.params
+            .iter()
+            .filter(|p| p.default.is_none())
+            .map(|p| {
+                let value = match p.kind {
+                    ParamKind::Box => "[10, 20, 30, 40]",
+                    ParamKind::Text => "hello",
+                    ParamKind::Keys => "enter",
+                    ParamKind::Direction => "down",
+                    ParamKind::Integer => "3",
+                    ParamKind::Button => "right",
+                    ParamKind::ScrollUnit => "lines",
+                };
+                (p.name.to_string(), value.to_string())
+            })
+            .collect()
+    }
+
+    fn kind_of(action: &InputAction) -> Option<ActionKind> {
+        match action {
+            InputAction::MouseLeftClick { .. } => Some(ActionKind::MouseLeftClick),
+            InputAction::MouseLeftDoubleClick { .. } => Some(ActionKind::MouseLeftDoubleClick),
+            InputAction::MouseRightClick { .. } => Some(ActionKind::MouseRightClick),
+            InputAction::MouseMiddleClick { .. } => Some(ActionKind::MouseMiddleClick),
+            InputAction::MouseMove { .. } => Some(ActionKind::MouseMove),
+            InputAction::Drag { .. } => Some(ActionKind::Drag),
+            InputAction::Scroll { .. } => Some(ActionKind::Scroll),
+            InputAction::ScrollUntil { .. } => Some(ActionKind::ScrollUntil),
+            InputAction::Hotkey { .. } => Some(ActionKind::Hotkey),
+            InputAction::KeyClick(_) => Some(ActionKind::KeyClick),
+            InputAction::WriteText(_) => Some(ActionKind::WriteText),
+            InputAction::SetClipboard(_) => Some(ActionKind::SetClipboard),
+            InputAction::GetClipboard => Some(ActionKind::GetClipboard),
+            InputAction::Wait { .. } => Some(ActionKind::Wait),
+            InputAction::KeyDown(_) => Some(ActionKind::KeyDown),
+            InputAction::KeyUp(_) => Some(ActionKind::KeyUp),
+            InputAction::KeyHold { .. } => Some(ActionKind::KeyHold),
+            InputAction::ButtonDown { .. } => Some(ActionKind::ButtonDown),
+            InputAction::ButtonUp(_) => Some(ActionKind::ButtonUp),
+            InputAction::Select { .. } => Some(ActionKind::Select),
+            InputAction::TripleClick { .. } => Some(ActionKind::TripleClick),
+            InputAction::SelectAll => Some(ActionKind::SelectAll),
+            // not exposed to the model
+            InputAction::Click { .. } => None,
+        }
+    }
+
+    /// Fails when the prompt documents something the parser doesn't build, or the other way round.
+    #[test]
+    fn test_prompt_and_parser_agree() {
+        let prompt = action_space_prompt();
+        let documented: Vec<&str> = prompt.lines().map(|line| line.split('(').next().unwrap()).collect();
+        assert_eq!(documented.len(), ACTION_SCHEMA.len());
+
+        for (line, spec) in prompt.lines().zip(ACTION_SCHEMA) {
+            assert_eq!(lookup_action(spec.name).unwrap().name, spec.name);
+            assert!(line.starts_with(&spec.signature()));
+            for param in spec.params.iter().filter(|p| p.default.is_none()) {
+                assert!(line.contains(&format!("{}=", param.name)), "{} is missing {} in the prompt", spec.name, param.name);
+            }
+
+            for name in std::iter::once(&spec.name).chain(spec.aliases) {
+                let parsed = InputAction::parse_from_action_type_and_inputs(name.to_string(), sample_inputs(spec));
+                if spec.is_terminal() {
+                    assert!(parsed.is_err(), "{} should not execute", name);
+                } else {
+                    let action = parsed.unwrap_or_else(|e| panic!("documented action {} doesn't parse: {}", name, e));
+                    assert_eq!(kind_of(&action), Some(spec.kind));
+                }
+            }
+        }
+        assert!(documented.contains(&"middle_click"));
+        assert!(documented.contains(&"finished") && documented.contains(&"call_user"));
+    }
+
+    #[test]
+    fn test_defaults_and_unknown_actions() {
+        let wait = InputAction::parse_from_action_type_and_inputs("wait".to_string(), HashMap::new()).unwrap();
+        assert!(matches!(wait, InputAction::Wait { milliseconds: 5000 }));
+
+        let click = lookup_action("click").unwrap();
+        let error = InputAction::parse_from_action_type_and_inputs("click".to_string(), HashMap::new()).unwrap_err();
+        assert!(error.to_string().contains("start_box"), "{}", error);
+        assert!(click.param("start_box").is_some());
+
+        assert!(lookup_action("teleport").is_none());
+        assert!(InputAction::parse_from_action_type_and_inputs("teleport".to_string(), HashMap::new()).is_err());
+    }
+}
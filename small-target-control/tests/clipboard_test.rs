@@ -0,0 +1,56 @@
+mod clipboard_test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use enigo::Settings;
+    use small_target_control::{ActionControl, Clipboard, ClipboardContent, InputAction, TextEntry};
+
+    #[test]
+    fn test_auto_pastes_non_ascii_and_long_text() {
+        assert!(!TextEntry::Auto.uses_paste("hello world"));
+        assert!(TextEntry::Auto.uses_paste("你好"));
+        assert!(TextEntry::Auto.uses_paste("ship it 🚀"));
+        assert!(TextEntry::Auto.uses_paste(&"a".repeat(500)));
+        assert!(!TextEntry::Keystrokes.uses_paste("你好"));
+        assert!(TextEntry::Paste.uses_paste("hello"));
+    }
+
+    #[test]
+    fn test_parse_clipboard_actions() -> Result<()> {
+        let set = InputAction::new("set_clipboard".to_string(), HashMap::from([("content".to_string(), "密码 ✓".to_string())]))?;
+        assert!(matches!(set, InputAction::SetClipboard(text) if text == "密码 ✓"));
+        assert!(matches!(InputAction::new("get_clipboard".to_string(), HashMap::new())?, InputAction::GetClipboard));
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    fn test_clipboard_round_trip() -> Result<()> {
+        let mut control = ActionControl::new(&Settings::default());
+        control.handle_action(InputAction::SetClipboard("剪贴板 🚀".to_string()))?;
+        control.handle_action(InputAction::GetClipboard)?;
+        assert_eq!(control.take_clipboard_text().as_deref(), Some("剪贴板 🚀"));
+        assert_eq!(control.take_clipboard_text(), None);
+
+        let mut other = Clipboard::default();
+        other.set_text("plain")?;
+        assert_eq!(other.get_text()?.as_deref(), Some("plain"));
+
+        // pasting puts a copied image back instead of clearing it
+        let pixel = arboard::ImageData {
+            width: 1,
+            height: 1,
+            bytes: vec![255, 0, 0, 255].into(),
+        };
+        other.restore(ClipboardContent::Image(pixel))?;
+        control.text_entry = TextEntry::Paste;
+        control.handle_action(InputAction::WriteText("x".to_string()))?;
+        assert!(matches!(other.save()?, Some(ClipboardContent::Image(image)) if image.width == 1 && image.height == 1));
+
+        // an empty clipboard is pasted through and left empty
+        other.clear()?;
+        control.handle_action(InputAction::WriteText("y".to_string()))?;
+        assert!(other.save()?.is_none());
+        Ok(())
+    }
+}
@@ -204,7 +204,7 @@ impl EnigoTest {
             ]),
         )?;
         let (x, y, length_with_sign, direction) = match input_action {
-            InputAction::Scroll { x, y, length, direction } => (x, y, length, direction),
+            InputAction::Scroll { x, y, length, direction, .. } => (x, y, length, direction),
             _ => panic!("Invalid action type"),
         };
         let res = self.action.handle_action(input_action);
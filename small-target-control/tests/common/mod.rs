@@ -1,3 +1,15 @@
+use std::collections::HashMap;
+
+// each test binary only uses part of the helpers
+#[allow(dead_code)]
 mod browser;
+#[allow(dead_code)]
 mod browser_events;
+#[allow(dead_code)]
 pub mod enigo_test;
+
+/// `action_inputs` as the model parser hands them over.
+#[allow(dead_code)]
+pub fn inputs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
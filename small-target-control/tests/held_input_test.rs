@@ -0,0 +1,66 @@
+mod common;
+
+mod held_input_test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use enigo::{Button, Key, Settings};
+    use small_target_control::{ActionControl, CancelToken, InputAction};
+
+    use crate::common::inputs;
+
+    #[test]
+    fn test_parse_held_actions() -> Result<()> {
+        assert!(matches!(InputAction::new("key_down".to_string(), inputs(&[("key", "shift")]))?, InputAction::KeyDown(Key::Shift)));
+        assert!(matches!(InputAction::new("key_hold".to_string(), inputs(&[("key", "backspace")]))?, InputAction::KeyHold { key: Key::Backspace, ms: 1000 }));
+        assert!(matches!(
+            InputAction::new("mouse_down".to_string(), inputs(&[("start_box", "[10,20]"), ("button", "right")]))?,
+            InputAction::ButtonDown { x: 10, y: 20, button: Button::Right }
+        ));
+        assert!(matches!(InputAction::new("mouse_up".to_string(), HashMap::new())?, InputAction::ButtonUp(Button::Left)));
+        assert!(InputAction::new("mouse_up".to_string(), inputs(&[("button", "thumb")])).is_err());
+
+        let click = InputAction::Click {
+            x: 1,
+            y: 2,
+            button: Button::Left,
+            modifiers: vec![Key::Shift],
+            count: 3,
+        };
+        let json = serde_json::to_string(&click)?;
+        assert!(matches!(serde_json::from_str::<InputAction>(&json)?, InputAction::Click { count: 3, .. }));
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    fn test_release_held_inputs() -> Result<()> {
+        let cancel_token = CancelToken::new();
+        let mut control = ActionControl::with_cancel_token(&Settings::default(), cancel_token.clone());
+        control.handle_action(InputAction::KeyDown(Key::Shift))?;
+        control.handle_action(InputAction::KeyDown(Key::Shift))?;
+        control.handle_action(InputAction::Click {
+            x: 5,
+            y: 5,
+            button: Button::Left,
+            modifiers: vec![Key::Shift, Key::Control],
+            count: 1,
+        })?;
+        // shift was held before the click and stays held, control was only held for it
+        assert_eq!(control.held_keys(), &[Key::Shift]);
+        control.handle_action(InputAction::ButtonDown { x: 5, y: 5, button: Button::Left })?;
+        assert_eq!(control.held_buttons(), &[Button::Left]);
+
+        control.release_all()?;
+        assert!(control.held_keys().is_empty() && control.held_buttons().is_empty());
+
+        // a hold cancelled half way still releases its key
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            cancel_token.cancel();
+        });
+        assert!(control.handle_action(InputAction::KeyHold { key: Key::Shift, ms: 10_000 }).is_err());
+        assert!(control.held_keys().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+mod kill_switch_test {
+    use std::time::{Duration, Instant};
+
+    use enigo::Settings;
+    use small_target_control::kill_switch::{is_in_corner, ActionCancelled, CancelToken, HotkeyMatcher, ListenKey};
+    use small_target_control::{ActionControl, InputAction};
+    use rdev::EventType;
+
+    #[test]
+    fn test_cancel_interrupts_sleep() {
+        let token = CancelToken::new();
+        let canceller = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+
+        let start = Instant::now();
+        let err = token.sleep(Duration::from_secs(30)).unwrap_err();
+        assert!(err.is::<ActionCancelled>());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        token.reset();
+        assert!(token.sleep(Duration::from_millis(30)).is_ok());
+    }
+
+    #[test]
+    fn test_injected_input_is_ours_for_a_moment() {
+        let token = CancelToken::new();
+        assert!(!token.is_injecting());
+        let outer = token.injecting();
+        let inner = token.clone().injecting();
+        drop(inner);
+        assert!(token.is_injecting());
+        drop(outer);
+        // events of the last move may still be on their way
+        assert!(token.is_injecting());
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(!token.is_injecting());
+    }
+
+    #[test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    fn test_failsafe_stays_armed_while_an_action_waits() -> anyhow::Result<()> {
+        let token = CancelToken::new();
+        let mut control = ActionControl::with_cancel_token(&Settings::default(), token.clone());
+        let watcher = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(400));
+            token.is_injecting()
+        });
+        control.handle_action(InputAction::MouseMove { x: 10, y: 10 })?;
+        control.handle_action(InputAction::Wait { milliseconds: 800 })?;
+        assert!(!watcher.join().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hotkey_matcher() {
+        let mut matcher = HotkeyMatcher::new(vec![ListenKey::ControlLeft, ListenKey::Escape]);
+        assert!(!matcher.on_event(&EventType::KeyPress(ListenKey::Escape)));
+        assert!(!matcher.on_event(&EventType::KeyRelease(ListenKey::Escape)));
+        assert!(!matcher.on_event(&EventType::KeyPress(ListenKey::ControlLeft)));
+        assert!(matcher.on_event(&EventType::KeyPress(ListenKey::Escape)));
+    }
+
+    #[test]
+    fn test_corner() {
+        let screen = (1920, 1080);
+        assert!(is_in_corner(0.0, 0.0, screen, 2.0));
+        assert!(is_in_corner(1919.0, 1079.0, screen, 2.0));
+        assert!(is_in_corner(1.0, 1078.0, screen, 2.0));
+        assert!(!is_in_corner(0.0, 500.0, screen, 2.0));
+        assert!(!is_in_corner(960.0, 540.0, screen, 2.0));
+    }
+}
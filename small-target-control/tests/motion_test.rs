@@ -0,0 +1,52 @@
+mod motion_test {
+    use std::time::Duration;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use small_target_control::{Easing, MotionProfile, TypingCadence};
+
+    #[test]
+    fn test_instant_profile_jumps_to_the_target() {
+        assert_eq!(MotionProfile::instant().path((0, 0), (300, 200)), vec![(300, 200)]);
+        assert_eq!(MotionProfile::human().path((50, 50), (50, 50)), vec![(50, 50)]);
+    }
+
+    #[test]
+    fn test_paths_end_on_the_target_without_repeats() {
+        let linear = MotionProfile {
+            easing: Easing::Linear,
+            steps: 3,
+            step_delay: Duration::ZERO,
+        };
+        assert_eq!(linear.path((0, 0), (400, -200)), vec![(100, -50), (200, -100), (300, -150), (400, -200)]);
+
+        let eased = MotionProfile::human();
+        let path = eased.path((0, 0), (1000, 0));
+        assert_eq!(path.len(), eased.steps as usize + 1);
+        assert_eq!(*path.last().unwrap(), (1000, 0));
+        assert!(path.windows(2).all(|w| w[0].0 < w[1].0));
+        // slow start and end, fast middle
+        let gaps: Vec<i32> = path.windows(2).map(|w| w[1].0 - w[0].0).collect();
+        assert!(gaps[0] < gaps[gaps.len() / 2] && gaps[gaps.len() - 1] < gaps[gaps.len() / 2]);
+
+        // short moves collapse repeated points
+        let short = eased.path((0, 0), (3, 0));
+        assert_eq!(short, vec![(1, 0), (2, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn test_typing_delays_stay_within_the_jitter() {
+        assert!(TypingCadence::default().is_burst());
+        let cadence = TypingCadence::human();
+        let mut rng = StdRng::seed_from_u64(7);
+        let delays: Vec<Duration> = (0..200).map(|_| cadence.next_delay(&mut rng)).collect();
+        assert!(delays.iter().all(|d| *d >= cadence.delay - cadence.jitter && *d <= cadence.delay + cadence.jitter));
+        assert!(delays.iter().any(|d| *d != delays[0]));
+
+        let steady = TypingCadence {
+            delay: Duration::from_millis(20),
+            jitter: Duration::ZERO,
+        };
+        assert_eq!(steady.next_delay(&mut rng), Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,79 @@
+mod plan_test {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use enigo::{Key, Settings};
+    use small_target_control::{ActionControl, ActionPlan, CancelToken, InputAction, PlanStep, PostCondition, ScreenProbe, ScrollTarget, StepOutcome};
+
+    #[test]
+    fn test_build_plan() -> Result<()> {
+        let plan = ActionPlan::from_actions([InputAction::KeyDown(Key::Shift), InputAction::MouseLeftClick { x: 1, y: 2 }], Duration::from_millis(50));
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan.steps[0].delay, Duration::ZERO);
+        assert_eq!(plan.steps[1].delay, Duration::from_millis(50));
+        assert!(plan.release_at_end);
+
+        let plan = plan.then_step(PlanStep::new(InputAction::GetClipboard).expect(PostCondition::ClipboardContains("ok".to_string()), Duration::from_millis(300)));
+        let json = serde_json::to_string(&plan)?;
+        let parsed: ActionPlan = serde_json::from_str(&json)?;
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed.steps[2].expect, Some(PostCondition::ClipboardContains("ok".to_string())));
+
+        // delays and conditions are optional in hand-written plans
+        let parsed: ActionPlan = serde_json::from_str(r#"{"steps":[{"action":{"type":"Wait","data":{"milliseconds":1}}}],"release_at_end":false}"#)?;
+        assert_eq!(parsed.steps[0].expect_timeout, Duration::from_secs(2));
+        Ok(())
+    }
+
+    /// a screen where nothing ever moves
+    struct FrozenScreen;
+
+    impl ScreenProbe for FrozenScreen {
+        fn fingerprint(&mut self, _point: (i32, i32)) -> Result<u64> {
+            Ok(0)
+        }
+
+        fn locate(&mut self, _target: &ScrollTarget) -> Result<Option<(i32, i32)>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    fn test_abort_releases_held_inputs() -> Result<()> {
+        let cancel_token = CancelToken::new();
+        let mut control = ActionControl::with_cancel_token(&Settings::default(), cancel_token.clone());
+        let plan = ActionPlan::new()
+            .then(InputAction::KeyDown(Key::Shift))
+            .then_step(PlanStep::new(InputAction::Wait { milliseconds: 1 }).expect(PostCondition::ScreenChanged { x: 0, y: 0 }, Duration::ZERO))
+            .then(InputAction::KeyClick(Key::Unicode('a')));
+        // no screen probe installed, the condition can't be checked at all
+        let report = control.run_plan(&plan);
+        let (index, failed) = report.failure().unwrap();
+        assert_eq!(index, 1);
+        assert!(matches!(failed.outcome, StepOutcome::Failed(_)));
+        assert!(control.held_keys().is_empty());
+
+        control.set_screen_probe(Box::new(FrozenScreen));
+        let report = control.run_plan(&plan);
+        assert!(!report.is_success());
+        let (index, failed) = report.failure().unwrap();
+        assert_eq!(index, 1);
+        assert!(matches!(failed.outcome, StepOutcome::Unmet(_)));
+        assert_eq!(report.results[2].outcome, StepOutcome::Skipped);
+        assert_eq!(report.skipped(), 1);
+        assert!(control.held_keys().is_empty());
+
+        let mut plan = ActionPlan::new().then(InputAction::KeyDown(Key::Shift));
+        plan.release_at_end = false;
+        assert!(control.run_plan(&plan).is_success());
+        assert_eq!(control.held_keys(), &[Key::Shift]);
+
+        cancel_token.cancel();
+        let report = control.run_plan(&ActionPlan::new().then(InputAction::Wait { milliseconds: 1000 }).then(InputAction::SelectAll));
+        assert!(report.is_cancelled());
+        assert_eq!(report.results[1].outcome, StepOutcome::Skipped);
+        assert!(control.held_keys().is_empty());
+        Ok(())
+    }
+}
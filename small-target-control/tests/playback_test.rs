@@ -0,0 +1,112 @@
+mod playback_test {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use enigo::{Mouse, Settings};
+    use small_target_control::{substitute, ActionControl, CancelToken, InputAction, Macro, PlaybackOptions, RecordedAction, Recording, ScreenProbe, ScrollTarget, StepOutcome};
+
+    const LOGIN_RON: &str = r#"(
+        name: "login",
+        params: { "user": None, "greeting": Some("hello") },
+        steps: [
+            (action: (type: MouseLeftClick, data: (x: 110, y: 220)), anchor: Some((template: "button.png", recorded_at: (100, 200)))),
+            (action: (type: WriteText, data: "{{ greeting }}, {{user}}\n"), delay_ms: 200),
+        ],
+    )"#;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_and_params() -> Result<()> {
+        let dir = temp_dir("macro-load");
+        let path = dir.join("login.ron");
+        std::fs::write(&path, LOGIN_RON)?;
+        let mac = Macro::load(&path)?;
+        assert_eq!(mac.steps.len(), 2);
+        assert_eq!(mac.steps[0].anchor.as_ref().unwrap().template, dir.join("button.png"));
+        assert_eq!(mac.steps[1].delay_ms, 200);
+
+        assert!(mac.resolve_params(&HashMap::new()).is_err());
+        let given = HashMap::from([("user".to_string(), "ada".to_string())]);
+        let params = mac.resolve_params(&given)?;
+        assert_eq!(substitute("{{ greeting }}, {{user}}\n", &params)?, "hello, ada\n");
+        assert!(mac.resolve_params(&HashMap::from([("user".to_string(), "ada".to_string()), ("pasword".to_string(), "x".to_string())])).is_err());
+        assert!(substitute("{{user", &params).is_err());
+        assert!(substitute("{{nobody}}", &params).is_err());
+
+        // JSON round trip keeps everything
+        let json_path = dir.join("login.json");
+        mac.save(&json_path)?;
+        let reloaded = Macro::load(&json_path)?;
+        assert_eq!(reloaded.params, mac.params);
+        assert_eq!(reloaded.steps[0].anchor, mac.steps[0].anchor);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_recording_and_translate() {
+        let recorded = |action, at_ms, duration_ms| RecordedAction {
+            action,
+            at: Duration::from_millis(at_ms),
+            duration: Duration::from_millis(duration_ms),
+            screenshot: None,
+        };
+        let recording = Recording {
+            actions: vec![recorded(InputAction::MouseLeftClick { x: 1, y: 2 }, 100, 50), recorded(InputAction::WriteText("hi".to_string()), 400, 100)],
+        };
+        let mac = Macro::from_recording("demo", &recording);
+        assert_eq!(mac.name, "demo");
+        assert_eq!(mac.steps[0].delay_ms, 0);
+        assert_eq!(mac.steps[1].delay_ms, 250);
+
+        assert!(matches!(InputAction::Drag { x1: 1, y1: 2, x2: 3, y2: 4 }.translate(10, -1), InputAction::Drag { x1: 11, y1: 1, x2: 13, y2: 3 }));
+        assert!(matches!(InputAction::WriteText("a".to_string()).translate(10, 10), InputAction::WriteText(_)));
+    }
+
+    /// the anchor is always found at (300, 250)
+    struct FixedProbe;
+
+    impl ScreenProbe for FixedProbe {
+        fn fingerprint(&mut self, _point: (i32, i32)) -> Result<u64> {
+            Ok(0)
+        }
+
+        fn locate(&mut self, _target: &ScrollTarget) -> Result<Option<(i32, i32)>> {
+            Ok(Some((300, 250)))
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    fn test_play_anchored_macro() -> Result<()> {
+        let mac: Macro = ron::from_str(LOGIN_RON)?;
+        let mut control = ActionControl::with_cancel_token(&Settings::default(), CancelToken::new());
+        let options = PlaybackOptions {
+            params: HashMap::from([("user".to_string(), "ada".to_string())]),
+            speed: 4.0,
+            ..PlaybackOptions::default()
+        };
+        // no probe for the anchor
+        let report = control.play_macro(&mac, &options)?;
+        assert!(matches!(report.results[0].outcome, StepOutcome::Failed(_)));
+        assert_eq!(report.results.len(), 1);
+
+        control.set_screen_probe(Box::new(FixedProbe));
+        let mac = Macro {
+            steps: mac.steps[..1].to_vec(),
+            ..mac
+        };
+        let report = control.play_macro(&mac, &PlaybackOptions { loops: 2, ..options })?;
+        assert!(report.is_success());
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(control.enigo.location()?, (310, 270));
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+mod policy_test {
+    use anyhow::Result;
+    use enigo::Key;
+    use small_target_control::policy::{ActionContext, ActionPolicy, AutoApprover, ChannelApprover, PolicyDecision, PolicyGate, RiskRule, RuleVerdict, WindowRule};
+    use small_target_control::InputAction;
+
+    fn payment_policy() -> Result<ActionPolicy> {
+        Ok(ActionPolicy::new(vec![
+            RiskRule::text_pattern("card number", r"\b\d{4}[ -]?\d{4}[ -]?\d{4}[ -]?\d{4}\b", RuleVerdict::Deny)?,
+            RiskRule::hotkey("close window", "alt+f4", RuleVerdict::RequireApproval),
+            RiskRule::click_in_window("checkout", WindowRule::new(&[], &["checkout".to_string()]), RuleVerdict::RequireApproval),
+        ]))
+    }
+
+    #[test]
+    fn test_classify() -> Result<()> {
+        let policy = payment_policy()?;
+        let nowhere = ActionContext::default();
+
+        assert_eq!(policy.classify(&InputAction::WriteText("hello".to_string()), &nowhere), PolicyDecision::Allow);
+        assert_eq!(
+            policy.classify(&InputAction::WriteText("4111 1111 1111 1111".to_string()), &nowhere),
+            PolicyDecision::Deny { rule: "card number".to_string() }
+        );
+        assert_eq!(
+            policy.classify(&InputAction::Hotkey { hot_keys: vec![Key::F4, Key::Alt] }, &nowhere),
+            PolicyDecision::RequireApproval { rule: "close window".to_string() }
+        );
+        assert_eq!(policy.classify(&InputAction::Hotkey { hot_keys: vec![Key::Alt] }, &nowhere), PolicyDecision::Allow);
+
+        // the same combination split over a held key and a key click, or with extra keys
+        let mut held = ActionContext::default();
+        held.track(&InputAction::KeyDown(Key::Alt));
+        let close = PolicyDecision::RequireApproval { rule: "close window".to_string() };
+        assert_eq!(policy.classify(&InputAction::KeyClick(Key::F4), &held), close);
+        assert_eq!(
+            policy.classify(
+                &InputAction::Hotkey {
+                    hot_keys: vec![Key::Shift, Key::Alt, Key::F4]
+                },
+                &nowhere
+            ),
+            close
+        );
+        held.track(&InputAction::KeyUp(Key::Alt));
+        assert_eq!(policy.classify(&InputAction::KeyClick(Key::F4), &held), PolicyDecision::Allow);
+        let alt_click = InputAction::Click {
+            x: 10,
+            y: 10,
+            button: enigo::Button::Left,
+            modifiers: vec![Key::Alt],
+            count: 1,
+        };
+        assert_eq!(policy.classify(&alt_click, &ActionContext::default().with_held_keys(&[Key::F4])), close);
+
+        let click = InputAction::MouseLeftClick { x: 10, y: 10 };
+        assert_eq!(policy.classify(&click, &nowhere), PolicyDecision::Allow);
+        assert_eq!(
+            policy.classify(&click, &ActionContext::new("Firefox", "Shop - Checkout")),
+            PolicyDecision::RequireApproval { rule: "checkout".to_string() }
+        );
+        // a window that can't be read might be the checkout
+        assert_eq!(
+            policy.classify(&click, &ActionContext::unknown_window()),
+            PolicyDecision::RequireApproval { rule: "checkout".to_string() }
+        );
+        assert_eq!(policy.classify(&InputAction::Wait { milliseconds: 10 }, &ActionContext::unknown_window()), PolicyDecision::Allow);
+        Ok(())
+    }
+
+    #[test]
+    fn test_gate_with_auto_approver() -> Result<()> {
+        let hotkey = InputAction::Hotkey { hot_keys: vec![Key::Alt, Key::F4] };
+        let context = ActionContext::default();
+
+        let deny_gate = PolicyGate::new(payment_policy()?, Box::new(AutoApprover::deny()));
+        assert!(!deny_gate.authorize(&hotkey, &context)?);
+        assert!(deny_gate.authorize(&InputAction::Wait { milliseconds: 10 }, &context)?);
+
+        let allow_gate = PolicyGate::new(payment_policy()?, Box::new(AutoApprover::allow()));
+        assert!(allow_gate.authorize(&hotkey, &context)?);
+        // denied rules never reach the approver
+        assert!(!allow_gate.authorize(&InputAction::WriteText("4111-1111-1111-1111".to_string()), &context)?);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_async_gate_keeps_the_runtime_going_while_the_approver_waits() -> Result<()> {
+        let (approver, requests) = ChannelApprover::new();
+        let gate = PolicyGate::new(payment_policy()?, Box::new(approver));
+        let hotkey = InputAction::Hotkey { hot_keys: vec![Key::Alt, Key::F4] };
+        let context = ActionContext::default();
+
+        // a single threaded runtime would deadlock if the approver blocked it
+        let (answered, ()) = tokio::join!(gate.authorize_async(&hotkey, &context), async {
+            let (request, answer) = tokio::task::spawn_blocking(move || requests.recv().unwrap()).await.unwrap();
+            assert_eq!(request.rule, "close window");
+            answer.send(true).unwrap();
+        });
+        assert!(answered?);
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+mod recorder_test {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use enigo::{Axis, Button, Key};
+    use rdev::{Button as ListenButton, EventType, Key as ListenKey};
+    use small_target_control::{coalesce, CoalesceConfig, InputAction, RawEvent, Recording};
+
+    /// events `ms` apart from each other, in order
+    struct Events {
+        at: Duration,
+        events: Vec<RawEvent>,
+    }
+
+    impl Events {
+        fn new() -> Self {
+            Self { at: Duration::ZERO, events: Vec::new() }
+        }
+
+        fn after(&mut self, ms: u64, event_type: EventType) -> &mut Self {
+            self.named(ms, event_type, None)
+        }
+
+        fn named(&mut self, ms: u64, event_type: EventType, name: Option<&str>) -> &mut Self {
+            self.at += Duration::from_millis(ms);
+            self.events.push(RawEvent {
+                at: self.at,
+                event_type,
+                name: name.map(str::to_string),
+            });
+            self
+        }
+
+        fn move_to(&mut self, ms: u64, x: f64, y: f64) -> &mut Self {
+            self.after(ms, EventType::MouseMove { x, y })
+        }
+
+        fn click(&mut self, ms: u64, button: ListenButton) -> &mut Self {
+            self.after(ms, EventType::ButtonPress(button)).after(60, EventType::ButtonRelease(button))
+        }
+
+        fn key(&mut self, ms: u64, key: ListenKey, name: Option<&str>) -> &mut Self {
+            self.named(ms, EventType::KeyPress(key), name).after(30, EventType::KeyRelease(key))
+        }
+
+        fn actions(&self) -> Vec<InputAction> {
+            coalesce(&self.events, &CoalesceConfig::default()).into_iter().map(|a| a.action).collect()
+        }
+    }
+
+    #[test]
+    fn test_clicks_and_drags() {
+        let mut events = Events::new();
+        events.move_to(0, 100.0, 200.0).click(10, ListenButton::Left);
+        events.click(100, ListenButton::Left).click(100, ListenButton::Left).click(100, ListenButton::Left);
+        events.move_to(500, 300.0, 300.0).click(10, ListenButton::Left).click(100, ListenButton::Left);
+        events.click(600, ListenButton::Right);
+        events.after(500, EventType::ButtonPress(ListenButton::Left)).move_to(50, 350.0, 310.0).move_to(50, 400.0, 320.0).after(50, EventType::ButtonRelease(ListenButton::Left));
+        events.after(500, EventType::KeyPress(ListenKey::ShiftLeft)).click(50, ListenButton::Left).after(50, EventType::KeyRelease(ListenKey::ShiftLeft));
+        let actions = events.actions();
+        assert!(matches!(actions[0], InputAction::TripleClick { x: 100, y: 200 }), "{:?}", actions);
+        // a fourth quick click starts over
+        assert!(matches!(actions[1], InputAction::MouseLeftClick { x: 100, y: 200 }), "{:?}", actions);
+        assert!(matches!(actions[2], InputAction::MouseLeftDoubleClick { x: 300, y: 300 }), "{:?}", actions);
+        assert!(matches!(actions[3], InputAction::MouseRightClick { x: 300, y: 300 }));
+        assert!(matches!(actions[4], InputAction::Drag { x1: 300, y1: 300, x2: 400, y2: 320 }));
+        match &actions[5] {
+            InputAction::Click { x: 400, y: 320, button: Button::Left, modifiers, count: 1 } => assert_eq!(modifiers, &[Key::Shift]),
+            other => panic!("expected a shift-click, got {:?}", other),
+        }
+        assert_eq!(actions.len(), 6);
+    }
+
+    #[test]
+    fn test_typing_hotkeys_and_wheel() {
+        let mut events = Events::new();
+        events.key(0, ListenKey::ShiftLeft, None).key(0, ListenKey::KeyH, Some("h"));
+        events.named(100, EventType::KeyPress(ListenKey::ShiftLeft), None).key(10, ListenKey::KeyI, Some("I")).after(10, EventType::KeyRelease(ListenKey::ShiftLeft));
+        events.key(100, ListenKey::KeyX, Some("x")).key(100, ListenKey::Backspace, Some("\u{8}")).key(100, ListenKey::Return, Some("\r"));
+        events.after(100, EventType::KeyPress(ListenKey::ControlLeft)).key(20, ListenKey::KeyA, Some("\u{1}")).after(20, EventType::KeyRelease(ListenKey::ControlLeft));
+        events.key(100, ListenKey::Escape, Some("\u{1b}"));
+        events.move_to(100, 50.0, 60.0);
+        for _ in 0..3 {
+            events.after(50, EventType::Wheel { delta_x: 0, delta_y: -1 });
+        }
+        events.after(50, EventType::Wheel { delta_x: 0, delta_y: 1 });
+        events.key(3000, ListenKey::KeyA, Some("a"));
+        let actions = events.actions();
+        assert!(matches!(&actions[0], InputAction::WriteText(text) if text == "hI\n"), "{:?}", actions);
+        assert!(matches!(&actions[1], InputAction::Hotkey { hot_keys } if hot_keys == &[Key::Control, Key::Unicode('a')]));
+        assert!(matches!(actions[2], InputAction::KeyClick(Key::Escape)));
+        assert!(matches!(actions[3], InputAction::Scroll { x: 50, y: 60, length: 3, direction: Axis::Vertical, .. }));
+        assert!(matches!(actions[4], InputAction::Scroll { length: -1, .. }));
+        assert!(matches!(&actions[5], InputAction::WriteText(text) if text == "a"));
+        assert_eq!(actions.len(), 6);
+    }
+
+    #[test]
+    fn test_hover_and_replay_timing() -> Result<()> {
+        let mut events = Events::new();
+        events.move_to(0, 10.0, 10.0).move_to(20, 40.0, 40.0);
+        events.key(1000, ListenKey::KeyQ, Some("q"));
+        events.move_to(50, 80.0, 80.0).click(2000, ListenButton::Left);
+        let recorded = coalesce(&events.events, &CoalesceConfig::default());
+        let actions: Vec<&InputAction> = recorded.iter().map(|a| &a.action).collect();
+        assert!(matches!(actions[0], InputAction::MouseMove { x: 40, y: 40 }), "{:?}", actions);
+        assert!(matches!(actions[1], InputAction::WriteText(_)));
+        // resting before the click doesn't add a move
+        assert!(matches!(actions[2], InputAction::MouseLeftClick { x: 80, y: 80 }));
+        assert_eq!(actions.len(), 3);
+
+        let recording = Recording { actions: recorded };
+        let plan = recording.to_plan();
+        assert_eq!(plan.steps[0].delay, Duration::ZERO);
+        assert_eq!(plan.steps[1].delay, Duration::from_millis(1000));
+        assert_eq!(plan.steps[2].delay, Duration::from_millis(2080));
+
+        let path = std::env::temp_dir().join(format!("recording-{}.json", std::process::id()));
+        recording.save(&path)?;
+        let loaded = Recording::load(&path)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(loaded.actions.len(), 3);
+        assert_eq!(loaded.actions[2].at, recording.actions[2].at);
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+mod common;
+
+mod scroll_test {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use enigo::{Axis, Settings};
+    use small_target_control::{ActionControl, CancelToken, InputAction, ScreenProbe, ScrollSettings, ScrollTarget, ScrollUnit};
+
+    use crate::common::inputs;
+
+    #[test]
+    fn test_scroll_units() {
+        let settings = ScrollSettings::default();
+        assert_eq!(settings.to_notches(3, ScrollUnit::Notches), 3);
+        assert_eq!(settings.to_notches(-9, ScrollUnit::Lines), -3);
+        assert_eq!(settings.to_notches(10, ScrollUnit::Pixels), 1);
+        assert_eq!(settings.to_notches(500, ScrollUnit::Pixels), 10);
+        assert_eq!(settings.to_notches(-2, ScrollUnit::Pages), -20);
+        assert_eq!(settings.to_notches(1000, ScrollUnit::Notches), 50);
+        assert_eq!(settings.to_notches(0, ScrollUnit::Lines), 0);
+
+        assert_eq!(settings.chunks(-3), vec![-1, -1, -1]);
+        let settings = ScrollSettings { chunk: 2, ..ScrollSettings::default() };
+        assert_eq!(settings.chunks(5), vec![2, 2, 1]);
+        assert_eq!(ScrollSettings::instant().chunks(5), vec![5]);
+    }
+
+    #[test]
+    fn test_parse_scroll() -> Result<()> {
+        let action = InputAction::new("scroll".to_string(), inputs(&[("start_box", "[10,20]"), ("direction", "up")]))?;
+        assert!(matches!(action, InputAction::Scroll { length: -5, direction: Axis::Vertical, unit: ScrollUnit::Notches, .. }));
+        let action = InputAction::new("scroll".to_string(), inputs(&[("start_box", "[10,20]"), ("direction", "right")]))?;
+        assert!(matches!(action, InputAction::Scroll { length: 3, direction: Axis::Horizontal, .. }));
+        let action = InputAction::new("scroll".to_string(), inputs(&[("start_box", "[10,20]"), ("direction", "down"), ("length", "300"), ("unit", "px")]))?;
+        assert!(matches!(action, InputAction::Scroll { length: 300, unit: ScrollUnit::Pixels, .. }));
+        assert!(InputAction::new("scroll".to_string(), inputs(&[("start_box", "[10,20]"), ("direction", "down"), ("unit", "pages")])).is_err());
+        assert!(InputAction::new("scroll".to_string(), inputs(&[("start_box", "[10,20]"), ("direction", "down"), ("unit", "miles")])).is_err());
+
+        let action = InputAction::new("scroll_until".to_string(), inputs(&[("start_box", "[10,20]"), ("direction", "down")]))?;
+        assert!(matches!(action, InputAction::ScrollUntil { step: 5, until: ScrollTarget::Stable, max_scrolls: 20, .. }));
+        let action = InputAction::new("scroll_until".to_string(), inputs(&[("start_box", "[10,20]"), ("direction", "down"), ("target", "Submit order")]))?;
+        assert!(matches!(action, InputAction::ScrollUntil { until: ScrollTarget::Text(ref text), .. } if text == "Submit order"));
+        let action = InputAction::new("scroll_until".to_string(), inputs(&[("start_box", "[10,20]"), ("direction", "down"), ("target", "icons/logo.PNG")]))?;
+        assert!(matches!(action, InputAction::ScrollUntil { until: ScrollTarget::Template(ref path), .. } if path.ends_with("logo.PNG")));
+
+        // recorded before units existed
+        let old: InputAction = serde_json::from_str(r#"{"type":"Scroll","data":{"x":1,"y":2,"length":-3,"direction":"Vertical"}}"#)?;
+        assert!(matches!(old, InputAction::Scroll { length: -3, unit: ScrollUnit::Notches, .. }));
+        Ok(())
+    }
+
+    /// the page moves for `pages` scrolls, the target shows up after `found_after` of them
+    struct FakeProbe {
+        looks: u32,
+        pages: u32,
+        found_after: Option<u32>,
+    }
+
+    impl ScreenProbe for FakeProbe {
+        fn fingerprint(&mut self, _point: (i32, i32)) -> Result<u64> {
+            self.looks += 1;
+            Ok(if self.looks > self.pages { u64::MAX } else { self.looks as u64 })
+        }
+
+        fn locate(&mut self, _target: &ScrollTarget) -> Result<Option<(i32, i32)>> {
+            Ok(self.found_after.filter(|after| self.looks > *after).map(|_| (7, 8)))
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    fn test_scroll_until() -> Result<()> {
+        let mut control = ActionControl::with_cancel_token(&Settings::default(), CancelToken::new());
+        control.scroll.settle = Duration::ZERO;
+        let until = |until: ScrollTarget| InputAction::ScrollUntil {
+            x: 10,
+            y: 10,
+            step: 1,
+            direction: Axis::Vertical,
+            until,
+            max_scrolls: 10,
+        };
+        assert!(control.handle_action(until(ScrollTarget::Stable)).is_err());
+
+        control.set_screen_probe(Box::new(FakeProbe { looks: 0, pages: 3, found_after: None }));
+        control.handle_action(until(ScrollTarget::Stable))?;
+
+        control.set_screen_probe(Box::new(FakeProbe { looks: 0, pages: 5, found_after: Some(2) }));
+        control.handle_action(until(ScrollTarget::Text("Submit".to_string())))?;
+        assert_eq!(control.take_located(), Some((7, 8)));
+
+        control.set_screen_probe(Box::new(FakeProbe { looks: 0, pages: 2, found_after: None }));
+        assert!(control.handle_action(until(ScrollTarget::Text("Submit".to_string()))).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+mod common;
+
+mod selection_test {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use enigo::{Key, Settings};
+    use small_target_control::{ActionControl, CancelToken, InputAction, RegionCapture, ScreenProbe, ScrollTarget, SelectMode, VerifyConfig};
+
+    use crate::common::inputs;
+
+    /// a screen where clicking never selects anything, counts how often it was compared
+    struct UnchangedScreen {
+        compared: Arc<Mutex<u32>>,
+    }
+
+    impl ScreenProbe for UnchangedScreen {
+        fn fingerprint(&mut self, _point: (i32, i32)) -> Result<u64> {
+            Ok(0)
+        }
+
+        fn locate(&mut self, _target: &ScrollTarget) -> Result<Option<(i32, i32)>> {
+            Ok(None)
+        }
+
+        fn capture_region(&mut self, point: (i32, i32), _radius: u32) -> Result<RegionCapture> {
+            Ok(RegionCapture {
+                x: point.0,
+                y: point.1,
+                width: 0,
+                height: 0,
+                rgba: Vec::new(),
+            })
+        }
+
+        fn changed_since(&mut self, _before: &RegionCapture, _threshold: u8) -> Result<f64> {
+            *self.compared.lock().unwrap() += 1;
+            Ok(0.0)
+        }
+    }
+
+    #[test]
+    fn test_parse_selection_actions() -> Result<()> {
+        assert!(matches!(
+            InputAction::new("select".to_string(), inputs(&[("start_box", "[10,20]"), ("end_box", "[30,40]")]))?,
+            InputAction::Select { x1: 10, y1: 20, x2: 30, y2: 40 }
+        ));
+        assert!(matches!(
+            InputAction::new("triple_click".to_string(), inputs(&[("start_box", "[5,6]")]))?,
+            InputAction::TripleClick { x: 5, y: 6 }
+        ));
+        assert!(matches!(InputAction::new("select_all".to_string(), HashMap::new())?, InputAction::SelectAll));
+        assert!(InputAction::new("select".to_string(), inputs(&[("start_box", "[10,20]")])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    fn test_select_keeps_held_shift() -> Result<()> {
+        let mut control = ActionControl::with_cancel_token(&Settings::default(), CancelToken::new());
+        assert_eq!(control.select_mode, SelectMode::ShiftClick);
+        control.handle_action(InputAction::KeyDown(Key::Shift))?;
+        control.handle_action(InputAction::Select { x1: 5, y1: 5, x2: 50, y2: 5 })?;
+        assert_eq!(control.held_keys(), &[Key::Shift]);
+        control.release_all()?;
+
+        control.select_mode = SelectMode::Drag;
+        control.handle_action(InputAction::Select { x1: 5, y1: 5, x2: 50, y2: 5 })?;
+        control.handle_action(InputAction::TripleClick { x: 5, y: 5 })?;
+        control.handle_action(InputAction::SelectAll)?;
+        assert!(control.held_keys().is_empty());
+        assert!(control.held_buttons().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    fn test_select_drags_when_shift_click_selects_nothing() -> Result<()> {
+        let compared = Arc::new(Mutex::new(0));
+        let mut control = ActionControl::with_cancel_token(&Settings::default(), CancelToken::new());
+        control.set_screen_probe(Box::new(UnchangedScreen { compared: compared.clone() }));
+        control.verify = Some(VerifyConfig {
+            settle: Duration::from_millis(200),
+            ..VerifyConfig::default()
+        });
+        control.handle_action(InputAction::Select { x1: 5, y1: 5, x2: 50, y2: 5 })?;
+        assert!(*compared.lock().unwrap() > 0);
+        assert!(control.held_keys().is_empty());
+        assert!(control.held_buttons().is_empty());
+        Ok(())
+    }
+}
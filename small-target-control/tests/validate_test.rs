@@ -0,0 +1,65 @@
+mod validate_test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use enigo::{Button, Key};
+    use small_target_control::{correction_prompt, parse_error_prompt, ActionValidator, InputAction, ScreenRect, Violation};
+
+    fn validator() -> ActionValidator {
+        // a laptop screen with a second monitor to its left
+        ActionValidator::new(vec![ScreenRect::new(0, 0, 1920, 1080), ScreenRect::new(-1280, 0, 1280, 1024)])
+    }
+
+    #[test]
+    fn test_points_on_monitors() {
+        let validator = validator();
+        assert!(validator.validate(&InputAction::MouseLeftClick { x: 1919, y: 1079 }).is_empty());
+        assert!(validator.validate(&InputAction::MouseLeftClick { x: -1280, y: 1000 }).is_empty());
+        assert_eq!(validator.validate(&InputAction::MouseLeftClick { x: 1920, y: 10 }), vec![Violation::OffScreen { x: 1920, y: 10 }]);
+        // below the shorter left monitor
+        assert_eq!(validator.validate(&InputAction::ButtonDown { x: -5, y: 1050, button: Button::Left }), vec![Violation::OffScreen { x: -5, y: 1050 }]);
+        assert_eq!(
+            validator.validate(&InputAction::Drag { x1: 10, y1: 10, x2: 11, y2: 11 }),
+            vec![Violation::SamePoint { x: 10, y: 10 }]
+        );
+        assert_eq!(
+            validator.validate(&InputAction::Select { x1: 10, y1: 10, x2: 5000, y2: 10 }),
+            vec![Violation::OffScreen { x: 5000, y: 10 }]
+        );
+        assert!(ActionValidator::new(Vec::new()).validate(&InputAction::MouseMove { x: 99999, y: 0 }).is_empty());
+    }
+
+    #[test]
+    fn test_limits() -> Result<()> {
+        let mut validator = validator();
+        assert_eq!(
+            validator.validate(&InputAction::Wait { milliseconds: 3_600_000 }),
+            vec![Violation::WaitTooLong { ms: 3_600_000, max_ms: 60_000 }]
+        );
+        assert!(validator.validate(&InputAction::WriteText("a".repeat(10_000))).is_empty());
+        assert_eq!(
+            validator.validate(&InputAction::SetClipboard("é".repeat(10_001))),
+            vec![Violation::TextTooLong { len: 10_001, max: 10_000 }]
+        );
+        let hold = InputAction::KeyHold { key: Key::Space, ms: 20_000 };
+        assert_eq!(validator.validate(&hold), vec![Violation::HoldTooLong { ms: 20_000, max_ms: 10_000 }]);
+        validator.limits.max_hold = Duration::from_secs(30);
+        assert!(validator.validate(&hold).is_empty());
+
+        let unknown = InputAction::new("hotkey".to_string(), HashMap::from([("key".to_string(), "ctrl+frobnicate".to_string())]))?;
+        assert_eq!(validator.validate(&unknown), vec![Violation::UnknownKey]);
+        assert_eq!(validator.validate(&InputAction::Hotkey { hot_keys: Vec::new() }), vec![Violation::NoKeys]);
+
+        let action = InputAction::Wait { milliseconds: 3_600_000 };
+        let prompt = correction_prompt(&action, &validator.validate(&action));
+        assert!(prompt.contains("was not executed"));
+        assert!(prompt.contains("- waiting 3600000 ms is longer than the allowed 60000 ms"));
+
+        let inputs = HashMap::from([("start_box".to_string(), "[]".to_string())]);
+        let error = InputAction::new("click".to_string(), inputs.clone()).unwrap_err();
+        let prompt = parse_error_prompt("click", &inputs, &error);
+        assert!(prompt.starts_with("The action click(start_box='[]') was not executed:\n- "));
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+mod verify_test {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+    use enigo::Settings;
+    use small_target_control::{watched_point, ActionControl, ActionPlan, InputAction, RegionCapture, ScreenProbe, ScrollTarget, StepOutcome, VerifyConfig};
+
+    /// Reports a change only for captures taken at `reacts_at`, and records every capture point.
+    struct FakeProbe {
+        reacts_at: Option<(i32, i32)>,
+        captured: Arc<Mutex<Vec<(i32, i32)>>>,
+    }
+
+    impl ScreenProbe for FakeProbe {
+        fn fingerprint(&mut self, _point: (i32, i32)) -> Result<u64> {
+            Ok(0)
+        }
+
+        fn locate(&mut self, _target: &ScrollTarget) -> Result<Option<(i32, i32)>> {
+            Ok(None)
+        }
+
+        fn capture_region(&mut self, point: (i32, i32), _radius: u32) -> Result<RegionCapture> {
+            self.captured.lock().unwrap().push(point);
+            Ok(RegionCapture {
+                x: point.0,
+                y: point.1,
+                width: 0,
+                height: 0,
+                rgba: Vec::new(),
+            })
+        }
+
+        fn changed_since(&mut self, before: &RegionCapture, _threshold: u8) -> Result<f64> {
+            Ok(if Some((before.x, before.y)) == self.reacts_at {
+                0.5
+            } else {
+                0.0
+            })
+        }
+    }
+
+    #[test]
+    fn test_nudge_offsets() {
+        // reporting only unless retries are asked for
+        assert_eq!(VerifyConfig::default().retries, 0);
+        let config = VerifyConfig { nudge: 3, ..VerifyConfig::default() };
+        let offsets: Vec<_> = (0..6).map(|attempt| config.offset(attempt)).collect();
+        assert_eq!(offsets, vec![(0, 0), (3, 0), (0, 3), (-3, 0), (0, -3), (6, 0)]);
+
+        assert_eq!(watched_point(&InputAction::MouseRightClick { x: 4, y: 5 }), Some((4, 5)));
+        assert_eq!(watched_point(&InputAction::MouseMove { x: 4, y: 5 }), None);
+        assert_eq!(watched_point(&InputAction::WriteText("a".to_string())), None);
+    }
+
+    #[test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    fn test_verified_clicks() -> Result<()> {
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let mut control = ActionControl::new(&Settings::default());
+        control.verify = Some(VerifyConfig {
+            retries: 2,
+            nudge: 5,
+            settle: std::time::Duration::ZERO,
+            ..VerifyConfig::default()
+        });
+
+        // the second, nudged click hits
+        control.set_screen_probe(Box::new(FakeProbe {
+            reacts_at: Some((105, 100)),
+            captured: captured.clone(),
+        }));
+        let report = control.run_plan(&ActionPlan::from_actions([InputAction::MouseMiddleClick { x: 100, y: 100 }], Default::default()));
+        assert!(report.is_success());
+        assert_eq!(*captured.lock().unwrap(), vec![(100, 100), (105, 100)]);
+
+        // nothing reacts, later steps are skipped
+        captured.lock().unwrap().clear();
+        control.set_screen_probe(Box::new(FakeProbe {
+            reacts_at: None,
+            captured: captured.clone(),
+        }));
+        let report = control.run_plan(&ActionPlan::from_actions(
+            [InputAction::MouseMiddleClick { x: 100, y: 100 }, InputAction::MouseMove { x: 0, y: 0 }],
+            Default::default(),
+        ));
+        let (index, failed) = report.failure().unwrap();
+        assert_eq!((index, &failed.outcome), (0, &StepOutcome::NoVisibleEffect));
+        assert_eq!(report.skipped(), 1);
+        assert_eq!(captured.lock().unwrap().len(), 3);
+        Ok(())
+    }
+}
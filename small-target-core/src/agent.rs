@@ -0,0 +1,631 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use image::DynamicImage;
+use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, ChatCompletionRequest, Content, MessageRole};
+use small_target_control::{correction_prompt, lookup_action, parse_error_prompt, ActionContext, ActionControl, ActionKind, ActionPlan, ActionValidator, CancelToken, InputAction, PlanStep, PolicyGate, ScreenRect, StepOutcome, VerifyConfig};
+use small_target_image::{dhash, image_resize, image_to_base64};
+use small_target_llm::action_parser::ActionParsed;
+use small_target_llm::openai_request::{image_message, text_message};
+use small_target_llm::usage::{estimate_image_tokens, estimate_text_tokens};
+use small_target_llm::{
+    digest_messages, parse_action_vlm_with, parse_tool_calls, sample_completions, stream_prediction_with, vote, Budget, BudgetExceeded, CacheConfig, CacheKey, CacheMode, ClientConfig, LlmClient, ModelPricing, ModelProfile, OpenAiProtocalCallPayload, PredictionParsed,
+    Grounder, PlannedStep, Planner, PromptRegistry, ProviderConfig, PromptVars, RenderedPrompt, ResponseCache, StreamEvent, StreamingActionParser, TokenUsage, ToolSpec, UsageTracker, VotingConfig, MAX_PIXELS,
+};
+use small_target_vision::{focused_window, SafeMonitor};
+use tokio::sync::broadcast;
+
+use crate::agent_handle::{AgentHandle, StepSnapshot};
+use crate::screen_probe::{screen_rect, MonitorProbe};
+use crate::tools::action_tools;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionMode {
+    /// `Action: click(start_box='...')` text, the UI-TARS format
+    Text,
+    /// actions offered as function tools and read from `tool_calls`, for general purpose models
+    ToolCalling,
+}
+
+pub struct AgentConfig {
+    pub model_name: String,
+    /// parser mode and coordinate format of the model, guessed from `model_name`
+    pub profile: ModelProfile,
+    /// prompt language, a code like "zh" or a language name
+    pub language: String,
+    /// template name in `prompts`, e.g. "computer" or "browser"
+    pub prompt_template: String,
+    pub prompts: PromptRegistry,
+    pub extra_notes: Vec<String>,
+    /// overrides the template's action space, `None` keeps its default: the executor's action schema, or the
+    /// part of it the template's model needs, e.g. only where to point for the pipeline's grounder
+    pub action_space: Option<String>,
+    pub max_steps: usize,
+    pub temperature: f64,
+    pub top_p: f64,
+    pub max_tokens: i64,
+    pub action_mode: ActionMode,
+    /// stream the response and show the thought as it arrives, text mode only
+    pub stream: bool,
+    /// endpoint, timeouts, retries and rate limits
+    pub client: ClientConfig,
+    /// limits that end the run with `AgentOutcome::BudgetExhausted`
+    pub budget: Budget,
+    /// used for the cost figures and the cost budget
+    pub pricing: Option<ModelPricing>,
+    /// a second model that plans each step in words, the model above then only grounds it;
+    /// `action_mode`, `stream` and `prompt_template` don't apply in that case
+    pub planner: Option<ProviderConfig>,
+    /// sample several answers per step and act on the consensus, text mode without streaming only
+    pub voting: Option<VotingConfig>,
+    /// replay recorded responses for identical screens, text mode without voting only
+    pub cache: Option<CacheConfig>,
+    /// pause between the actions of one response
+    pub action_delay: Duration,
+    /// check that clicks change the screen and report the ones that don't, overrides the executor's setting
+    pub verify: Option<VerifyConfig>,
+}
+
+impl AgentConfig {
+    pub fn new(base_url: &str, model_name: &str, api_key: &str) -> Self {
+        Self {
+            model_name: model_name.to_string(),
+            profile: ModelProfile::for_model(model_name),
+            language: "en".to_string(),
+            prompt_template: "computer".to_string(),
+            prompts: PromptRegistry::builtin(),
+            extra_notes: Vec::new(),
+            action_space: None,
+            max_steps: 50,
+            temperature: 0.0,
+            top_p: 0.7,
+            max_tokens: 1000,
+            action_mode: ActionMode::Text,
+            stream: false,
+            client: ClientConfig::new(base_url, api_key),
+            budget: Budget::default(),
+            pricing: None,
+            planner: None,
+            voting: None,
+            cache: None,
+            action_delay: Duration::from_millis(100),
+            verify: None,
+        }
+    }
+
+    /// This config's model as the grounder of a planner/grounder pipeline.
+    pub fn grounder_config(&self) -> ProviderConfig {
+        ProviderConfig {
+            client: self.client.clone(),
+            model_name: self.model_name.clone(),
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            profile: self.profile.clone(),
+            vision: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentOutcome {
+    Finished,
+    CallUser,
+    Cancelled,
+    MaxSteps,
+    BudgetExhausted(BudgetExceeded),
+}
+
+struct Pipeline {
+    planner: Planner,
+    grounder: Grounder,
+    planner_prompt: String,
+}
+
+/// Screenshot -> model -> parsed actions -> input, until the model says it is done.
+pub struct Agent {
+    config: AgentConfig,
+    client: LlmClient,
+    monitor: SafeMonitor,
+    /// shared with the blocking thread a plan runs on
+    control: Arc<Mutex<ActionControl>>,
+    policy: Option<PolicyGate>,
+    validator: ActionValidator,
+    handle: AgentHandle,
+    events: broadcast::Sender<StreamEvent>,
+    history: Vec<ChatCompletionMessage>,
+    usage: UsageTracker,
+    system_prompt: Option<RenderedPrompt>,
+    tools: Vec<ToolSpec>,
+    pipeline: Option<Pipeline>,
+    cache: Option<ResponseCache>,
+    /// size of the last screenshot sent, after resizing
+    image_size: (u32, u32),
+}
+
+impl Agent {
+    pub fn new(config: AgentConfig, monitor: SafeMonitor, mut control: ActionControl) -> Result<Self> {
+        if !control.has_screen_probe() {
+            control.set_screen_probe(Box::new(MonitorProbe::new(monitor.clone())));
+        }
+        if config.verify.is_some() {
+            control.verify = config.verify;
+        }
+        let handle = AgentHandle::new(control.cancel_token.clone());
+        let pipeline = match &config.planner {
+            Some(planner) => Some(Pipeline {
+                planner: Planner::new(planner.clone())?,
+                grounder: Grounder::new(config.grounder_config())?,
+                planner_prompt: String::new(),
+            }),
+            None => None,
+        };
+        let cache = config.cache.clone().map(ResponseCache::new).transpose()?;
+        Ok(Self {
+            client: LlmClient::new(config.client.clone())?,
+            validator: ActionValidator::new(vec![screen_rect(&monitor)]),
+            monitor,
+            control: Arc::new(Mutex::new(control)),
+            policy: None,
+            handle,
+            events: broadcast::channel(256).0,
+            history: Vec::new(),
+            usage: UsageTracker::new(config.pricing, config.budget),
+            system_prompt: None,
+            tools: action_tools(&config.profile),
+            pipeline,
+            cache,
+            config,
+            image_size: (0, 0),
+        })
+    }
+
+    pub fn with_policy(mut self, policy: PolicyGate) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Replaces the default check against the agent's monitor and `ValidationLimits::default()`.
+    pub fn with_validator(mut self, validator: ActionValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// The token shared with `ActionControl`, hand it to a `KillSwitch` or cancel it directly.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.control.lock().unwrap().cancel_token.clone()
+    }
+
+    /// Pause, step or redirect the agent from another task or thread.
+    pub fn handle(&self) -> AgentHandle {
+        self.handle.clone()
+    }
+
+    /// Thought text and parsed actions of every model response, as they arrive.
+    pub fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
+        self.events.subscribe()
+    }
+
+    /// Token, image and cost figures of the current or last run, per step and in total.
+    pub fn usage(&self) -> &UsageTracker {
+        &self.usage
+    }
+
+    /// `name@version` of the prompt used by the current or last run.
+    pub fn prompt_version(&self) -> Option<&str> {
+        self.system_prompt.as_ref().map(|p| p.version_id.as_str())
+    }
+
+    fn render_system_prompt(&mut self) -> Result<RenderedPrompt> {
+        let mut vars = PromptVars::new(&self.config.language);
+        vars.extra_notes = self.config.extra_notes.clone();
+        vars.action_space = self.config.action_space.clone();
+        if let Some(pipeline) = self.pipeline.as_mut() {
+            let planner_prompt = self.config.prompts.render("planner", &vars)?;
+            let mut grounding = self.config.prompts.render("grounding", &vars)?;
+            grounding.version_id = format!("{}+{}", planner_prompt.version_id, grounding.version_id);
+            pipeline.planner_prompt = planner_prompt.text;
+            pipeline.planner.reset();
+            return Ok(grounding);
+        }
+        if self.config.action_mode == ActionMode::ToolCalling {
+            vars.extra_notes.push("Perform the action by calling exactly one of the provided tools, write the thought as the message text.".to_string());
+        }
+        self.config.prompts.render(&self.config.prompt_template, &vars)
+    }
+
+    /// A user message for the model, and a progress note for the planner.
+    fn tell_model(&mut self, text: String) {
+        if let Some(pipeline) = self.pipeline.as_mut() {
+            pipeline.planner.note(&text);
+        }
+        self.history.push(text_message(MessageRole::user, text));
+    }
+
+    pub async fn run(&mut self, instruction: &str) -> Result<AgentOutcome> {
+        let outcome = self.run_steps(instruction).await;
+        // a stopped, finished or failed run must not leave keys or buttons held
+        if let Err(e) = self.control.lock().unwrap().release_all() {
+            log::warn!("{}", e);
+        }
+        outcome
+    }
+
+    async fn run_steps(&mut self, instruction: &str) -> Result<AgentOutcome> {
+        self.history.clear();
+        let system_prompt = self.render_system_prompt()?;
+        log::info!("running with prompt {}", system_prompt.version_id);
+        self.system_prompt = Some(system_prompt);
+        self.usage = UsageTracker::new(self.config.pricing, self.config.budget);
+        let cancel_token = self.cancel_token();
+        cancel_token.reset();
+
+        for step in 0..self.config.max_steps {
+            if cancel_token.is_cancelled() {
+                return Ok(AgentOutcome::Cancelled);
+            }
+            if let Err(exceeded) = self.usage.check_budget() {
+                log::warn!("stopping after {} step(s): {}", step, exceeded);
+                return Ok(AgentOutcome::BudgetExhausted(exceeded));
+            }
+            for text in self.handle.take_injected() {
+                self.tell_model(format!("Additional instruction from the user: {}", text));
+            }
+            let (screenshot, predictions) = tokio::select! {
+                result = self.predict(instruction) => result?,
+                _ = cancel_token.cancelled() => return Ok(AgentOutcome::Cancelled),
+            };
+            log::info!("step {}: {} action(s)", step, predictions.len());
+
+            // the response's actions run together once each was approved, the model's last word comes after them
+            let mut plan = ActionPlan::new();
+            plan.release_at_end = false;
+            let mut ending = None;
+            // actions run after all of them are approved, so they share the window focused now and
+            // see the keys held by the ones queued before them
+            let mut context: Option<ActionContext> = None;
+            for prediction in predictions {
+                if cancel_token.is_cancelled() {
+                    return Ok(AgentOutcome::Cancelled);
+                }
+                if prediction.action_parsed.action_type.is_empty() {
+                    log::warn!("no action parsed, thought: {}", prediction.thought);
+                    continue;
+                }
+                match lookup_action(&prediction.action_parsed.action_type).map(|spec| spec.kind) {
+                    Some(ActionKind::Finished) => {
+                        ending = Some(AgentOutcome::Finished);
+                        break;
+                    }
+                    Some(ActionKind::CallUser) => {
+                        ending = Some(AgentOutcome::CallUser);
+                        break;
+                    }
+                    _ => {}
+                }
+                let action = match to_input_action(&prediction, screen_rect(&self.monitor)) {
+                    Ok(action) => action,
+                    Err(e) => {
+                        log::warn!("unusable action {:?}: {:#}", prediction.action_parsed, e);
+                        self.tell_model(parse_error_prompt(&prediction.action_parsed.action_type, &prediction.action_parsed.action_inputs, &e));
+                        continue;
+                    }
+                };
+                self.handle.publish(StepSnapshot {
+                    step,
+                    screenshot: screenshot.clone(),
+                    prediction: prediction.clone(),
+                    pending_action: action.clone(),
+                    prompt_version: self.prompt_version().unwrap_or_default().to_string(),
+                });
+                tokio::select! {
+                    _ = self.handle.wait_turn() => {}
+                    _ = cancel_token.cancelled() => return Ok(AgentOutcome::Cancelled),
+                }
+                if cancel_token.is_cancelled() {
+                    return Ok(AgentOutcome::Cancelled);
+                }
+                let action = self.handle.take_edited_action().unwrap_or(action);
+                let violations = self.validator.validate(&action);
+                if !violations.is_empty() {
+                    log::warn!("invalid action {:?}: {:?}", action, violations);
+                    self.tell_model(correction_prompt(&action, &violations));
+                    continue;
+                }
+                let allowed = match &self.policy {
+                    Some(policy) => {
+                        let context = context.get_or_insert_with(|| focused_context().with_held_keys(self.control.lock().unwrap().held_keys()));
+                        let allowed = tokio::select! {
+                            allowed = policy.authorize_async(&action, context) => allowed?,
+                            _ = cancel_token.cancelled() => return Ok(AgentOutcome::Cancelled),
+                        };
+                        if allowed {
+                            context.track(&action);
+                        }
+                        allowed
+                    }
+                    None => true,
+                };
+                if !allowed {
+                    self.tell_model(format!("The action {:?} was rejected, choose another way.", action));
+                    continue;
+                }
+                let delay = if plan.is_empty() { Duration::ZERO } else { self.config.action_delay };
+                plan = plan.then_step(PlanStep::new(action).with_delay(delay));
+            }
+            if !plan.is_empty() {
+                // input injection sleeps between moves and keys, keep it off the runtime's worker threads
+                let control = self.control.clone();
+                let steps = plan.clone();
+                let report = tokio::task::spawn_blocking(move || control.lock().unwrap().run_plan(&steps)).await?;
+                if report.is_cancelled() {
+                    return Ok(AgentOutcome::Cancelled);
+                }
+                if let Some((index, failed)) = report.failure() {
+                    log::warn!("action {} of {} {}", index + 1, plan.len(), failed.outcome);
+                    self.tell_model(match failed.outcome {
+                        StepOutcome::NoVisibleEffect => format!(
+                            "Your last action {:?} had no visible effect, {} later action(s) were skipped. Check that it targets the right element.",
+                            failed.action,
+                            report.skipped()
+                        ),
+                        _ => format!("The action {:?} {}, {} later action(s) were skipped.", failed.action, failed.outcome, report.skipped()),
+                    });
+                }
+                let (located, clipboard_text) = {
+                    let mut control = self.control.lock().unwrap();
+                    (control.take_located(), control.take_clipboard_text())
+                };
+                if let Some(point) = located {
+                    let (x, y) = to_model_point(point, screen_rect(&self.monitor), &self.config.profile, self.image_size);
+                    self.tell_model(format!("The scroll target is now visible at ({},{}).", x, y));
+                }
+                if let Some(text) = clipboard_text {
+                    self.tell_model(format!("Clipboard content: {}", text));
+                }
+            }
+            if let Some(outcome) = ending {
+                return Ok(outcome);
+            }
+        }
+        Ok(AgentOutcome::MaxSteps)
+    }
+
+    async fn predict(&mut self, instruction: &str) -> Result<(DynamicImage, Vec<PredictionParsed>)> {
+        let screenshot = self.monitor.capture_image().await?;
+        let resized = image_resize(screenshot.clone(), MAX_PIXELS)?;
+        let image_size = (resized.width(), resized.height());
+        self.image_size = image_size;
+        let screen_hash = dhash(&resized);
+        let image_base64 = image_to_base64(resized)?;
+        if self.pipeline.is_some() {
+            let predictions = self.plan_and_ground(instruction, image_base64, image_size).await?;
+            return Ok((screenshot, predictions));
+        }
+
+        let system_prompt = self.system_prompt.as_ref().map(|p| p.text.as_str()).unwrap_or_default();
+        let prompt = format!("{}{}", system_prompt, instruction);
+        let mut history = self.history.clone();
+        history.push(image_message(image_base64));
+        // used when the server doesn't report usage
+        let prompt_estimate = history
+            .iter()
+            .filter_map(|m| match &m.content {
+                Content::Text(text) => Some(estimate_text_tokens(text)),
+                _ => None,
+            })
+            .sum::<u64>()
+            + estimate_text_tokens(&prompt)
+            + estimate_image_tokens(image_size.0, image_size.1);
+
+        let payload = OpenAiProtocalCallPayload::new(
+            self.config.client.base_url.clone(),
+            self.config.model_name.clone(),
+            self.config.client.api_key.clone(),
+            vec![text_message(MessageRole::user, prompt)],
+            history,
+            self.config.temperature,
+            self.config.top_p,
+            self.config.max_tokens,
+        );
+        let request = payload.into_request();
+        let cache_key = match &self.cache {
+            Some(cache) if cache.mode() != CacheMode::Off && self.config.action_mode == ActionMode::Text && self.config.voting.is_none() => Some(CacheKey {
+                model: self.config.model_name.clone(),
+                prompt_version: self.prompt_version().unwrap_or_default().to_string(),
+                screen_hash,
+                history_digest: digest_messages(&request.messages),
+            }),
+            _ => None,
+        };
+        let cached = match (&self.cache, &cache_key) {
+            (Some(cache), Some(key)) => match cache.get(key) {
+                None if cache.mode() == CacheMode::ReadOnly => return Err(anyhow!("no cached response for screen {:016x} in read-only cache mode", key.screen_hash)),
+                cached => cached,
+            },
+            _ => None,
+        };
+        let started = Instant::now();
+        let mut requests = 1;
+        let (text, predictions, usage) = if let Some(cached) = cached {
+            let predictions = parse_action_vlm_with(&cached.text, &self.config.profile.decoder(image_size), &self.config.profile.mode);
+            let _ = self.events.send(StreamEvent::ActionsReady(predictions.clone()));
+            let _ = self.events.send(StreamEvent::Done(cached.text.clone()));
+            // nothing was sent
+            requests = 0;
+            (cached.text, predictions, Some(TokenUsage::default()))
+        } else if self.config.action_mode == ActionMode::ToolCalling {
+            let response = self.client.chat_completion_with_tools(&request, &self.tools).await?;
+            let message = &response.choices.first().ok_or_else(|| anyhow!("empty model response"))?.message;
+            let mut predictions = parse_tool_calls(message, &self.config.profile.decoder(image_size))?;
+            let thought = message.content.clone().unwrap_or_default();
+            // some models still answer in text
+            if predictions.is_empty() {
+                predictions = parse_action_vlm_with(&thought, &self.config.profile.decoder(image_size), &self.config.profile.mode);
+            }
+            let calls: Vec<String> = message
+                .tool_calls
+                .iter()
+                .flatten()
+                .map(|call| format!("{}({})", call.function.name.as_deref().unwrap_or_default(), call.function.arguments.as_deref().unwrap_or_default()))
+                .collect();
+            let text = if calls.is_empty() { thought } else { format!("Thought: {}\nAction: {}", thought.trim(), calls.join("\n\n")) };
+            let _ = self.events.send(StreamEvent::ActionsReady(predictions.clone()));
+            let _ = self.events.send(StreamEvent::Done(text.clone()));
+            (text, predictions, Some(TokenUsage::from(&response.usage)))
+        } else if let Some(voting) = &self.config.voting {
+            let (text, predictions, usage, sent) = self.predict_by_vote(&request, voting, image_size).await?;
+            requests = sent;
+            let _ = self.events.send(StreamEvent::ActionsReady(predictions.clone()));
+            let _ = self.events.send(StreamEvent::Done(text.clone()));
+            (text, predictions, Some(usage))
+        } else if self.config.stream {
+            let parser = StreamingActionParser::with_decoder(Box::new(self.config.profile.decoder(image_size)), &self.config.profile.mode);
+            let streamed = stream_prediction_with(&self.client, &request, parser, &self.events).await?;
+            (streamed.text, streamed.predictions, streamed.usage)
+        } else {
+            let response = self.client.chat_completion(&request).await?;
+            let text = response
+                .choices
+                .first()
+                .and_then(|choice| choice.message.content.clone())
+                .ok_or_else(|| anyhow!("empty model response"))?;
+            let predictions = parse_action_vlm_with(&text, &self.config.profile.decoder(image_size), &self.config.profile.mode);
+            // no subscribers is fine
+            let _ = self.events.send(StreamEvent::ActionsReady(predictions.clone()));
+            let _ = self.events.send(StreamEvent::Done(text.clone()));
+            (text, predictions, Some(TokenUsage::from(&response.usage)))
+        };
+        log::debug!("model response: {}", text);
+        let estimate = TokenUsage {
+            prompt_tokens: prompt_estimate,
+            completion_tokens: estimate_text_tokens(&text),
+        };
+        self.usage.record_step(requests as u32, usage, estimate, &vec![image_size; requests], started.elapsed());
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key) {
+            if requests > 0 {
+                cache.put(key, &text)?;
+            }
+        }
+
+        self.history.push(text_message(MessageRole::assistant, text));
+        Ok((screenshot, predictions))
+    }
+
+    /// Samples until enough candidates agree, calls the user when no round reaches `min_agreement`.
+    /// Returns the winning text, its actions, the usage of all samples and the number of requests.
+    async fn predict_by_vote(&self, request: &ChatCompletionRequest, voting: &VotingConfig, image_size: (u32, u32)) -> Result<(String, Vec<PredictionParsed>, TokenUsage, usize)> {
+        let decoder = self.config.profile.decoder(image_size);
+        let mut usage = TokenUsage::default();
+        let mut requests = 0;
+        for round in 0..=voting.retries {
+            let samples = sample_completions(&self.client, request, voting).await?;
+            usage += samples.usage;
+            requests += samples.requests;
+            let candidates: Vec<_> = samples.texts.iter().map(|text| parse_action_vlm_with(text, &decoder, &self.config.profile.mode)).collect();
+            match vote(&candidates, voting.radius) {
+                Some(winner) if winner.agreement() >= voting.min_agreement => {
+                    log::debug!("{} of {} samples agree", winner.support, winner.total);
+                    return Ok((samples.texts[winner.index].clone(), winner.predictions, usage, requests));
+                }
+                winner => log::info!("samples disagree in round {}, best support {}", round + 1, winner.map_or(0, |w| w.support)),
+            }
+        }
+        let thought = "The sampled answers disagree on the next action.".to_string();
+        Ok((format!("Thought: {}\nAction: call_user()", thought), vec![terminal_prediction("call_user", thought)], usage, requests))
+    }
+
+    /// The planner picks the next step in words, the grounder turns it into an action.
+    async fn plan_and_ground(&mut self, instruction: &str, image_base64: String, image_size: (u32, u32)) -> Result<Vec<PredictionParsed>> {
+        let pipeline = self.pipeline.as_mut().ok_or_else(|| anyhow!("no planner configured"))?;
+        let started = Instant::now();
+        let planned = pipeline.planner.plan(&pipeline.planner_prompt, instruction, Some(image_base64.clone())).await?;
+        let mut images = if pipeline.planner.config().vision { vec![image_size] } else { Vec::new() };
+
+        let (thought, step) = match planned.step {
+            PlannedStep::Act { thought, step } => (thought, step),
+            PlannedStep::Finished { thought } => {
+                self.usage.record_step(1, Some(planned.usage), TokenUsage::default(), &images, started.elapsed());
+                return Ok(vec![terminal_prediction("finished", thought)]);
+            }
+            PlannedStep::CallUser { thought } => {
+                self.usage.record_step(1, Some(planned.usage), TokenUsage::default(), &images, started.elapsed());
+                return Ok(vec![terminal_prediction("call_user", thought)]);
+            }
+        };
+        let system_prompt = self.system_prompt.as_ref().map(|p| p.text.as_str()).unwrap_or_default();
+        let grounded = pipeline.grounder.ground(system_prompt, &step, image_base64, image_size).await?;
+        // planner and grounder are one step
+        let mut usage = planned.usage;
+        usage += grounded.usage;
+        images.push(image_size);
+        self.usage.record_step(2, Some(usage), TokenUsage::default(), &images, started.elapsed());
+        log::debug!("grounder response: {}", grounded.text);
+
+        let mut predictions = grounded.predictions;
+        for prediction in &mut predictions {
+            prediction.thought = format!("{}\nStep: {}", thought, step).trim().to_string();
+        }
+        // no subscribers is fine
+        let _ = self.events.send(StreamEvent::ActionsReady(predictions.clone()));
+        let _ = self.events.send(StreamEvent::Done(format!("{}\n{}", planned.text, grounded.text)));
+        Ok(predictions)
+    }
+}
+
+/// The focused window for `ClickInWindow` rules, unknown when it can't be read so those rules still apply.
+fn focused_context() -> ActionContext {
+    match focused_window() {
+        Ok(Some((app_name, title))) => ActionContext::new(&app_name, &title),
+        Ok(None) => ActionContext::unknown_window(),
+        Err(e) => {
+            log::warn!("failed to read the focused window: {}", e);
+            ActionContext::unknown_window()
+        }
+    }
+}
+
+fn terminal_prediction(action_type: &str, thought: String) -> PredictionParsed {
+    PredictionParsed {
+        reflection: None,
+        thought,
+        action_parsed: ActionParsed {
+            action_type: action_type.to_string(),
+            action_inputs: HashMap::new(),
+        },
+    }
+}
+
+/// Scale the normalised boxes from `parse_action_vlm` to points of `screen`, in virtual desktop
+/// coordinates like every `InputAction`, and build the action.
+pub fn to_input_action(prediction: &PredictionParsed, screen: ScreenRect) -> Result<InputAction> {
+    let mut inputs = HashMap::new();
+    for (name, value) in &prediction.action_parsed.action_inputs {
+        if name == "start_box" || name == "end_box" {
+            let values: Vec<f32> = serde_json::from_str(value)?;
+            if values.len() < 2 {
+                return Err(anyhow!("invalid {}: {}", name, value));
+            }
+            // box center, a point box is [x, y, x, y]
+            let (x, y) = if values.len() >= 4 {
+                ((values[0] + values[2]) / 2.0, (values[1] + values[3]) / 2.0)
+            } else {
+                (values[0], values[1])
+            };
+            let point = [(x * screen.width as f32).round() + screen.x as f32, (y * screen.height as f32).round() + screen.y as f32];
+            inputs.insert(name.clone(), serde_json::to_string(&point)?);
+        } else {
+            inputs.insert(name.clone(), value.clone());
+        }
+    }
+    InputAction::new(prediction.action_parsed.action_type.clone(), inputs)
+}
+
+/// A desktop point from the executor, in the coordinates the model uses for the screenshots of `screen`.
+pub fn to_model_point(point: (i32, i32), screen: ScreenRect, profile: &ModelProfile, image_size: (u32, u32)) -> (i32, i32) {
+    let x = (point.0 - screen.x) as f32 / screen.width.max(1) as f32;
+    let y = (point.1 - screen.y) as f32 / screen.height.max(1) as f32;
+    profile.encode_point((x, y), image_size)
+}
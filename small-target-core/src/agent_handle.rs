@@ -0,0 +1,146 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use image::DynamicImage;
+use small_target_control::{CancelToken, InputAction};
+use small_target_llm::PredictionParsed;
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    Running,
+    Paused,
+    /// let exactly one action through, then back to `Paused`
+    Step,
+}
+
+/// What the agent is about to do, published before every action.
+#[derive(Debug, Clone)]
+pub struct StepSnapshot {
+    pub step: usize,
+    pub screenshot: DynamicImage,
+    pub prediction: PredictionParsed,
+    pub pending_action: InputAction,
+    /// `name@version` of the system prompt
+    pub prompt_version: String,
+}
+
+struct Shared {
+    state: RunState,
+    snapshot: Option<StepSnapshot>,
+    edited_action: Option<InputAction>,
+    injected: Vec<String>,
+}
+
+/// Cloneable remote control for a running `Agent`, for a human or the Tauri UI.
+#[derive(Clone)]
+pub struct AgentHandle {
+    shared: Arc<Mutex<Shared>>,
+    changed: Arc<Notify>,
+    cancel_token: CancelToken,
+}
+
+impl AgentHandle {
+    pub fn new(cancel_token: CancelToken) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                state: RunState::Running,
+                snapshot: None,
+                edited_action: None,
+                injected: Vec::new(),
+            })),
+            changed: Arc::new(Notify::new()),
+            cancel_token,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn set_state(&self, state: RunState) {
+        self.lock().state = state;
+        self.changed.notify_waiters();
+    }
+
+    /// freeze before the next action
+    pub fn pause(&self) {
+        self.set_state(RunState::Paused);
+    }
+
+    pub fn resume(&self) {
+        self.set_state(RunState::Running);
+    }
+
+    /// run the pending action and pause again
+    pub fn step(&self) {
+        self.set_state(RunState::Step);
+    }
+
+    /// abort the run, same as the kill switch
+    pub fn stop(&self) {
+        self.cancel_token.cancel();
+        self.changed.notify_waiters();
+    }
+
+    pub fn state(&self) -> RunState {
+        self.lock().state
+    }
+
+    /// Extra instruction sent to the model with the next screenshot.
+    pub fn inject_instruction(&self, text: &str) {
+        self.lock().injected.push(text.to_string());
+    }
+
+    pub fn snapshot(&self) -> Option<StepSnapshot> {
+        self.lock().snapshot.clone()
+    }
+
+    /// Replace the pending action while paused, returns false when nothing is pending.
+    pub fn edit_pending_action(&self, action: InputAction) -> bool {
+        let mut shared = self.lock();
+        match shared.snapshot.as_mut() {
+            Some(snapshot) => {
+                snapshot.pending_action = action.clone();
+                shared.edited_action = Some(action);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn publish(&self, snapshot: StepSnapshot) {
+        let mut shared = self.lock();
+        shared.snapshot = Some(snapshot);
+        shared.edited_action = None;
+    }
+
+    pub fn take_injected(&self) -> Vec<String> {
+        std::mem::take(&mut self.lock().injected)
+    }
+
+    pub fn take_edited_action(&self) -> Option<InputAction> {
+        self.lock().edited_action.take()
+    }
+
+    /// Called by the agent loop before each action, blocks while paused.
+    pub async fn wait_turn(&self) {
+        loop {
+            let changed = self.changed.notified();
+            if self.cancel_token.is_cancelled() {
+                return;
+            }
+            {
+                let mut shared = self.lock();
+                match shared.state {
+                    RunState::Running => return,
+                    RunState::Step => {
+                        shared.state = RunState::Paused;
+                        return;
+                    }
+                    RunState::Paused => {}
+                }
+            }
+            changed.await;
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use enigo::Settings;
+use small_target_control::{ActionControl, KillSwitch, KillSwitchConfig, Macro, PlaybackOptions, Recorder, RecorderConfig};
+use small_target_core::MonitorProbe;
+use small_target_vision::monitor::{get_default_monitor, get_monitor_by_id};
+
+const USAGE: &str = "usage:
+  small-target macro play <file> [--param name=value]... [--speed 1.0] [--loops 1] [--loop-delay-ms 0] [--monitor id]
+  small-target macro record <file>
+
+play: ctrl+shift+escape or the mouse in a screen corner stops playback
+record: ctrl+shift+r stops recording, .ron files are written as RON, anything else as JSON";
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["macro", "play", file, rest @ ..] => play(PathBuf::from(file), rest).await,
+        ["macro", "record", file] => record(PathBuf::from(file)),
+        _ => Err(anyhow!("{}", USAGE)),
+    };
+    if let Err(e) = result {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn play(file: PathBuf, args: &[&str]) -> Result<()> {
+    let mac = Macro::load(&file)?;
+    let mut options = PlaybackOptions::default();
+    let mut monitor_id = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().copied().ok_or_else(|| anyhow!("{} needs a value\n{}", arg, USAGE));
+        match *arg {
+            "--param" => {
+                let param = value()?;
+                let (name, value) = param.split_once('=').ok_or_else(|| anyhow!("--param takes name=value, got {}", param))?;
+                options.params.insert(name.to_string(), value.to_string());
+            }
+            "--speed" => options.speed = value()?.parse().context("invalid --speed")?,
+            "--loops" => options.loops = value()?.parse().context("invalid --loops")?,
+            "--loop-delay-ms" => options.loop_delay = Duration::from_millis(value()?.parse().context("invalid --loop-delay-ms")?),
+            "--monitor" => monitor_id = Some(value()?.parse::<u32>().context("invalid --monitor")?),
+            other => return Err(anyhow!("unknown option {}\n{}", other, USAGE)),
+        }
+    }
+
+    let monitor = match monitor_id {
+        Some(id) => get_monitor_by_id(id).await.ok_or_else(|| anyhow!("no monitor {}", id))?,
+        None => get_default_monitor().await,
+    };
+    let mut control = ActionControl::new(&Settings::default());
+    control.set_screen_probe(Box::new(MonitorProbe::new(monitor)));
+    let _kill_switch = KillSwitch::start(KillSwitchConfig::default(), control.cancel_token.clone())?;
+
+    let report = control.play_macro(&mac, &options)?;
+    for result in &report.results {
+        println!("{:?} {} in {:?}", result.action, result.outcome, result.elapsed);
+    }
+    if let Some(e) = &report.release_error {
+        eprintln!("releasing held input failed: {}", e);
+    }
+    match report.failure() {
+        Some((index, failed)) => Err(anyhow!("step {} {}", index + 1, failed.outcome)),
+        None => Ok(()),
+    }
+}
+
+fn record(file: PathBuf) -> Result<()> {
+    let recorder = Recorder::start(RecorderConfig::default())?;
+    println!("recording, press ctrl+shift+r to stop");
+    while recorder.is_recording() {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let recording = recorder.stop();
+    let name = file.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let mac = Macro::from_recording(&name, &recording);
+    mac.save(&file)?;
+    println!("saved {} action(s) to {:?}", mac.steps.len(), file);
+    Ok(())
+}
+
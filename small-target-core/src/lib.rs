@@ -1,3 +1,15 @@
+pub mod agent;
+pub use agent::{ActionMode, Agent, AgentConfig, AgentOutcome};
+
+pub mod screen_probe;
+pub use screen_probe::{screen_rect, screenshot_hook, MonitorProbe};
+
+pub mod tools;
+pub use tools::action_tools;
+
+pub mod agent_handle;
+pub use agent_handle::{AgentHandle, RunState, StepSnapshot};
+
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use image::{DynamicImage, RgbaImage};
+use small_target_control::{RegionCapture, ScreenProbe, ScreenRect, ScreenshotHook, ScrollTarget};
+use small_target_image::{dhash, diff_images, find_template, image_from_path};
+use small_target_vision::SafeMonitor;
+
+/// Where `monitor` sits in the virtual desktop.
+pub fn screen_rect(monitor: &SafeMonitor) -> ScreenRect {
+    let (x, y) = monitor.position();
+    ScreenRect::new(x, y, monitor.width(), monitor.height())
+}
+
+/// Screenshots of `monitor` saved as `dir/0000.png`, `dir/0001.png`, ... while recording.
+pub fn screenshot_hook(monitor: SafeMonitor, dir: PathBuf) -> Result<ScreenshotHook> {
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {:?}", dir))?;
+    Ok(Box::new(move |index| {
+        let path = dir.join(format!("{:04}.png", index));
+        monitor.capture_image_blocking()?.save(&path)?;
+        Ok(path)
+    }))
+}
+
+/// side of the square around the scroll point that `fingerprint` hashes
+const FINGERPRINT_REGION: u32 = 512;
+
+/// `ScreenProbe` over a monitor: perceptual hashes for "stopped moving" and template matching for images.
+/// It can't read text, `ScrollTarget::Text` needs a probe with OCR or a model behind it.
+pub struct MonitorProbe {
+    monitor: SafeMonitor,
+    /// lowest `find_template` score accepted as found
+    pub min_score: f32,
+    templates: HashMap<PathBuf, DynamicImage>,
+}
+
+impl MonitorProbe {
+    pub fn new(monitor: SafeMonitor) -> Self {
+        Self {
+            monitor,
+            min_score: 0.95,
+            templates: HashMap::new(),
+        }
+    }
+
+    fn template(&mut self, path: &PathBuf) -> Result<&DynamicImage> {
+        if !self.templates.contains_key(path) {
+            let template = image_from_path(path.to_str().ok_or_else(|| anyhow!("invalid template path {:?}", path))?).with_context(|| format!("failed to load template {:?}", path))?;
+            self.templates.insert(path.clone(), template);
+        }
+        Ok(&self.templates[path])
+    }
+
+    /// The monitor's pixels inside `rect`, given in virtual desktop coordinates and clipped to the monitor.
+    fn capture_rect(&self, x: i32, y: i32, width: u32, height: u32) -> Result<RegionCapture> {
+        let screen = self.monitor.capture_image_blocking()?;
+        let (left, top) = self.monitor.position();
+        let x0 = (x - left).clamp(0, screen.width() as i32) as u32;
+        let y0 = (y - top).clamp(0, screen.height() as i32) as u32;
+        let x1 = (x - left + width as i32).clamp(0, screen.width() as i32) as u32;
+        let y1 = (y - top + height as i32).clamp(0, screen.height() as i32) as u32;
+        if x1 <= x0 || y1 <= y0 {
+            return Err(anyhow!("({}, {}) is not on monitor {}", x, y, self.monitor.id()));
+        }
+        Ok(RegionCapture {
+            x: left + x0 as i32,
+            y: top + y0 as i32,
+            width: x1 - x0,
+            height: y1 - y0,
+            rgba: screen.crop_imm(x0, y0, x1 - x0, y1 - y0).to_rgba8().into_raw(),
+        })
+    }
+}
+
+impl ScreenProbe for MonitorProbe {
+    fn fingerprint(&mut self, point: (i32, i32)) -> Result<u64> {
+        let screen = self.monitor.capture_image_blocking()?;
+        let (left, top) = self.monitor.position();
+        let size = FINGERPRINT_REGION.min(screen.width()).min(screen.height());
+        let x = ((point.0 - left).max(0) as u32).saturating_sub(size / 2).min(screen.width() - size);
+        let y = ((point.1 - top).max(0) as u32).saturating_sub(size / 2).min(screen.height() - size);
+        Ok(dhash(&screen.crop_imm(x, y, size, size)))
+    }
+
+    fn locate(&mut self, target: &ScrollTarget) -> Result<Option<(i32, i32)>> {
+        match target {
+            ScrollTarget::Stable => Ok(None),
+            ScrollTarget::Text(text) => Err(anyhow!("can't look for text {:?} on screen without OCR", text)),
+            ScrollTarget::Template(path) => {
+                let screen = self.monitor.capture_image_blocking()?;
+                let (left, top) = self.monitor.position();
+                let min_score = self.min_score;
+                let found = find_template(&screen, self.template(path)?, min_score);
+                Ok(found.map(|m| {
+                    let (x, y) = m.center();
+                    (left + x as i32, top + y as i32)
+                }))
+            }
+        }
+    }
+
+    fn capture_region(&mut self, point: (i32, i32), radius: u32) -> Result<RegionCapture> {
+        self.capture_rect(point.0 - radius as i32, point.1 - radius as i32, radius * 2, radius * 2)
+    }
+
+    fn changed_since(&mut self, before: &RegionCapture, threshold: u8) -> Result<f64> {
+        let after = self.capture_rect(before.x, before.y, before.width, before.height)?;
+        let image = |capture: &RegionCapture| -> Result<DynamicImage> {
+            let pixels = RgbaImage::from_raw(capture.width, capture.height, capture.rgba.clone()).ok_or_else(|| anyhow!("region capture has the wrong number of pixels"))?;
+            Ok(DynamicImage::ImageRgba8(pixels))
+        };
+        Ok(diff_images(&image(before)?, &image(&after)?, threshold)?.ratio())
+    }
+}
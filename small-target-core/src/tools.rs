@@ -0,0 +1,84 @@
+use serde_json::{json, Map, Value};
+use small_target_control::action_schema::{ParamKind, ParamSpec};
+use small_target_control::{ActionKind, ActionSpec, ACTION_SCHEMA};
+use small_target_llm::{ModelProfile, ToolSpec};
+
+/// The executor's action schema as function tools, for models that answer with `tool_calls`.
+/// Boxes are described in the coordinates of `profile`.
+pub fn action_tools(profile: &ModelProfile) -> Vec<ToolSpec> {
+    ACTION_SCHEMA.iter().map(|spec| action_tool(spec, profile)).collect()
+}
+
+pub fn action_tool(spec: &ActionSpec, profile: &ModelProfile) -> ToolSpec {
+    let mut properties = Map::new();
+    for param in spec.params {
+        properties.insert(param.name.to_string(), param_schema(param, profile));
+    }
+    let required: Vec<&str> = spec.params.iter().filter(|p| p.default.is_none()).map(|p| p.name).collect();
+    let description = match spec.note {
+        Some(note) => format!("{} {}", describe(spec.kind), note),
+        None => describe(spec.kind).to_string(),
+    };
+    ToolSpec {
+        name: spec.name.to_string(),
+        description,
+        parameters: json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        }),
+    }
+}
+
+fn param_schema(param: &ParamSpec, profile: &ModelProfile) -> Value {
+    let mut schema = match param.kind {
+        ParamKind::Box => json!({
+            "type": "array",
+            "items": { "type": "number" },
+            "minItems": 2,
+            "maxItems": 4,
+            "description": format!("{} of the target element, or [x, y] for a point, in {}", param.placeholder, profile.describe_space()),
+        }),
+        ParamKind::Text => json!({ "type": "string" }),
+        ParamKind::Keys => json!({ "type": "string", "description": "a key or a combination joined with +, e.g. control+c" }),
+        ParamKind::Direction => json!({ "type": "string", "enum": ["up", "down", "left", "right"] }),
+        ParamKind::Integer => json!({ "type": "integer" }),
+        ParamKind::Button => json!({ "type": "string", "enum": ["left", "right", "middle"] }),
+        ParamKind::ScrollUnit => json!({ "type": "string", "enum": ["notches", "lines", "pixels", "pages"] }),
+    };
+    match param.default {
+        Some("") => schema["description"] = Value::String("optional".to_string()),
+        Some(default) => schema["description"] = Value::String(format!("optional, defaults to {}", default)),
+        None => {}
+    }
+    schema
+}
+
+fn describe(kind: ActionKind) -> &'static str {
+    match kind {
+        ActionKind::MouseLeftClick => "Left click the element.",
+        ActionKind::MouseLeftDoubleClick => "Double click the element.",
+        ActionKind::MouseRightClick => "Right click the element.",
+        ActionKind::MouseMiddleClick => "Middle click the element.",
+        ActionKind::MouseMove => "Move the mouse onto the element.",
+        ActionKind::Drag => "Drag from the start element to the end element.",
+        ActionKind::Select => "Select the text from the start element to the end element.",
+        ActionKind::TripleClick => "Triple click to select a whole line or paragraph.",
+        ActionKind::SelectAll => "Select everything in the focused element.",
+        ActionKind::Scroll => "Scroll at the element, by default a few wheel notches.",
+        ActionKind::ScrollUntil => "Scroll at the element repeatedly.",
+        ActionKind::Hotkey => "Press a key combination.",
+        ActionKind::KeyClick => "Press a key.",
+        ActionKind::KeyDown => "Hold a key down until key_up.",
+        ActionKind::KeyUp => "Release a key held by key_down.",
+        ActionKind::KeyHold => "Hold a key for a while.",
+        ActionKind::ButtonDown => "Press a mouse button at the element and keep it pressed.",
+        ActionKind::ButtonUp => "Release a mouse button held by mouse_down.",
+        ActionKind::WriteText => "Type text at the focused element.",
+        ActionKind::SetClipboard => "Put text on the clipboard.",
+        ActionKind::GetClipboard => "Read the text on the clipboard.",
+        ActionKind::Wait => "Wait for the screen to change.",
+        ActionKind::Finished => "The task is complete.",
+        ActionKind::CallUser => "Hand over to the user.",
+    }
+}
@@ -0,0 +1,41 @@
+mod agent_e2e_test {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use enigo::Settings;
+    use small_target_control::ActionControl;
+    use small_target_core::{Agent, AgentConfig, AgentOutcome};
+    use small_target_mock::{Matcher, MockResponse, MockScript, MockVlmServer};
+    use small_target_vision::monitor::get_default_monitor;
+
+    #[tokio::test]
+    #[ignore = "needs a display, run with --ignored under X11 or Xvfb"]
+    async fn test_agent_loop_against_mock_model() -> Result<()> {
+        let script = MockScript::new()
+            .on_times(Matcher::Step(0), 1, MockResponse::error(503, "warming up"))
+            .then(MockResponse::text("Thought: Move to the middle of the screen.\nAction: mouse_move(start_box='(500,500)')"))
+            .then(MockResponse::text("Thought: The pointer is there.\nAction: finished()"));
+        let server = MockVlmServer::start(script).await?;
+
+        let mut config = AgentConfig::new(&server.base_url(), "ui-tars", "key");
+        config.client.initial_backoff = Duration::from_millis(10);
+        config.max_steps = 5;
+        let monitor = get_default_monitor().await;
+        let mut agent = Agent::new(config, monitor, ActionControl::new(&Settings::default()))?;
+
+        assert_eq!(agent.run("move the mouse to the center").await?, AgentOutcome::Finished);
+
+        // the 503 was retried by the client
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        for request in &requests {
+            assert_eq!(request.model, "ui-tars");
+            assert!(request.texts[0].ends_with("move the mouse to the center"));
+            assert_eq!(request.images.len(), 1);
+        }
+        // the second step sees the first answer in the history
+        assert!(requests[2].texts.iter().any(|text| text.contains("Move to the middle of the screen.")));
+        assert_eq!(agent.usage().steps().len(), 2);
+        Ok(())
+    }
+}
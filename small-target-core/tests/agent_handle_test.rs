@@ -0,0 +1,81 @@
+mod agent_handle_test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use image::DynamicImage;
+    use small_target_control::{CancelToken, InputAction};
+    use small_target_core::agent_handle::{AgentHandle, RunState, StepSnapshot};
+    use small_target_llm::action_parser::{ActionParsed, PredictionParsed};
+    use tokio::time::timeout;
+
+    fn snapshot(action: InputAction) -> StepSnapshot {
+        StepSnapshot {
+            step: 0,
+            screenshot: DynamicImage::new_rgb8(4, 4),
+            prediction: PredictionParsed {
+                reflection: None,
+                thought: "click".to_string(),
+                action_parsed: ActionParsed {
+                    action_type: "click".to_string(),
+                    action_inputs: HashMap::new(),
+                },
+            },
+            pending_action: action,
+            prompt_version: "computer@v1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pause_resume_and_step() {
+        let handle = AgentHandle::new(CancelToken::new());
+        assert!(timeout(Duration::from_millis(100), handle.wait_turn()).await.is_ok());
+
+        handle.pause();
+        assert!(timeout(Duration::from_millis(100), handle.wait_turn()).await.is_err());
+
+        let waiter = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.wait_turn().await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        handle.step();
+        timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        // one action went through, the agent is paused again
+        assert_eq!(handle.state(), RunState::Paused);
+
+        handle.resume();
+        assert!(timeout(Duration::from_millis(100), handle.wait_turn()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_stop_releases_paused_agent() {
+        let handle = AgentHandle::new(CancelToken::new());
+        handle.pause();
+        let waiter = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.wait_turn().await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        handle.stop();
+        timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_edit_pending_action_and_inject() {
+        let handle = AgentHandle::new(CancelToken::new());
+        assert!(!handle.edit_pending_action(InputAction::Wait { milliseconds: 1 }));
+
+        handle.publish(snapshot(InputAction::MouseLeftClick { x: 1, y: 1 }));
+        assert!(handle.edit_pending_action(InputAction::MouseLeftClick { x: 10, y: 20 }));
+        match handle.take_edited_action() {
+            Some(InputAction::MouseLeftClick { x, y }) => assert_eq!((x, y), (10, 20)),
+            other => panic!("unexpected edit {:?}", other),
+        }
+        assert!(handle.take_edited_action().is_none());
+        assert!(matches!(handle.snapshot().unwrap().pending_action, InputAction::MouseLeftClick { x: 10, y: 20 }));
+
+        handle.inject_instruction("use the search box instead");
+        assert_eq!(handle.take_injected(), vec!["use the search box instead".to_string()]);
+        assert!(handle.take_injected().is_empty());
+    }
+}
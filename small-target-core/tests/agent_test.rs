@@ -0,0 +1,111 @@
+mod agent_test {
+    use anyhow::Result;
+    use openai_api_rs::v1::chat_completion::ChatCompletionMessageForResponse;
+    use small_target_control::{lookup_action, ActionValidator, InputAction, ScreenRect};
+    use small_target_core::action_tools;
+    use small_target_core::agent::{to_input_action, to_model_point};
+    use small_target_llm::{parse_action_vlm, parse_tool_calls, promps::FACTOR, ModelProfile, PromptRegistry};
+
+    #[test]
+    fn test_to_input_action_scales_to_screen() -> Result<()> {
+        let predictions = parse_action_vlm("Thought: click it\nAction: click(start_box='(500,250)')", FACTOR, "bc");
+        match to_input_action(&predictions[0], ScreenRect::new(0, 0, 1920, 1080))? {
+            InputAction::MouseLeftClick { x, y } => assert_eq!((x, y), (960, 270)),
+            other => panic!("unexpected action {:?}", other),
+        }
+
+        let predictions = parse_action_vlm("Thought: drag\nAction: drag(start_box='[100,100,300,300]', end_box='(900,900)')", FACTOR, "bc");
+        match to_input_action(&predictions[0], ScreenRect::new(0, 0, 1000, 1000))? {
+            InputAction::Drag { x1, y1, x2, y2 } => assert_eq!((x1, y1, x2, y2), (200, 200, 900, 900)),
+            other => panic!("unexpected action {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_second_monitor_points_are_on_screen() -> Result<()> {
+        // a monitor right of a 1920 wide primary, both actions and validation use desktop coordinates
+        let screen = ScreenRect::new(1920, -200, 1280, 1024);
+        let predictions = parse_action_vlm("Thought: click it\nAction: click(start_box='(500,500)')", FACTOR, "bc");
+        let action = to_input_action(&predictions[0], screen)?;
+        match action {
+            InputAction::MouseLeftClick { x, y } => assert_eq!((x, y), (2560, 312)),
+            ref other => panic!("unexpected action {:?}", other),
+        }
+        assert!(ActionValidator::new(vec![screen]).validate(&action).is_empty());
+        assert!(!ActionValidator::new(vec![ScreenRect::new(0, 0, 1920, 1080)]).validate(&action).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_located_points_are_reported_in_model_coordinates() {
+        let screen = ScreenRect::new(1920, -200, 1280, 1024);
+        assert_eq!(to_model_point((2560, 56), screen, &ModelProfile::ui_tars(), (1280, 1024)), (500, 250));
+        assert_eq!(to_model_point((2560, 56), screen, &ModelProfile::qwen2_5_vl(), (640, 512)), (320, 128));
+    }
+
+    #[test]
+    fn test_tool_calls_drive_the_same_actions() -> Result<()> {
+        let tools = action_tools(&ModelProfile::ui_tars());
+        assert!(tools.iter().all(|tool| lookup_action(&tool.name).is_some()));
+        let drag = tools.iter().find(|tool| tool.name == "drag").unwrap();
+        assert_eq!(drag.parameters["required"], serde_json::json!(["start_box", "end_box"]));
+
+        let message: ChatCompletionMessageForResponse = serde_json::from_value(serde_json::json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{ "id": "call_0", "type": "function", "function": { "name": "middle_click", "arguments": "{\"start_box\": [500, 250]}" } }]
+        }))?;
+        let predictions = parse_tool_calls(&message, &ModelProfile::ui_tars().decoder((1280, 720)))?;
+        match to_input_action(&predictions[0], ScreenRect::new(0, 0, 1920, 1080))? {
+            InputAction::MouseMiddleClick { x, y } => assert_eq!((x, y), (960, 270)),
+            other => panic!("unexpected action {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tool_calls_follow_the_model_profile() -> Result<()> {
+        // Qwen2.5-VL answers in pixels of the resized screenshot
+        let profile = ModelProfile::qwen2_5_vl();
+        let tools = action_tools(&profile);
+        let click = tools.iter().find(|tool| tool.name == "click").unwrap();
+        assert!(click.parameters["properties"]["start_box"]["description"].as_str().unwrap().contains("pixels of the screenshot"));
+
+        let message: ChatCompletionMessageForResponse = serde_json::from_value(serde_json::json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{ "id": "call_0", "type": "function", "function": { "name": "click", "arguments": "{\"start_box\": [640, 180]}" } }]
+        }))?;
+        let predictions = parse_tool_calls(&message, &profile.decoder((1280, 720)))?;
+        match to_input_action(&predictions[0], ScreenRect::new(0, 0, 2560, 1440))? {
+            InputAction::MouseLeftClick { x, y } => assert_eq!((x, y), (1280, 360)),
+            other => panic!("unexpected action {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_builtin_action_spaces_are_executable() {
+        let registry = PromptRegistry::builtin();
+        for name in registry.names() {
+            let Some(action_space) = registry.get(name).unwrap().defaults.get("action_space") else {
+                continue;
+            };
+            for line in action_space.lines().filter(|line| line.contains('(')) {
+                let (action, rest) = line.split_once('(').unwrap();
+                let spec = lookup_action(action.trim()).unwrap_or_else(|| panic!("{} offers {}, which can't be executed", name, action));
+                for param in rest.split(", ").filter_map(|arg| arg.split_once('=')).map(|(param, _)| param.trim()) {
+                    assert!(
+                        spec.params.iter().any(|p| p.name == param),
+                        "{} offers {}({}=...), which {} doesn't take",
+                        name,
+                        action,
+                        param,
+                        spec.name
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use anyhow::{anyhow, Result};
+use image::DynamicImage;
+
+/// Pixels that differ between two images of the same size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageDiff {
+    pub changed: u64,
+    pub total: u64,
+    /// x, y, width and height around every changed pixel
+    pub bounds: Option<(u32, u32, u32, u32)>,
+}
+
+impl ImageDiff {
+    /// share of changed pixels, 0.0 to 1.0
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.changed as f64 / self.total as f64
+    }
+}
+
+/// A pixel counts as changed when a color channel moves by more than `threshold`, which absorbs
+/// compression noise and subpixel rendering. Alpha is ignored.
+pub fn diff_images(before: &DynamicImage, after: &DynamicImage, threshold: u8) -> Result<ImageDiff> {
+    if before.width() != after.width() || before.height() != after.height() {
+        return Err(anyhow!("can't diff a {}x{} image with a {}x{} one", before.width(), before.height(), after.width(), after.height()));
+    }
+    let before = before.to_rgb8();
+    let after = after.to_rgb8();
+    let mut changed = 0u64;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, pixel) in before.enumerate_pixels() {
+        let other = after.get_pixel(x, y);
+        let delta = pixel.0.iter().zip(other.0.iter()).map(|(a, b)| a.abs_diff(*b)).max().unwrap_or(0);
+        if delta > threshold {
+            changed += 1;
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    Ok(ImageDiff {
+        changed,
+        total: before.width() as u64 * before.height() as u64,
+        bounds: (changed > 0).then(|| (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1)),
+    })
+}
@@ -1,2 +1,11 @@
 pub mod image_utils;
-pub use image_utils::{image_from_path, image_resize, image_to_base64};
\ No newline at end of file
+pub use image_utils::{image_from_path, image_resize, image_to_base64};
+
+pub mod perceptual_hash;
+pub use perceptual_hash::{dhash, hamming_distance};
+
+pub mod template_match;
+pub use template_match::{find_template, TemplateMatch};
+
+pub mod image_diff;
+pub use image_diff::{diff_images, ImageDiff};
@@ -0,0 +1,20 @@
+use image::{imageops::FilterType, DynamicImage};
+
+/// 64-bit difference hash: shrink to 9x8 grayscale and compare horizontal neighbours.
+/// Re-encoding, slight blur and scaling keep it stable, different screens differ in many bits.
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let bit = small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | bit as u64;
+        }
+    }
+    hash
+}
+
+/// number of differing bits of two `dhash` values
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,122 @@
+use image::{DynamicImage, GrayImage};
+
+/// Where a template was found, `score` is 1.0 for an exact match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemplateMatch {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub score: f32,
+}
+
+impl TemplateMatch {
+    pub fn center(&self) -> (u32, u32) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+}
+
+// candidates from the coarse pass that get refined at full size
+const CANDIDATES: usize = 8;
+
+/// Best grayscale match of `template` in `image` scoring at least `min_score`.
+/// Searches a downscaled copy first and refines the best spots, so a screen takes milliseconds, not seconds.
+pub fn find_template(image: &DynamicImage, template: &DynamicImage, min_score: f32) -> Option<TemplateMatch> {
+    let image = image.to_luma8();
+    let template = template.to_luma8();
+    let (tw, th) = template.dimensions();
+    if tw == 0 || th == 0 || tw > image.width() || th > image.height() {
+        return None;
+    }
+    let scale = (tw.min(th) / 8).clamp(1, 4);
+    let candidates = if scale == 1 {
+        search(&image, &template, 0..=image.width() - tw, 0..=image.height() - th, 1)
+    } else {
+        let small_image = shrink(&image, 0, 0, scale);
+        let mut refined = Vec::new();
+        // the template can sit at any offset from the coarse grid, each phase lines its blocks up with the image's
+        for oy in 0..scale {
+            for ox in 0..scale {
+                let small_template = shrink(&template, ox, oy, scale);
+                if small_template.width() == 0 || small_template.height() == 0 || small_template.width() > small_image.width() || small_template.height() > small_image.height() {
+                    continue;
+                }
+                let coarse = search(
+                    &small_image,
+                    &small_template,
+                    0..=small_image.width() - small_template.width(),
+                    0..=small_image.height() - small_template.height(),
+                    CANDIDATES,
+                );
+                for (cx, cy, _) in coarse {
+                    let (Some(x), Some(y)) = ((cx * scale).checked_sub(ox), (cy * scale).checked_sub(oy)) else {
+                        continue;
+                    };
+                    if x + tw > image.width() || y + th > image.height() {
+                        continue;
+                    }
+                    let xs = x.saturating_sub(1)..=(x + 1).min(image.width() - tw);
+                    let ys = y.saturating_sub(1)..=(y + 1).min(image.height() - th);
+                    refined.extend(search(&image, &template, xs, ys, 1));
+                }
+            }
+        }
+        refined
+    };
+    let (x, y, difference) = candidates.into_iter().min_by_key(|c| c.2)?;
+    let score = 1.0 - difference as f32 / (tw as u64 * th as u64 * 255) as f32;
+    (score >= min_score).then_some(TemplateMatch {
+        x,
+        y,
+        width: tw,
+        height: th,
+        score,
+    })
+}
+
+/// Averages `scale` x `scale` blocks starting at (`ox`, `oy`), partial blocks at the edges are dropped.
+fn shrink(image: &GrayImage, ox: u32, oy: u32, scale: u32) -> GrayImage {
+    let width = image.width().saturating_sub(ox) / scale;
+    let height = image.height().saturating_sub(oy) / scale;
+    GrayImage::from_fn(width, height, |x, y| {
+        let mut sum = 0u32;
+        for dy in 0..scale {
+            for dx in 0..scale {
+                sum += image.get_pixel(ox + x * scale + dx, oy + y * scale + dy)[0] as u32;
+            }
+        }
+        image::Luma([(sum / (scale * scale)) as u8])
+    })
+}
+
+/// The `keep` positions with the smallest sum of absolute differences, best first.
+fn search(image: &GrayImage, template: &GrayImage, xs: std::ops::RangeInclusive<u32>, ys: std::ops::RangeInclusive<u32>, keep: usize) -> Vec<(u32, u32, u64)> {
+    let mut best: Vec<(u32, u32, u64)> = Vec::with_capacity(keep + 1);
+    for y in ys {
+        for x in xs.clone() {
+            let limit = if best.len() == keep { best[keep - 1].2 } else { u64::MAX };
+            if let Some(difference) = difference_at(image, template, x, y, limit) {
+                let index = best.partition_point(|c| c.2 <= difference);
+                best.insert(index, (x, y, difference));
+                best.truncate(keep);
+            }
+        }
+    }
+    best
+}
+
+/// `None` as soon as the difference reaches `limit`
+fn difference_at(image: &GrayImage, template: &GrayImage, x: u32, y: u32, limit: u64) -> Option<u64> {
+    let mut difference = 0u64;
+    for ty in 0..template.height() {
+        for tx in 0..template.width() {
+            let a = image.get_pixel(x + tx, y + ty)[0];
+            let b = template.get_pixel(tx, ty)[0];
+            difference += a.abs_diff(b) as u64;
+        }
+        if difference >= limit {
+            return None;
+        }
+    }
+    Some(difference)
+}
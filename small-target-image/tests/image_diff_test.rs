@@ -0,0 +1,29 @@
+mod image_diff_test {
+    use image::{DynamicImage, Rgb, RgbImage};
+    use small_target_image::diff_images;
+
+    #[test]
+    fn test_diff_images() {
+        let before = RgbImage::from_pixel(100, 50, Rgb([200, 200, 200]));
+        let mut after = before.clone();
+        // a button turning pressed, plus noise below the threshold everywhere else
+        for (x, y, pixel) in after.enumerate_pixels_mut() {
+            *pixel = if (20..30).contains(&x) && (10..15).contains(&y) {
+                Rgb([60, 90, 200])
+            } else {
+                Rgb([205, 196, 200])
+            };
+        }
+        let diff = diff_images(&DynamicImage::ImageRgb8(before.clone()), &DynamicImage::ImageRgb8(after), 8).unwrap();
+        assert_eq!(diff.changed, 50);
+        assert_eq!(diff.total, 5000);
+        assert_eq!(diff.ratio(), 0.01);
+        assert_eq!(diff.bounds, Some((20, 10, 10, 5)));
+
+        let same = diff_images(&DynamicImage::ImageRgb8(before.clone()), &DynamicImage::ImageRgb8(before.clone()), 0).unwrap();
+        assert_eq!((same.changed, same.bounds), (0, None));
+
+        let smaller = DynamicImage::ImageRgb8(RgbImage::new(99, 50));
+        assert!(diff_images(&DynamicImage::ImageRgb8(before), &smaller, 8).is_err());
+    }
+}
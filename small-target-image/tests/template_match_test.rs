@@ -0,0 +1,35 @@
+mod template_match_test {
+    use image::{DynamicImage, GrayImage, Luma};
+    use small_target_image::find_template;
+
+    /// a noisy screen so every spot looks different
+    fn screen(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let mut h = (x as u64) << 32 | y as u64;
+            h = (h ^ (h >> 33)).wrapping_mul(0xff51afd7ed558ccd);
+            h = (h ^ (h >> 33)).wrapping_mul(0xc4ceb9fe1a85ec53);
+            Luma([(h >> 56) as u8])
+        })
+    }
+
+    #[test]
+    fn test_find_template() {
+        let screen = screen(400, 300);
+        for (x, y, w, h) in [(123, 77, 40, 24), (5, 260, 6, 6), (360, 0, 40, 40)] {
+            let template = image::imageops::crop_imm(&screen, x, y, w, h).to_image();
+            let found = find_template(&DynamicImage::ImageLuma8(screen.clone()), &DynamicImage::ImageLuma8(template), 0.99).unwrap();
+            assert_eq!((found.x, found.y), (x, y));
+            assert_eq!(found.center(), (x + w / 2, y + h / 2));
+            assert!(found.score > 0.999);
+        }
+    }
+
+    #[test]
+    fn test_missing_template() {
+        let screen = DynamicImage::ImageLuma8(screen(200, 100));
+        let template = DynamicImage::ImageLuma8(GrayImage::from_pixel(30, 30, Luma([128])));
+        assert!(find_template(&screen, &template, 0.95).is_none());
+        let too_big = DynamicImage::ImageLuma8(GrayImage::new(300, 10));
+        assert!(find_template(&screen, &too_big, 0.0).is_none());
+    }
+}
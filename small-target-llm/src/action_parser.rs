@@ -1,7 +1,11 @@
 use lazy_static::lazy_static;
 use regex::Regex;
+use small_target_schema::lookup_action;
 use std::collections::HashMap;
 
+use crate::call_parser::{parse_call, Call};
+use crate::coordinates::{CoordinateDecoder, CoordinateScale, DecoderChain};
+
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ActionParsed {
@@ -16,7 +20,13 @@ pub struct PredictionParsed {
     pub action_parsed: ActionParsed
 }
 
+/// Boxes relative to `factor`, in any of the known coordinate formats.
 pub fn parse_action_vlm(text: &str, factor: (f32, f32), mode: &str) -> Vec<PredictionParsed> {
+    parse_action_vlm_with(text, &DecoderChain::all_formats(CoordinateScale::Relative(factor.0, factor.1)), mode)
+}
+
+/// Boxes converted by `decoder`, see `ModelProfile::decoder`.
+pub fn parse_action_vlm_with(text: &str, decoder: &dyn CoordinateDecoder, mode: &str) -> Vec<PredictionParsed> {
     let text = text.trim();
     let mut reflection = None;
     let mut thought = None;
@@ -67,39 +77,22 @@ pub fn parse_action_vlm(text: &str, factor: (f32, f32), mode: &str) -> Vec<Predi
         }
         _ => panic!("Invalid mode"),
     }
-    let all_actions: Vec<&str> = action_str.split("\n\n").collect();
     let mut actions = Vec::new();
-
-    for raw_str in all_actions {
-        let cleaned_str = raw_str.replace('\n', r"\n").trim_start().to_string();
-        let action_instance = parse_action(&cleaned_str);
-
+    for action_instance in parse_actions(&action_str) {
         let mut action_inputs = HashMap::new();
-        let mut action_type = String::new();
-
-        if let Some(act) = action_instance {
-            action_type = act.function;
-            for (param_name, param_value) in act.args {
-                let trimmed = param_value.trim().to_string();
-                if param_name.contains("start_box") || param_name.contains("end_box") {
-                    let numbers: Vec<f32> = trimmed
-                        .trim_matches(|c| c == '(' || c == ')' || c == '[' || c == ']')
-                        .split(',')
-                        .filter_map(|s| s.parse::<f32>().ok())
-                        .enumerate()
-                        .map(|(index, num)| num / if index % 2 == 0 { factor.0 } else { factor.1 })
-                        .collect();
-
-                    let numbers = if numbers.len() == 2 {
-                        vec![numbers[0], numbers[1], numbers[0], numbers[1]]
-                    } else {
-                        numbers
-                    };
-
-                    action_inputs.insert(param_name, serde_json::to_string(&numbers).unwrap());
-                } else {
-                    action_inputs.insert(param_name, trimmed);
-                }
+        for (param_name, param_value) in action_instance.args {
+            if param_name.contains("start_box") || param_name.contains("end_box") {
+                let value = match decoder.decode(&param_value) {
+                    Some(normalized) => serde_json::to_string(&normalized).unwrap(),
+                    None => {
+                        log::warn!("can't decode {}: {}", param_name, param_value);
+                        "[]".to_string()
+                    }
+                };
+                action_inputs.insert(param_name, value);
+            } else {
+                // kept exactly, a trailing newline in `content` means submit
+                action_inputs.insert(param_name, param_value);
             }
         }
 
@@ -107,7 +100,7 @@ pub fn parse_action_vlm(text: &str, factor: (f32, f32), mode: &str) -> Vec<Predi
             reflection: reflection.clone(),
             thought: thought.clone().unwrap_or_default(),
             action_parsed: ActionParsed {
-                action_type,
+                action_type: action_instance.function,
                 action_inputs,
             },
         });
@@ -115,38 +108,56 @@ pub fn parse_action_vlm(text: &str, factor: (f32, f32), mode: &str) -> Vec<Predi
 
     actions
 }
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct ParsedAction {
     function: String,
     args: HashMap<String, String>,
 }
 
-//for example parse: click(start_box='(530,965)')  
-//return ParsedAction { function: "click", args: {"start_box": "(530,965)"} }
-fn parse_action(action_str: &str) -> Option<ParsedAction> {
-    lazy_static! {
-        static ref FUNC_RE: Regex = Regex::new(r"^(\w+)\((.*)\)$").unwrap();
-        static ref ARG_RE: Regex = Regex::new(r#"((?:[^,'"]|'[^']*'|"[^"]*")+)"#).unwrap();
-    }
-
-    let cleaned = action_str.trim();
-    let caps = FUNC_RE.captures(cleaned)?;
-    let function_name = caps.get(1)?.as_str().to_string();
-    let args_str = caps.get(2)?.as_str().trim();
-
-    let mut args: HashMap<String, String> = HashMap::new();
-    if !args_str.is_empty() {
-        for pair in ARG_RE.find_iter(args_str) {
-            let pair = pair.as_str();
-            let parts: Vec<&str> = pair.splitn(2, '=').collect();
-            if parts.len() != 2 {
-                continue;
+/// Calls one after another, usually separated by a blank line. Text that doesn't parse is skipped
+/// up to the next blank line; with no call at all there is one empty action, as before.
+fn parse_actions(action_str: &str) -> Vec<ParsedAction> {
+    let mut actions = Vec::new();
+    let mut rest = action_str;
+    while !rest.trim().is_empty() {
+        match parse_call(rest) {
+            Ok((call, after)) => {
+                actions.push(to_parsed_action(call));
+                rest = after;
+            }
+            Err(e) => {
+                log::debug!("skipping unparsable action text: {}: {:?}", e, rest);
+                match rest.find("\n\n") {
+                    Some(pos) => rest = &rest[pos + 2..],
+                    None => break,
+                }
             }
-            let key = parts[0].trim().to_string();
-            let value = parts[1].trim().trim_matches(|c| c == '\'' || c == '"').to_string();
-
-            args.insert(key, value);
         }
     }
-    Some(ParsedAction { function: function_name, args })
+    if actions.is_empty() {
+        actions.push(ParsedAction::default());
+    }
+    actions
+}
+
+//for example parse: click(start_box='(530,965)')
+//return ParsedAction { function: "click", args: {"start_box": "(530,965)"} }
+fn to_parsed_action(call: Call) -> ParsedAction {
+    let positional = positional_names(&call.name);
+    let mut args = HashMap::new();
+    let mut index = 0;
+    for (key, value) in call.args {
+        let key = key.unwrap_or_else(|| {
+            let name = positional.get(index).map(|n| n.to_string()).unwrap_or_else(|| format!("arg{}", index));
+            index += 1;
+            name
+        });
+        args.insert(key, value.to_arg_string());
+    }
+    ParsedAction { function: call.name, args }
+}
+
+/// parameter names for models that pass arguments without keywords, in `ACTION_SCHEMA` order
+fn positional_names(function: &str) -> Vec<&'static str> {
+    lookup_action(function).map(|spec| spec.params.iter().map(|param| param.name).collect()).unwrap_or_default()
 }
@@ -0,0 +1,174 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, Content};
+use serde::{Deserialize, Serialize};
+use small_target_image::hamming_distance;
+
+use crate::promps::fnv1a;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    Off,
+    /// serve hits, record misses
+    ReadWrite,
+    /// serve hits, never write; the agent fails on a miss instead of calling the model
+    ReadOnly,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub dir: PathBuf,
+    pub mode: CacheMode,
+    /// least recently used entries go first once there are more
+    pub max_entries: usize,
+    pub max_age: Option<Duration>,
+    /// screenshots whose perceptual hashes differ in at most this many bits count as the same screen
+    pub max_distance: u32,
+}
+
+impl CacheConfig {
+    pub fn new(dir: impl Into<PathBuf>, mode: CacheMode) -> Self {
+        Self {
+            dir: dir.into(),
+            mode,
+            max_entries: 10_000,
+            max_age: None,
+            max_distance: 4,
+        }
+    }
+}
+
+/// What a cached response depends on; the screen part is matched approximately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub model: String,
+    pub prompt_version: String,
+    /// `dhash` of the screenshot sent
+    pub screen_hash: u64,
+    /// `digest_messages` of everything else sent
+    pub history_digest: u64,
+}
+
+impl CacheKey {
+    /// hash of model, prompt version and history, the part that has to match exactly
+    fn prefix(&self) -> String {
+        let exact = format!("{}\n{}\n{:016x}", self.model, self.prompt_version, self.history_digest);
+        format!("{:016x}", fnv1a(exact.as_bytes()))
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}-{:016x}.json", self.prefix(), self.screen_hash)
+    }
+}
+
+/// Digest of the text of the messages, images are left to the screen hash.
+pub fn digest_messages(messages: &[ChatCompletionMessage]) -> u64 {
+    let mut text = String::new();
+    for message in messages {
+        text.push_str(&format!("{:?}\n", message.role));
+        match &message.content {
+            Content::Text(t) => text.push_str(t),
+            Content::ImageUrl(parts) => parts.iter().filter_map(|part| part.text.as_deref()).for_each(|t| text.push_str(t)),
+        }
+        text.push('\0');
+    }
+    fnv1a(text.as_bytes())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub text: String,
+    pub model: String,
+    pub prompt_version: String,
+    /// unix seconds
+    pub created: u64,
+}
+
+/// On-disk model responses, one json file per entry.
+pub struct ResponseCache {
+    config: CacheConfig,
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig) -> Result<Self> {
+        if config.mode == CacheMode::ReadWrite {
+            fs::create_dir_all(&config.dir)?;
+        }
+        Ok(Self { config })
+    }
+
+    pub fn mode(&self) -> CacheMode {
+        self.config.mode
+    }
+
+    /// The entry with the closest screen hash within `max_distance`.
+    pub fn get(&self, key: &CacheKey) -> Option<CachedResponse> {
+        if self.config.mode == CacheMode::Off {
+            return None;
+        }
+        let prefix = format!("{}-", key.prefix());
+        let (distance, path) = fs::read_dir(&self.config.dir)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| !self.is_expired(path))
+            .filter_map(|path| {
+                let stem = path.file_stem()?.to_str()?.strip_prefix(&prefix)?;
+                let hash = u64::from_str_radix(stem, 16).ok()?;
+                Some((hamming_distance(hash, key.screen_hash), path))
+            })
+            .filter(|(distance, _)| *distance <= self.config.max_distance)
+            .min_by_key(|(distance, _)| *distance)?;
+        let response: CachedResponse = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+        log::debug!("cache hit {:?}, screen distance {}", path.file_name(), distance);
+        if self.config.mode == CacheMode::ReadWrite {
+            // refresh the modification time, eviction is least recently used first
+            let _ = fs::File::options().append(true).open(&path).and_then(|file| file.set_modified(SystemTime::now()));
+        }
+        Some(response)
+    }
+
+    /// Records a response, a no-op unless the mode is `ReadWrite`.
+    pub fn put(&self, key: &CacheKey, text: &str) -> Result<()> {
+        if self.config.mode != CacheMode::ReadWrite {
+            return Ok(());
+        }
+        let response = CachedResponse {
+            text: text.to_string(),
+            model: key.model.clone(),
+            prompt_version: key.prompt_version.clone(),
+            created: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+        };
+        fs::write(self.config.dir.join(key.file_name()), serde_json::to_string_pretty(&response)?)?;
+        self.evict()?;
+        Ok(())
+    }
+
+    /// Removes expired entries and the least recently used ones above `max_entries`, returns how many.
+    pub fn evict(&self) -> Result<usize> {
+        let mut entries: Vec<(SystemTime, PathBuf)> = fs::read_dir(&self.config.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|e| e == "json"))
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect();
+        entries.sort();
+        let excess = entries.len().saturating_sub(self.config.max_entries);
+        let mut removed = 0;
+        for (i, (_, path)) in entries.iter().enumerate() {
+            if i < excess || self.is_expired(path) {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn is_expired(&self, path: &Path) -> bool {
+        let Some(max_age) = self.config.max_age else {
+            return false;
+        };
+        fs::metadata(path).and_then(|m| m.modified()).ok().and_then(|modified| modified.elapsed().ok()).is_some_and(|age| age > max_age)
+    }
+}
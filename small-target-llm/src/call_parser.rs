@@ -0,0 +1,221 @@
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// An argument value of a Python-call-like action string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgValue {
+    /// quoted string, escapes decoded
+    Str(String),
+    /// unquoted number or word, e.g. `100` or `down`
+    Bare(String),
+    /// `(..)` or `[..]`
+    Seq(Vec<ArgValue>),
+}
+
+impl ArgValue {
+    /// The text the action parser stores, sequences as `[a,b]`.
+    pub fn to_arg_string(&self) -> String {
+        match self {
+            ArgValue::Str(s) | ArgValue::Bare(s) => s.clone(),
+            ArgValue::Seq(items) => format!("[{}]", items.iter().map(ArgValue::to_arg_string).collect::<Vec<_>>().join(",")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub name: String,
+    /// in order, `None` for positional arguments
+    pub args: Vec<(Option<String>, ArgValue)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallParseError {
+    /// byte offset into the input
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CallParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+impl Error for CallParseError {}
+
+/// Parses `name(arg, key='value', box=(1, 2))` at the start of `input` (leading whitespace allowed),
+/// returns the call and whatever follows the closing parenthesis.
+pub fn parse_call(input: &str) -> Result<(Call, &str), CallParseError> {
+    let mut parser = Parser {
+        input,
+        chars: input.char_indices().peekable(),
+    };
+    let call = parser.call()?;
+    let rest = match parser.chars.peek() {
+        Some((pos, _)) => &input[*pos..],
+        None => "",
+    };
+    Ok((call, rest))
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn position(&mut self) -> usize {
+        self.chars.peek().map(|(pos, _)| *pos).unwrap_or(self.input.len())
+    }
+
+    fn error<T>(&mut self, message: &str) -> Result<T, CallParseError> {
+        Err(CallParseError {
+            position: self.position(),
+            message: message.to_string(),
+        })
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), CallParseError> {
+        match self.chars.next_if(|(_, c)| *c == expected) {
+            Some(_) => Ok(()),
+            None => self.error(&format!("expected '{}'", expected)),
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        let mut name = String::new();
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+            name.push(c);
+        }
+        name
+    }
+
+    fn call(&mut self) -> Result<Call, CallParseError> {
+        self.skip_whitespace();
+        let name = self.identifier();
+        if name.is_empty() {
+            return self.error("expected an action name");
+        }
+        self.skip_whitespace();
+        self.expect('(')?;
+        let mut args = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.chars.next_if(|(_, c)| *c == ')').is_some() {
+                return Ok(Call { name, args });
+            }
+            args.push(self.argument()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ')')) => return Ok(Call { name, args }),
+                Some((pos, c)) => {
+                    return Err(CallParseError {
+                        position: pos,
+                        message: format!("unexpected '{}' after argument", c),
+                    })
+                }
+                None => return self.error("unclosed argument list"),
+            }
+        }
+    }
+
+    /// `key=value` or a positional value
+    fn argument(&mut self) -> Result<(Option<String>, ArgValue), CallParseError> {
+        let is_keyword = {
+            let rest = &self.input[self.position()..];
+            let name_len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            name_len > 0 && rest[name_len..].trim_start().starts_with('=')
+        };
+        if is_keyword {
+            let key = self.identifier();
+            self.skip_whitespace();
+            self.expect('=')?;
+            self.skip_whitespace();
+            Ok((Some(key), self.value()?))
+        } else {
+            Ok((None, self.value()?))
+        }
+    }
+
+    fn value(&mut self) -> Result<ArgValue, CallParseError> {
+        match self.chars.peek().map(|(_, c)| *c) {
+            Some(quote @ ('\'' | '"')) => {
+                self.chars.next();
+                self.string(quote)
+            }
+            Some(open @ ('(' | '[')) => {
+                self.chars.next();
+                self.sequence(if open == '(' { ')' } else { ']' })
+            }
+            Some(_) => self.bare(),
+            None => self.error("expected a value"),
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<ArgValue, CallParseError> {
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, 'r')) => value.push('\r'),
+                    Some((_, c @ ('\\' | '\'' | '"'))) => value.push(c),
+                    // unknown escapes stay as written, e.g. Windows paths
+                    Some((_, c)) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => return self.error("unterminated string"),
+                },
+                Some((_, c)) if c == quote => return Ok(ArgValue::Str(value)),
+                Some((_, c)) => value.push(c),
+                None => return self.error("unterminated string"),
+            }
+        }
+    }
+
+    fn sequence(&mut self, close: char) -> Result<ArgValue, CallParseError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.chars.next_if(|(_, c)| *c == close).is_some() {
+                return Ok(ArgValue::Seq(items));
+            }
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, c)) if c == close => return Ok(ArgValue::Seq(items)),
+                Some((pos, c)) => {
+                    return Err(CallParseError {
+                        position: pos,
+                        message: format!("unexpected '{}' in sequence", c),
+                    })
+                }
+                None => return self.error("unclosed sequence"),
+            }
+        }
+    }
+
+    /// runs to the next `,` or closing bracket, surrounding whitespace trimmed
+    fn bare(&mut self) -> Result<ArgValue, CallParseError> {
+        let mut value = String::new();
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| !matches!(c, ',' | ')' | ']' | '(' | '[' | '\'' | '"')) {
+            value.push(c);
+        }
+        let value = value.trim();
+        if value.is_empty() {
+            return self.error("expected a value");
+        }
+        Ok(ArgValue::Bare(value.to_string()))
+    }
+}
@@ -0,0 +1,220 @@
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use openai_api_rs::v1::chat_completion::{ChatCompletionRequest, ChatCompletionResponse};
+use rand::Rng;
+use reqwest::StatusCode;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::time::{sleep_until, Instant};
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// OpenAI compatible endpoint, e.g. `http://localhost:8000/v1`
+    pub base_url: String,
+    pub api_key: String,
+    /// whole request timeout, including reading the body
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// retries after the first attempt, only for retryable errors
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    /// longest wait between two attempts, a server's `Retry-After` included
+    pub max_backoff: Duration,
+    /// requests in flight at once
+    pub max_concurrency: usize,
+    /// minimum gap between two request starts, zero disables rate limiting
+    pub min_interval: Duration,
+}
+
+impl ClientConfig {
+    pub fn new(base_url: &str, api_key: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            timeout: Duration::from_secs(120),
+            connect_timeout: Duration::from_secs(10),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_concurrency: 4,
+            min_interval: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LlmError {
+    Timeout,
+    /// 429, `retry_after` from the response header when present
+    RateLimited { retry_after: Option<Duration>, body: String },
+    /// 5xx and other retryable statuses (408, 425)
+    Server { status: u16, body: String },
+    /// 4xx, retrying won't help
+    Client { status: u16, body: String },
+    Connection(String),
+    InvalidRequest(String),
+    InvalidResponse(String),
+}
+
+impl LlmError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, LlmError::Timeout | LlmError::RateLimited { .. } | LlmError::Server { .. } | LlmError::Connection(_))
+    }
+
+    fn from_status(status: StatusCode, retry_after: Option<Duration>, body: String) -> Self {
+        match status.as_u16() {
+            429 => LlmError::RateLimited { retry_after, body },
+            408 | 425 | 500..=599 => LlmError::Server { status: status.as_u16(), body },
+            code => LlmError::Client { status: code, body },
+        }
+    }
+}
+
+impl fmt::Display for LlmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LlmError::Timeout => write!(f, "request timed out"),
+            LlmError::RateLimited { retry_after, body } => write!(f, "rate limited (retry after {:?}): {}", retry_after, body),
+            LlmError::Server { status, body } => write!(f, "server error {}: {}", status, body),
+            LlmError::Client { status, body } => write!(f, "request rejected {}: {}", status, body),
+            LlmError::Connection(e) => write!(f, "connection failed: {}", e),
+            LlmError::InvalidRequest(e) => write!(f, "invalid request: {}", e),
+            LlmError::InvalidResponse(e) => write!(f, "invalid response: {}", e),
+        }
+    }
+}
+
+impl Error for LlmError {}
+
+impl From<reqwest::Error> for LlmError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            LlmError::Timeout
+        } else if error.is_decode() {
+            LlmError::InvalidResponse(error.to_string())
+        } else {
+            LlmError::Connection(error.to_string())
+        }
+    }
+}
+
+/// Chat completion client with timeouts, retries and a concurrency/rate limiter.
+/// Keep one per endpoint, connections are pooled inside it.
+pub struct LlmClient {
+    http: reqwest::Client,
+    config: ClientConfig,
+    permits: Arc<Semaphore>,
+    next_start: Mutex<Instant>,
+}
+
+impl LlmClient {
+    pub fn new(config: ClientConfig) -> Result<Self, LlmError> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .build()
+            .map_err(|e| LlmError::Connection(e.to_string()))?;
+        Ok(Self {
+            http,
+            permits: Arc::new(Semaphore::new(config.max_concurrency.max(1))),
+            next_start: Mutex::new(Instant::now()),
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    pub async fn chat_completion(&self, request: &ChatCompletionRequest) -> Result<ChatCompletionResponse, LlmError> {
+        let body = serde_json::to_value(request).map_err(|e| LlmError::InvalidRequest(e.to_string()))?;
+        let text = self.post_json("chat/completions", &body).await?;
+        serde_json::from_str(&text).map_err(|e| LlmError::InvalidResponse(format!("{}: {}", e, text)))
+    }
+
+    /// POST with retries, returns the raw response body.
+    pub async fn post_json(&self, path: &str, body: &serde_json::Value) -> Result<String, LlmError> {
+        self.with_retries(|| self.send_once(path, body)).await
+    }
+
+    /// Runs `operation` again on retryable errors, with backoff in between.
+    pub async fn with_retries<T, F, Fut>(&self, mut operation: F) -> Result<T, LlmError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, LlmError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let error = match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            if !error.is_retryable() || attempt >= self.config.max_retries {
+                log::error!("llm request failed after {} attempt(s): {}", attempt + 1, error);
+                return Err(error);
+            }
+            let delay = self.backoff(attempt, &error);
+            log::warn!("llm request attempt {} failed: {}, retrying in {:?}", attempt + 1, error, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends without retrying, still limited by the concurrency and rate settings.
+    pub async fn send(&self, path: &str, body: &serde_json::Value) -> Result<reqwest::Response, LlmError> {
+        let _permit = self.permits.acquire().await.map_err(|e| LlmError::Connection(e.to_string()))?;
+        self.wait_rate_limit().await;
+
+        let response = self
+            .http
+            .post(format!("{}/{}", self.config.base_url, path))
+            .bearer_auth(&self.config.api_key)
+            .timeout(self.config.timeout)
+            .json(body)
+            .send()
+            .await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let body = response.text().await.unwrap_or_default();
+        Err(LlmError::from_status(status, retry_after, body))
+    }
+
+    async fn send_once(&self, path: &str, body: &serde_json::Value) -> Result<String, LlmError> {
+        Ok(self.send(path, body).await?.text().await?)
+    }
+
+    async fn wait_rate_limit(&self) {
+        if self.config.min_interval.is_zero() {
+            return;
+        }
+        let mut next_start = self.next_start.lock().await;
+        let now = Instant::now();
+        let start = (*next_start).max(now);
+        *next_start = start + self.config.min_interval;
+        drop(next_start);
+        sleep_until(start).await;
+    }
+
+    /// exponential backoff with equal jitter, never shorter than the server's `Retry-After` up to `max_backoff`
+    fn backoff(&self, attempt: u32, error: &LlmError) -> Duration {
+        let exponential = self.config.initial_backoff.saturating_mul(1u32 << attempt.min(16)).min(self.config.max_backoff);
+        let half = exponential / 2;
+        let jitter = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+        let delay = half + Duration::from_millis(jitter);
+        match error {
+            LlmError::RateLimited { retry_after: Some(retry_after), .. } => delay.max((*retry_after).min(self.config.max_backoff)),
+            _ => delay,
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::promps::FACTOR;
+
+/// How the numbers a model emits relate to the screenshot it was sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoordinateScale {
+    /// divided by the factor, e.g. 0-1000 for UI-TARS and Qwen2-VL
+    Relative(f32, f32),
+    /// pixels of the (resized) image the model saw
+    Absolute(u32, u32),
+}
+
+impl CoordinateScale {
+    fn normalize(&self, numbers: &[f32]) -> [f32; 4] {
+        let (sx, sy) = match *self {
+            CoordinateScale::Relative(fx, fy) => (fx, fy),
+            CoordinateScale::Absolute(w, h) => (w as f32, h as f32),
+        };
+        let (x1, y1) = (numbers[0] / sx, numbers[1] / sy);
+        if numbers.len() >= 4 {
+            [x1, y1, numbers[2] / sx, numbers[3] / sy]
+        } else {
+            [x1, y1, x1, y1]
+        }
+    }
+}
+
+/// Turns the text of a `start_box`/`end_box` argument into a normalised `[x1, y1, x2, y2]` in 0..1.
+pub trait CoordinateDecoder: Send + Sync {
+    /// `None` when the text isn't in this decoder's format
+    fn decode(&self, raw: &str) -> Option<[f32; 4]>;
+}
+
+/// numbers separated by commas, whitespace or `),(`, 2 or 4 of them
+fn numbers(text: &str) -> Option<Vec<f32>> {
+    let numbers: Vec<f32> = text
+        .split(|c: char| c == ',' || c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']'))
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f32>().ok())
+        .collect::<Option<_>>()?;
+    matches!(numbers.len(), 2 | 4).then_some(numbers)
+}
+
+/// `(x,y)`, `[x1,y1,x2,y2]` and `x,y`
+pub struct PlainDecoder {
+    pub scale: CoordinateScale,
+}
+
+impl PlainDecoder {
+    pub fn relative(factor: (f32, f32)) -> Self {
+        Self {
+            scale: CoordinateScale::Relative(factor.0, factor.1),
+        }
+    }
+
+    pub fn absolute(image_size: (u32, u32)) -> Self {
+        Self {
+            scale: CoordinateScale::Absolute(image_size.0, image_size.1),
+        }
+    }
+}
+
+impl CoordinateDecoder for PlainDecoder {
+    fn decode(&self, raw: &str) -> Option<[f32; 4]> {
+        let raw = raw.trim();
+        if raw.contains('<') {
+            return None;
+        }
+        numbers(raw).map(|n| self.scale.normalize(&n))
+    }
+}
+
+/// `<|box_start|>(x1,y1),(x2,y2)<|box_end|>` or `<|box_start|>(x,y)<|box_end|>`
+pub struct BoxTokenDecoder {
+    pub scale: CoordinateScale,
+}
+
+impl CoordinateDecoder for BoxTokenDecoder {
+    fn decode(&self, raw: &str) -> Option<[f32; 4]> {
+        let inner = raw.trim().strip_prefix("<|box_start|>")?;
+        let inner = inner.strip_suffix("<|box_end|>").unwrap_or(inner);
+        numbers(inner).map(|n| self.scale.normalize(&n))
+    }
+}
+
+/// `<point>x y</point>` or `<point>(x,y)</point>`, also `<bbox>x1 y1 x2 y2</bbox>`
+pub struct PointTagDecoder {
+    pub scale: CoordinateScale,
+}
+
+impl CoordinateDecoder for PointTagDecoder {
+    fn decode(&self, raw: &str) -> Option<[f32; 4]> {
+        let raw = raw.trim();
+        let inner = ["point", "bbox"].iter().find_map(|tag| {
+            let inner = raw.strip_prefix(&format!("<{}>", tag))?;
+            Some(inner.strip_suffix(&format!("</{}>", tag)).unwrap_or(inner))
+        })?;
+        numbers(inner).map(|n| self.scale.normalize(&n))
+    }
+}
+
+/// Tries each decoder in order.
+pub struct DecoderChain(pub Vec<Box<dyn CoordinateDecoder>>);
+
+impl DecoderChain {
+    /// every known format with the same scale
+    pub fn all_formats(scale: CoordinateScale) -> Self {
+        DecoderChain(vec![Box::new(BoxTokenDecoder { scale }), Box::new(PointTagDecoder { scale }), Box::new(PlainDecoder { scale })])
+    }
+}
+
+impl CoordinateDecoder for DecoderChain {
+    fn decode(&self, raw: &str) -> Option<[f32; 4]> {
+        self.0.iter().find_map(|decoder| decoder.decode(raw))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateSpace {
+    /// numbers relative to `ModelProfile::factor`
+    Relative,
+    /// pixels of the image sent to the model
+    Absolute,
+}
+
+/// Output conventions of a model family: parser mode and coordinate format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelProfile {
+    pub name: String,
+    /// `parse_action_vlm` mode, "bc" or "o1"
+    pub mode: String,
+    pub space: CoordinateSpace,
+    pub factor: (f32, f32),
+}
+
+impl ModelProfile {
+    /// UI-TARS 1.0 and Qwen2-VL: 0-1000 relative coordinates
+    pub fn ui_tars() -> Self {
+        Self {
+            name: "ui-tars".to_string(),
+            mode: "bc".to_string(),
+            space: CoordinateSpace::Relative,
+            factor: FACTOR,
+        }
+    }
+
+    /// UI-TARS 1.5: `<|box_start|>` tokens in pixels of the resized image
+    pub fn ui_tars_1_5() -> Self {
+        Self {
+            name: "ui-tars-1.5".to_string(),
+            space: CoordinateSpace::Absolute,
+            ..Self::ui_tars()
+        }
+    }
+
+    /// Qwen2.5-VL: absolute pixels, `<point>` or plain numbers
+    pub fn qwen2_5_vl() -> Self {
+        Self {
+            name: "qwen2.5-vl".to_string(),
+            space: CoordinateSpace::Absolute,
+            ..Self::ui_tars()
+        }
+    }
+
+    /// Best guess from the served model name, UI-TARS 1.0 conventions otherwise.
+    pub fn for_model(model_name: &str) -> Self {
+        let name = model_name.to_lowercase();
+        if name.contains("ui-tars-1.5") || name.contains("ui-tars-1_5") || name.contains("uitars-1.5") {
+            Self::ui_tars_1_5()
+        } else if name.contains("qwen2.5-vl") || name.contains("qwen2_5_vl") || name.contains("qwen2.5vl") {
+            Self::qwen2_5_vl()
+        } else {
+            Self::ui_tars()
+        }
+    }
+
+    /// What the numbers of a box mean, for prompts and tool schemas.
+    pub fn describe_space(&self) -> String {
+        match self.space {
+            CoordinateSpace::Relative => format!("0-{} screenshot coordinates", self.factor.0),
+            CoordinateSpace::Absolute => "pixels of the screenshot".to_string(),
+        }
+    }
+
+    /// A normalised point in the numbers this model uses, the reverse of `decoder`.
+    pub fn encode_point(&self, point: (f32, f32), image_size: (u32, u32)) -> (i32, i32) {
+        let (sx, sy) = match self.space {
+            CoordinateSpace::Relative => self.factor,
+            CoordinateSpace::Absolute => (image_size.0 as f32, image_size.1 as f32),
+        };
+        ((point.0 * sx).round() as i32, (point.1 * sy).round() as i32)
+    }
+
+    /// `image_size` is the size of the image actually sent, after resizing.
+    pub fn decoder(&self, image_size: (u32, u32)) -> DecoderChain {
+        let scale = match self.space {
+            CoordinateSpace::Relative => CoordinateScale::Relative(self.factor.0, self.factor.1),
+            CoordinateSpace::Absolute => CoordinateScale::Absolute(image_size.0, image_size.1),
+        };
+        DecoderChain::all_formats(scale)
+    }
+}
+
+impl Default for ModelProfile {
+    fn default() -> Self {
+        Self::ui_tars()
+    }
+}
@@ -1,9 +1,36 @@
 
+pub mod client;
+pub use client::{ClientConfig, LlmClient, LlmError};
+
+pub mod streaming;
+pub use streaming::{stream_prediction, stream_prediction_with, StreamEvent, StreamingActionParser};
+
+pub mod tool_calling;
+pub use tool_calling::{parse_tool_calls, ToolSpec};
+
+pub mod usage;
+pub use usage::{Budget, BudgetExceeded, ModelPricing, TokenUsage, UsageTracker};
+
 pub mod openai_request;
 pub use openai_request::{OpenAiProtocalCallPayload, openai_request, MAX_PIXELS};
 
+pub mod pipeline;
+pub use pipeline::{Grounder, PlannedStep, Planner, ProviderConfig};
+
 pub mod promps;
-pub use promps::get_system_prompt;
+pub use promps::{get_system_prompt, PromptRegistry, PromptTemplate, PromptVars, RenderedPrompt};
+
+pub mod call_parser;
+pub use call_parser::{parse_call, ArgValue, Call, CallParseError};
 
 pub mod action_parser;
-pub use action_parser::{parse_action_vlm, PredictionParsed};
+pub use action_parser::{parse_action_vlm, parse_action_vlm_with, PredictionParsed};
+
+pub mod cache;
+pub use cache::{digest_messages, CacheConfig, CacheKey, CacheMode, CachedResponse, ResponseCache};
+
+pub mod voting;
+pub use voting::{sample_completions, vote, SampleMode, Samples, Vote, VotingConfig};
+
+pub mod coordinates;
+pub use coordinates::{CoordinateDecoder, CoordinateScale, ModelProfile};
@@ -1,10 +1,7 @@
-use anyhow::{anyhow, Ok, Result};
-use openai_api_rs::v1::{
-    api::OpenAIClient,
-    chat_completion::{ChatCompletionMessage, ChatCompletionRequest, ChatCompletionResponse},
-    error::APIError,
-};
-use std::result::Result::Ok as StdOK;
+use anyhow::Result;
+use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, ChatCompletionRequest, ChatCompletionResponse, Content, ContentType, ImageUrl, ImageUrlType, MessageRole};
+
+use crate::client::{ClientConfig, LlmClient};
 
 /// max pixels of image to send to llm
 pub const MAX_PIXELS: u32 = 1350 * 28 * 28;
@@ -41,40 +38,55 @@ impl OpenAiProtocalCallPayload {
             max_tokens,
         }
     }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    pub fn into_request(self) -> ChatCompletionRequest {
+        let mut messages = Vec::new();
+
+        messages.extend(self.contents);
+        messages.extend(self.history);
+
+        ChatCompletionRequest::new(self.model_name, messages)
+            .temperature(self.temperature)
+            .top_p(self.top_p)
+            .max_tokens(self.max_tokens)
+    }
 }
 
+/// One-off request with the default `ClientConfig`, keep an `LlmClient` around to reuse the limiter settings.
 pub async fn openai_request(payload: OpenAiProtocalCallPayload) -> Result<ChatCompletionResponse> {
-    let client = OpenAIClient::builder()
-        .with_endpoint(payload.base_url)
-        .with_api_key(payload.api_key)
-        .build()
-        .map_err(|e| anyhow::anyhow!("Failed to build OpenAIClient: {}", e))?;
-
-    let mut messages = Vec::new();
+    let client = LlmClient::new(ClientConfig::new(payload.base_url(), payload.api_key()))?;
+    Ok(client.chat_completion(&payload.into_request()).await?)
+}
 
-    messages.extend(payload.contents);
-    messages.extend(payload.history);
+pub fn text_message(role: MessageRole, text: String) -> ChatCompletionMessage {
+    ChatCompletionMessage {
+        role,
+        content: Content::Text(text),
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
 
-    let req = ChatCompletionRequest::new(payload.model_name, messages)
-        .temperature(payload.temperature)
-        .top_p(payload.top_p)
-        .max_tokens(payload.max_tokens);
-    let result = match client.chat_completion(req).await {
-        StdOK(response) => response,
-        Err(e) => match e {
-            APIError::ReqwestError(e) => {
-                if let Some(status) = e.status() {
-                    log::error!("HTTP request failed with status: {} - {}", status, e);
-                } else {
-                    log::error!("Request failed with error: {}", e);
-                }
-                return Err(anyhow!("API request failed: {}", e));
-            }
-            APIError::CustomError { message } => {
-                log::error!("OpenAI API request failed: {}", message);
-                return Err(anyhow!("API error: {}", message));
-            }
-        },
-    };
-    Ok(result)
+/// user message holding a `data:` url from `image_to_base64`
+pub fn image_message(image_base64: String) -> ChatCompletionMessage {
+    ChatCompletionMessage {
+        role: MessageRole::user,
+        content: Content::ImageUrl(vec![ImageUrl {
+            text: None,
+            r#type: ContentType::image_url,
+            image_url: Some(ImageUrlType { url: image_base64 }),
+        }]),
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+    }
 }
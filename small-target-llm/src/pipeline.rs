@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Result};
+use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, ChatCompletionRequest, MessageRole};
+
+use crate::action_parser::{parse_action_vlm_with, PredictionParsed};
+use crate::client::{ClientConfig, LlmClient};
+use crate::coordinates::ModelProfile;
+use crate::openai_request::{image_message, text_message};
+use crate::usage::TokenUsage;
+
+/// Endpoint and sampling settings of one model in the planner/grounder pipeline.
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub client: ClientConfig,
+    pub model_name: String,
+    pub temperature: f64,
+    pub top_p: f64,
+    pub max_tokens: i64,
+    pub profile: ModelProfile,
+    /// send the screenshot, off for text-only planners
+    pub vision: bool,
+}
+
+impl ProviderConfig {
+    pub fn new(base_url: &str, model_name: &str, api_key: &str) -> Self {
+        Self {
+            client: ClientConfig::new(base_url, api_key),
+            model_name: model_name.to_string(),
+            temperature: 0.0,
+            top_p: 0.7,
+            max_tokens: 1000,
+            profile: ModelProfile::for_model(model_name),
+            vision: true,
+        }
+    }
+
+    pub fn request(&self, messages: Vec<ChatCompletionMessage>) -> ChatCompletionRequest {
+        ChatCompletionRequest::new(self.model_name.clone(), messages)
+            .temperature(self.temperature)
+            .top_p(self.top_p)
+            .max_tokens(self.max_tokens)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlannedStep {
+    /// natural language step for the grounder, e.g. "click the Save button"
+    Act { thought: String, step: String },
+    Finished { thought: String },
+    CallUser { thought: String },
+}
+
+/// `Thought: ...` then `Step: ...`, a missing `Step:` makes the whole text the step.
+pub fn parse_plan(text: &str) -> PlannedStep {
+    let text = text.trim();
+    let (head, step) = match text.rfind("Step:") {
+        Some(pos) => (&text[..pos], text[pos + "Step:".len()..].trim()),
+        None => ("", text),
+    };
+    let thought = head.trim().strip_prefix("Thought:").unwrap_or(head).trim().to_string();
+    let step = step.trim_matches(|c| c == '`' || c == '"' || c == '\'').trim();
+    match step.trim_end_matches("()").to_lowercase().as_str() {
+        "finished" => PlannedStep::Finished { thought },
+        "call_user" => PlannedStep::CallUser { thought },
+        _ => PlannedStep::Act {
+            thought,
+            step: step.to_string(),
+        },
+    }
+}
+
+pub struct Planned {
+    pub step: PlannedStep,
+    pub text: String,
+    pub usage: TokenUsage,
+}
+
+/// Decides the next step in words, keeping the steps done so far.
+pub struct Planner {
+    config: ProviderConfig,
+    client: LlmClient,
+    notes: Vec<String>,
+}
+
+impl Planner {
+    pub fn new(config: ProviderConfig) -> Result<Self> {
+        Ok(Self {
+            client: LlmClient::new(config.client.clone())?,
+            config,
+            notes: Vec::new(),
+        })
+    }
+
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    /// forget the steps of the previous task
+    pub fn reset(&mut self) {
+        self.notes.clear();
+    }
+
+    /// Adds a line to the progress the planner sees, e.g. a user instruction or a rejected action.
+    pub fn note(&mut self, text: &str) {
+        self.notes.push(text.to_string());
+    }
+
+    pub async fn plan(&mut self, system_prompt: &str, task: &str, screenshot_base64: Option<String>) -> Result<Planned> {
+        let mut messages = vec![text_message(MessageRole::user, format!("{}{}", system_prompt, task))];
+        if !self.notes.is_empty() {
+            let progress: Vec<String> = self.notes.iter().enumerate().map(|(i, note)| format!("{}. {}", i + 1, note)).collect();
+            messages.push(text_message(MessageRole::user, format!("Progress so far:\n{}", progress.join("\n"))));
+        }
+        if let Some(image) = screenshot_base64.filter(|_| self.config.vision) {
+            messages.push(image_message(image));
+        }
+
+        let response = self.client.chat_completion(&self.config.request(messages)).await?;
+        let text = response.choices.first().and_then(|c| c.message.content.clone()).ok_or_else(|| anyhow!("empty planner response"))?;
+        let step = parse_plan(&text);
+        if let PlannedStep::Act { step, .. } = &step {
+            self.notes.push(step.clone());
+        }
+        log::info!("planner: {:?}", step);
+        Ok(Planned {
+            step,
+            text,
+            usage: TokenUsage::from(&response.usage),
+        })
+    }
+}
+
+pub struct Grounded {
+    pub predictions: Vec<PredictionParsed>,
+    pub text: String,
+    pub usage: TokenUsage,
+}
+
+/// Turns one step in words into an action with coordinates on the screenshot.
+pub struct Grounder {
+    config: ProviderConfig,
+    client: LlmClient,
+}
+
+impl Grounder {
+    pub fn new(config: ProviderConfig) -> Result<Self> {
+        Ok(Self {
+            client: LlmClient::new(config.client.clone())?,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    /// `image_size` is the size of the image behind `screenshot_base64`, for absolute coordinates.
+    pub async fn ground(&self, system_prompt: &str, step: &str, screenshot_base64: String, image_size: (u32, u32)) -> Result<Grounded> {
+        let messages = vec![text_message(MessageRole::user, format!("{}{}", system_prompt, step)), image_message(screenshot_base64)];
+        let response = self.client.chat_completion(&self.config.request(messages)).await?;
+        let text = response.choices.first().and_then(|c| c.message.content.clone()).ok_or_else(|| anyhow!("empty grounder response"))?;
+        let predictions = parse_action_vlm_with(&text, &self.config.profile.decoder(image_size), &self.config.profile.mode);
+        Ok(Grounded {
+            predictions,
+            text,
+            usage: TokenUsage::from(&response.usage),
+        })
+    }
+}
@@ -1,37 +1,241 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use small_target_schema::{action_space_prompt, action_space_prompt_for};
+
 pub const FACTOR: (f32, f32) = (1000.0, 1000.0);
 
+/// only what the desktop executor can do on a mirrored phone screen, no app launcher or system buttons
+pub const MOBILE_ACTIONS: &[&str] = &["click", "type", "scroll", "drag", "wait", "finished", "call_user"];
+
+/// where to point, the planner decides everything else
+pub const GROUNDING_ACTIONS: &[&str] = &["click", "left_double", "right_single", "drag"];
+
+/// Version of the built-in templates, bump it whenever one of them changes.
+pub const BUILTIN_PROMPT_VERSION: &str = "v3";
+
+const VERSION_HEADER: &str = "<!-- version:";
+
+lazy_static! {
+    static ref BUILTIN: PromptRegistry = PromptRegistry::builtin();
+}
+
+/// Legacy entry point, the built-in `computer` template for the current platform.
 pub fn get_system_prompt(language: &str) -> String {
-    format!(
-        r#"You are a GUI agent. You are given a task and your action history, with screenshots. You need to perform the next action to complete the task.
-
-            ## Output Format
-            ```
-            Thought: ...
-            Action: ...
-            ```
-
-            ## Action Space
-            click(start_box='[x1, y1, x2, y2]')
-            left_double(start_box='[x1, y1, x2, y2]')
-            right_single(start_box='[x1, y1, x2, y2]')
-            drag(start_box='[x1, y1, x2, y2]', end_box='[x3, y3, x4, y4]')
-            hotkey(key='')
-            type(content='') #If you want to submit your input, use "\\n" at the end of `content`.
-            scroll(start_box='[x1, y1, x2, y2]', direction='down or up or right or left')
-            wait() #Sleep for 5s and take a screenshot to check for any changes.
-            finished()
-            call_user() # Submit the task and call the user when the task is unsolvable, or when you need the user's help.
-
-            ## Note
-            - Use {} in `Thought` part.
-            - Write a small plan and finally summarize your next action (with its target element) in one sentence in `Thought` part.
-
-            ## User Instruction
-        "#,
-        if language == "zh" {
-            "Chinese"
-        } else {
-            "English"
+    BUILTIN.render("computer", &PromptVars::new(language)).map(|p| p.text).unwrap_or_default()
+}
+
+/// "zh" -> "Chinese", anything that isn't a known code is used as is.
+pub fn language_name(language: &str) -> &str {
+    match language.to_lowercase().as_str() {
+        "zh" | "zh-cn" | "zh-tw" => "Chinese",
+        "en" | "en-us" | "en-gb" | "" => "English",
+        "ja" => "Japanese",
+        "ko" => "Korean",
+        "fr" => "French",
+        "de" => "German",
+        "es" => "Spanish",
+        "ru" => "Russian",
+        _ => language,
+    }
+}
+
+pub fn current_platform() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "macOS",
+        "windows" => "Windows",
+        "linux" => "Linux",
+        "android" => "Android",
+        "ios" => "iOS",
+        other => other,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PromptVars {
+    /// language code or name for the `Thought` part
+    pub language: String,
+    pub platform: String,
+    /// overrides the template's default action space
+    pub action_space: Option<String>,
+    /// rendered as extra `- ` lines in the notes
+    pub extra_notes: Vec<String>,
+}
+
+impl PromptVars {
+    pub fn new(language: &str) -> Self {
+        Self {
+            language: language.to_string(),
+            platform: current_platform().to_string(),
+            action_space: None,
+            extra_notes: Vec::new(),
+        }
+    }
+
+    fn values(&self) -> HashMap<&'static str, String> {
+        let mut values = HashMap::new();
+        values.insert("language", language_name(&self.language).to_string());
+        values.insert("platform", self.platform.clone());
+        values.insert("extra_notes", self.extra_notes.iter().map(|note| format!("- {}", note.trim())).collect::<Vec<_>>().join("\n"));
+        if let Some(action_space) = &self.action_space {
+            values.insert("action_space", action_space.trim().to_string());
+        }
+        values
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub version: String,
+    /// text with `{{variable}}` placeholders
+    pub body: String,
+    /// values used when `PromptVars` leaves a variable unset
+    pub defaults: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedPrompt {
+    pub text: String,
+    /// `name@version#hash`, stored with each step so a trajectory says which prompt produced it. The hash
+    /// covers the template and the action space, which change without a version bump when the schema does.
+    pub version_id: String,
+}
+
+impl PromptTemplate {
+    pub fn new(name: &str, version: &str, body: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            body: body.to_string(),
+            defaults: HashMap::new(),
         }
-    )
-}
\ No newline at end of file
+    }
+
+    pub fn with_default(mut self, variable: &str, value: &str) -> Self {
+        self.defaults.insert(variable.to_string(), value.to_string());
+        self
+    }
+
+    pub fn version_id(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+
+    /// Fails on unknown placeholders and on variables without a value.
+    pub fn render(&self, vars: &PromptVars) -> Result<RenderedPrompt> {
+        let values = vars.values();
+        let mut lines = Vec::new();
+        for line in self.body.lines() {
+            let rendered = self.render_line(line, &values)?;
+            // a line holding only an empty variable disappears instead of leaving a blank line
+            if rendered.trim().is_empty() && !line.trim().is_empty() {
+                continue;
+            }
+            lines.push(rendered.trim_end().to_string());
+        }
+        let mut text = lines.join("\n");
+        text.push('\n');
+        let action_space = values.get("action_space").or_else(|| self.defaults.get("action_space")).map(String::as_str).unwrap_or_default();
+        let hash = fnv1a(format!("{}\0{}", self.body, action_space).as_bytes()) as u32;
+        Ok(RenderedPrompt {
+            text,
+            version_id: format!("{}#{:08x}", self.version_id(), hash),
+        })
+    }
+
+    fn render_line(&self, line: &str, values: &HashMap<&'static str, String>) -> Result<String> {
+        let mut out = String::new();
+        let mut rest = line;
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..].find("}}").ok_or_else(|| anyhow!("unclosed placeholder in prompt {}: {}", self.name, line))? + start;
+            let variable = rest[start + 2..end].trim();
+            let value = match values.get(variable) {
+                Some(value) => value.as_str(),
+                None => self.defaults.get(variable).map(String::as_str).ok_or_else(|| anyhow!("no value for {{{{{}}}}} in prompt {}", variable, self.name))?,
+            };
+            out.push_str(&rest[..start]);
+            out.push_str(value);
+            rest = &rest[end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+/// Named prompt templates: the built-ins plus any loaded from files.
+#[derive(Debug, Clone, Default)]
+pub struct PromptRegistry {
+    templates: HashMap<String, PromptTemplate>,
+}
+
+impl PromptRegistry {
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        let version = BUILTIN_PROMPT_VERSION;
+        let computer_actions = action_space_prompt();
+        registry.insert(PromptTemplate::new("computer", version, include_str!("../prompts/computer.md")).with_default("action_space", &computer_actions));
+        registry.insert(PromptTemplate::new("browser", version, include_str!("../prompts/browser.md")).with_default("action_space", &computer_actions));
+        registry.insert(PromptTemplate::new("mobile", version, include_str!("../prompts/mobile.md")).with_default("action_space", &action_space_prompt_for(MOBILE_ACTIONS)));
+        registry.insert(PromptTemplate::new("grounding", version, include_str!("../prompts/grounding.md")).with_default("action_space", &action_space_prompt_for(GROUNDING_ACTIONS)));
+        registry.insert(PromptTemplate::new("planner", version, include_str!("../prompts/planner.md")));
+        registry
+    }
+
+    /// Adds or replaces the template with the same name.
+    pub fn insert(&mut self, template: PromptTemplate) {
+        self.templates.insert(template.name.clone(), template);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.templates.get(name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// The file stem is the template name. A first line `<!-- version: x -->` sets the version,
+    /// otherwise it is a hash of the content. Inherits defaults of a template it replaces.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<String> {
+        let path = path.as_ref();
+        let name = path.file_stem().and_then(|s| s.to_str()).ok_or_else(|| anyhow!("invalid template path {:?}", path))?;
+        let content = fs::read_to_string(path)?;
+        let (version, body) = match content.split_once('\n') {
+            Some((first, body)) if first.trim().starts_with(VERSION_HEADER) => {
+                let version = first.trim().trim_start_matches(VERSION_HEADER).trim_end_matches("-->").trim();
+                (version.to_string(), body.to_string())
+            }
+            _ => (format!("{:08x}", fnv1a(content.as_bytes()) as u32), content.clone()),
+        };
+        let mut template = PromptTemplate::new(name, &version, &body);
+        if let Some(existing) = self.templates.get(name) {
+            template.defaults = existing.defaults.clone();
+        }
+        log::info!("loaded prompt template {} from {:?}", template.version_id(), path);
+        self.insert(template);
+        Ok(name.to_string())
+    }
+
+    /// Loads every `.md` and `.txt` file of a directory, returns the names loaded.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<Vec<String>> {
+        let mut paths: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| matches!(path.extension().and_then(|e| e.to_str()), Some("md" | "txt")))
+            .collect();
+        paths.sort();
+        paths.iter().map(|path| self.load_file(path)).collect()
+    }
+
+    pub fn render(&self, name: &str, vars: &PromptVars) -> Result<RenderedPrompt> {
+        self.get(name).ok_or_else(|| anyhow!("unknown prompt template: {}", name))?.render(vars)
+    }
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
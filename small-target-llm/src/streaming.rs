@@ -0,0 +1,338 @@
+use std::time::Duration;
+
+use openai_api_rs::v1::chat_completion::ChatCompletionRequest;
+use tokio::sync::broadcast;
+
+use crate::action_parser::{parse_action_vlm_with, PredictionParsed};
+use crate::client::{LlmClient, LlmError};
+use crate::coordinates::{CoordinateDecoder, CoordinateScale, DecoderChain};
+use crate::usage::TokenUsage;
+
+const ACTION_MARKER: &str = "Action:";
+/// actions that end the response, no call can follow them
+const TERMINAL_ACTIONS: [&str; 2] = ["finished", "call_user"];
+/// how long to wait after a call's line ended for another call before taking the block as complete
+const CALL_GAP: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// new thought text, concatenated they give the whole `Thought:` part
+    Thought(String),
+    /// the action block is complete, sent before the stream ends
+    ActionsReady(Vec<PredictionParsed>),
+    /// full response text
+    Done(String),
+}
+
+/// Accumulates streamed text, splits off the thought and spots the moment the action block is complete.
+/// A response may hold several calls separated by blank lines, so a closed call only completes the block
+/// when it is terminal or followed by text that can't be another call. A call whose line just ended may
+/// still be followed by another one, see `call_line_ended`.
+pub struct StreamingActionParser {
+    decoder: Box<dyn CoordinateDecoder>,
+    mode: String,
+    text: String,
+    thought_sent: usize,
+    actions_ready: bool,
+}
+
+impl StreamingActionParser {
+    pub fn new(factor: (f32, f32), mode: &str) -> Self {
+        Self::with_decoder(Box::new(DecoderChain::all_formats(CoordinateScale::Relative(factor.0, factor.1))), mode)
+    }
+
+    pub fn with_decoder(decoder: Box<dyn CoordinateDecoder>, mode: &str) -> Self {
+        Self {
+            decoder,
+            mode: mode.to_string(),
+            text: String::new(),
+            thought_sent: 0,
+            actions_ready: false,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Feed a delta, returns the new thought text and, once, the parsed actions.
+    pub fn push(&mut self, delta: &str) -> (Option<String>, Option<Vec<PredictionParsed>>) {
+        self.text.push_str(delta);
+
+        let thought = self.stable_thought();
+        let thought_delta = if thought.len() > self.thought_sent {
+            let new = thought[self.thought_sent..].to_string();
+            self.thought_sent = thought.len();
+            Some(new)
+        } else {
+            None
+        };
+
+        let actions = if !self.actions_ready && self.action_block_complete() {
+            self.actions_ready = true;
+            Some(parse_action_vlm_with(&self.text, self.decoder.as_ref(), &self.mode))
+        } else {
+            None
+        };
+        (thought_delta, actions)
+    }
+
+    /// The end of the stream, returns the parsed actions unless `push` already did.
+    pub fn finish(&mut self) -> Option<Vec<PredictionParsed>> {
+        if self.actions_ready {
+            return None;
+        }
+        self.actions_ready = true;
+        Some(parse_action_vlm_with(&self.text, self.decoder.as_ref(), &self.mode))
+    }
+
+    /// The last call is closed and its line ended, but nothing came after it yet. Another call may
+    /// follow, the caller decides how long to wait before calling `finish`.
+    pub fn call_line_ended(&self) -> bool {
+        if self.actions_ready || self.mode == "o1" {
+            return false;
+        }
+        match self.after_last_call() {
+            Some(rest) => rest.contains('\n') && rest.trim().is_empty(),
+            None => false,
+        }
+    }
+
+    /// Text after the last closed call of the action block, `None` while there is none or a call is open.
+    fn after_last_call(&self) -> Option<&str> {
+        let pos = self.text.rfind(ACTION_MARKER)? + ACTION_MARKER.len();
+        let (_, end) = call_names(&self.text[pos..])?;
+        Some(&self.text[pos + end?..])
+    }
+
+    /// Thought text that can't change any more, holds back a possible partial `Action:` marker.
+    fn stable_thought(&self) -> &str {
+        let start = match self.text.find("Thought:") {
+            Some(pos) => pos + "Thought:".len(),
+            None => return "",
+        };
+        let rest = &self.text[start..];
+        let rest = &rest[rest.len() - rest.trim_start().len()..];
+        if let Some(end) = rest.find(ACTION_MARKER) {
+            return rest[..end].trim_end();
+        }
+        let mut end = rest.len();
+        for keep in (1..ACTION_MARKER.len()).rev() {
+            if end >= keep && rest.is_char_boundary(end - keep) && ACTION_MARKER.starts_with(&rest[end - keep..]) {
+                end -= keep;
+                break;
+            }
+        }
+        // trailing whitespace may be followed by `Action:`, keep it until we know
+        rest[..end].trim_end()
+    }
+
+    fn action_block_complete(&self) -> bool {
+        if self.mode == "o1" {
+            return self.text.contains("</Output>");
+        }
+        let Some(pos) = self.text.rfind(ACTION_MARKER) else {
+            return false;
+        };
+        let Some((names, _)) = call_names(&self.text[pos + ACTION_MARKER.len()..]) else {
+            return false;
+        };
+        if names.last().is_some_and(|name| TERMINAL_ACTIONS.contains(&name.as_str())) {
+            return true;
+        }
+        // a closed call, a line break, then something that isn't the start of another call
+        let Some(rest) = self.after_last_call() else {
+            return false;
+        };
+        let Some((_, next)) = rest.split_once('\n') else {
+            return false;
+        };
+        let next = next.trim_start();
+        let name_len = next.len() - next.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_').len();
+        next[name_len..].chars().next().is_some_and(|c| c != '(')
+    }
+}
+
+/// Names of the `name(...)` calls in the text and the byte offset just past the last closed one,
+/// `None` while a call is still open, quotes respected
+fn call_names(text: &str) -> Option<(Vec<String>, Option<usize>)> {
+    let mut names = Vec::new();
+    let mut end = None;
+    let mut name = String::new();
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' if depth > 0 => quote = Some(c),
+            '(' => {
+                if depth == 0 {
+                    names.push(std::mem::take(&mut name));
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    end = Some(i + 1);
+                }
+            }
+            c if depth == 0 && (c.is_alphanumeric() || c == '_') => name.push(c),
+            _ if depth == 0 => name.clear(),
+            _ => {}
+        }
+    }
+    (depth == 0 && quote.is_none()).then_some((names, end))
+}
+
+/// Reads `data:` lines of an OpenAI style server-sent-events response.
+pub struct ChatStream {
+    response: reqwest::Response,
+    /// raw bytes, a chunk may end inside a UTF-8 character so only whole lines are decoded
+    buffer: Vec<u8>,
+    done: bool,
+    usage: Option<TokenUsage>,
+}
+
+impl ChatStream {
+    /// token counts from the final chunk, only once the stream was read to the end
+    pub fn usage(&self) -> Option<TokenUsage> {
+        self.usage
+    }
+
+    /// next content delta, `None` at the end of the stream
+    pub async fn next_delta(&mut self) -> Result<Option<String>, LlmError> {
+        loop {
+            if let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else { continue };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    self.done = true;
+                    return Ok(None);
+                }
+                let chunk: serde_json::Value = serde_json::from_str(data).map_err(|e| LlmError::InvalidResponse(format!("{}: {}", e, data)))?;
+                if let (Some(prompt), Some(completion)) = (chunk["usage"]["prompt_tokens"].as_u64(), chunk["usage"]["completion_tokens"].as_u64()) {
+                    self.usage = Some(TokenUsage {
+                        prompt_tokens: prompt,
+                        completion_tokens: completion,
+                    });
+                }
+                if let Some(content) = chunk["choices"][0]["delta"]["content"].as_str() {
+                    if !content.is_empty() {
+                        return Ok(Some(content.to_string()));
+                    }
+                }
+                continue;
+            }
+            if self.done {
+                return Ok(None);
+            }
+            match self.response.chunk().await? {
+                Some(bytes) => self.buffer.extend_from_slice(&bytes),
+                None => {
+                    self.done = true;
+                    // last line without a trailing newline
+                    if !self.buffer.trim_ascii().is_empty() {
+                        self.buffer.push(b'\n');
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl LlmClient {
+    /// Starts a streaming completion, retrying only until the response headers arrive.
+    pub async fn chat_completion_stream(&self, request: &ChatCompletionRequest) -> Result<ChatStream, LlmError> {
+        let mut body = serde_json::to_value(request).map_err(|e| LlmError::InvalidRequest(e.to_string()))?;
+        body["stream"] = serde_json::Value::Bool(true);
+        body["stream_options"] = serde_json::json!({ "include_usage": true });
+        let response = self.with_retries(|| self.send("chat/completions", &body)).await?;
+        Ok(ChatStream {
+            response,
+            buffer: Vec::new(),
+            done: false,
+            usage: None,
+        })
+    }
+}
+
+pub struct StreamedPrediction {
+    /// text received until the action block completed (or the whole response)
+    pub text: String,
+    pub predictions: Vec<PredictionParsed>,
+    /// `None` when the stream was cut short before the server sent its usage chunk
+    pub usage: Option<TokenUsage>,
+}
+
+/// Stream a completion, publishing thought text as it arrives. Returns once the response ends, or
+/// as soon as the action block is complete instead of waiting for the rest: a terminal action, or a
+/// call whose line ended without another call following within `CALL_GAP`.
+pub async fn stream_prediction(
+    client: &LlmClient,
+    request: &ChatCompletionRequest,
+    factor: (f32, f32),
+    mode: &str,
+    events: &broadcast::Sender<StreamEvent>,
+) -> Result<StreamedPrediction, LlmError> {
+    stream_prediction_with(client, request, StreamingActionParser::new(factor, mode), events).await
+}
+
+/// `stream_prediction` with a parser set up for the model's coordinate format.
+pub async fn stream_prediction_with(
+    client: &LlmClient,
+    request: &ChatCompletionRequest,
+    mut parser: StreamingActionParser,
+    events: &broadcast::Sender<StreamEvent>,
+) -> Result<StreamedPrediction, LlmError> {
+    let mut stream = client.chat_completion_stream(request).await?;
+
+    loop {
+        let delta = if parser.call_line_ended() {
+            match tokio::time::timeout(CALL_GAP, stream.next_delta()).await {
+                Ok(delta) => delta?,
+                // no other call is coming, don't wait for the model to wind down
+                Err(_) => break,
+            }
+        } else {
+            stream.next_delta().await?
+        };
+        let Some(delta) = delta else { break };
+        let (thought, actions) = parser.push(&delta);
+        if let Some(thought) = thought {
+            // no subscribers is fine
+            let _ = events.send(StreamEvent::Thought(thought));
+        }
+        if let Some(predictions) = actions {
+            let _ = events.send(StreamEvent::ActionsReady(predictions.clone()));
+            let _ = events.send(StreamEvent::Done(parser.text().to_string()));
+            return Ok(StreamedPrediction {
+                text: parser.text().to_string(),
+                predictions,
+                usage: stream.usage(),
+            });
+        }
+    }
+
+    let text = parser.text().to_string();
+    let predictions = parser.finish().unwrap_or_default();
+    let _ = events.send(StreamEvent::ActionsReady(predictions.clone()));
+    let _ = events.send(StreamEvent::Done(text.clone()));
+    Ok(StreamedPrediction {
+        text,
+        predictions,
+        usage: stream.usage(),
+    })
+}
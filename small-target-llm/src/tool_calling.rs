@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use openai_api_rs::v1::chat_completion::{ChatCompletionMessageForResponse, ChatCompletionRequest, ChatCompletionResponse};
+use serde_json::{json, Value};
+
+use crate::action_parser::{ActionParsed, PredictionParsed};
+use crate::client::{LlmClient, LlmError};
+use crate::coordinates::CoordinateDecoder;
+
+/// One action offered to the model as an OpenAI function tool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments object
+    pub parameters: Value,
+}
+
+impl ToolSpec {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            }
+        })
+    }
+}
+
+impl LlmClient {
+    /// Chat completion with `tools` attached, the model answers through `tool_calls`.
+    pub async fn chat_completion_with_tools(&self, request: &ChatCompletionRequest, tools: &[ToolSpec]) -> Result<ChatCompletionResponse, LlmError> {
+        let mut body = serde_json::to_value(request).map_err(|e| LlmError::InvalidRequest(e.to_string()))?;
+        body["tools"] = Value::Array(tools.iter().map(ToolSpec::to_json).collect());
+        body["tool_choice"] = Value::String("auto".to_string());
+        let text = self.post_json("chat/completions", &body).await?;
+        serde_json::from_str(&text).map_err(|e| LlmError::InvalidResponse(format!("{}: {}", e, text)))
+    }
+}
+
+/// Turns the `tool_calls` of a response message into the same predictions `parse_action_vlm` gives,
+/// boxes read by the model's `decoder`. The message text, if any, is the thought.
+pub fn parse_tool_calls(message: &ChatCompletionMessageForResponse, decoder: &dyn CoordinateDecoder) -> Result<Vec<PredictionParsed>, LlmError> {
+    let thought = message.content.clone().unwrap_or_default().trim().to_string();
+    let mut predictions = Vec::new();
+    for call in message.tool_calls.iter().flatten() {
+        let name = call.function.name.clone().ok_or_else(|| LlmError::InvalidResponse(format!("tool call {} without a name", call.id)))?;
+        let arguments = call.function.arguments.as_deref().unwrap_or("{}");
+        let arguments: Value = if arguments.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(arguments).map_err(|e| LlmError::InvalidResponse(format!("arguments of {}: {}: {}", name, e, arguments)))?
+        };
+        let Value::Object(arguments) = arguments else {
+            return Err(LlmError::InvalidResponse(format!("arguments of {} are not an object", name)));
+        };
+
+        let mut action_inputs = HashMap::new();
+        for (param_name, value) in arguments {
+            let input = if param_name.ends_with("_box") {
+                normalize_box(&value, decoder).ok_or_else(|| LlmError::InvalidResponse(format!("invalid {} of {}: {}", param_name, name, value)))?
+            } else {
+                match value {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                }
+            };
+            action_inputs.insert(param_name, input);
+        }
+        predictions.push(PredictionParsed {
+            reflection: None,
+            thought: thought.clone(),
+            action_parsed: ActionParsed {
+                action_type: name,
+                action_inputs,
+            },
+        });
+    }
+    Ok(predictions)
+}
+
+/// `[x1, y1, x2, y2]`, `[x, y]` or any string the decoder reads, scaled to 0..1 as a four value JSON array
+fn normalize_box(value: &Value, decoder: &dyn CoordinateDecoder) -> Option<String> {
+    let text = match value {
+        Value::Array(items) => {
+            let numbers: Vec<String> = items.iter().map(|v| v.as_f64().map(|n| n.to_string())).collect::<Option<_>>()?;
+            format!("[{}]", numbers.join(","))
+        }
+        Value::String(s) => s.clone(),
+        _ => return None,
+    };
+    serde_json::to_string(&decoder.decode(&text)?).ok()
+}
@@ -0,0 +1,195 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+use openai_api_rs::v1::common::Usage;
+
+/// Qwen2-VL style models merge 2x2 patches of 14px, one token per 28x28 block.
+const IMAGE_TOKEN_PATCH: u32 = 28;
+
+/// Tokens an image costs after the server-side resize, known before sending it.
+pub fn estimate_image_tokens(width: u32, height: u32) -> u64 {
+    (width.div_ceil(IMAGE_TOKEN_PATCH) as u64) * (height.div_ceil(IMAGE_TOKEN_PATCH) as u64)
+}
+
+/// Rough count for text when the server doesn't report usage (cut-off streams).
+pub fn estimate_text_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl From<&Usage> for TokenUsage {
+    fn from(usage: &Usage) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_tokens as u64,
+            completion_tokens: usage.completion_tokens as u64,
+        }
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// Price per million tokens, in whatever currency the caller budgets in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelPricing {
+    pub prompt_per_million: f64,
+    pub completion_per_million: f64,
+}
+
+impl ModelPricing {
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt_per_million + usage.completion_tokens as f64 * self.completion_per_million) / 1_000_000.0
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StepUsage {
+    /// model requests the step made, 0 for a cached response, more for voting or a planner and grounder
+    pub calls: u32,
+    pub tokens: TokenUsage,
+    /// false when the tokens are estimated rather than reported by the server
+    pub reported: bool,
+    pub images: u32,
+    pub pixels: u64,
+    pub estimated_image_tokens: u64,
+    pub latency: Duration,
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunUsage {
+    pub steps: usize,
+    pub calls: u32,
+    pub tokens: TokenUsage,
+    pub images: u32,
+    pub pixels: u64,
+    pub latency: Duration,
+    pub cost: Option<f64>,
+    pub wall_time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Budget {
+    pub max_tokens: Option<u64>,
+    pub max_cost: Option<f64>,
+    pub max_wall_time: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetExceeded {
+    Tokens { used: u64, limit: u64 },
+    Cost { used: f64, limit: f64 },
+    WallTime { elapsed: Duration, limit: Duration },
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BudgetExceeded::Tokens { used, limit } => write!(f, "token budget exceeded: {} of {}", used, limit),
+            BudgetExceeded::Cost { used, limit } => write!(f, "cost budget exceeded: {:.4} of {:.4}", used, limit),
+            BudgetExceeded::WallTime { elapsed, limit } => write!(f, "time budget exceeded: {:?} of {:?}", elapsed, limit),
+        }
+    }
+}
+
+impl Error for BudgetExceeded {}
+
+/// Per step and per run accounting of model calls, with hard budget limits.
+pub struct UsageTracker {
+    pricing: Option<ModelPricing>,
+    budget: Budget,
+    started: Instant,
+    steps: Vec<StepUsage>,
+}
+
+impl UsageTracker {
+    pub fn new(pricing: Option<ModelPricing>, budget: Budget) -> Self {
+        Self {
+            pricing,
+            budget,
+            started: Instant::now(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// A step made of a single model call, see `record_step`.
+    pub fn record(&mut self, usage: Option<TokenUsage>, estimate: TokenUsage, images: &[(u32, u32)], latency: Duration) -> &StepUsage {
+        self.record_step(1, usage, estimate, images, latency)
+    }
+
+    /// One agent step, with the usage of all of its `calls` summed. `images` are the (width, height) actually
+    /// sent, `usage` is `None` when the server didn't report it.
+    pub fn record_step(&mut self, calls: u32, usage: Option<TokenUsage>, estimate: TokenUsage, images: &[(u32, u32)], latency: Duration) -> &StepUsage {
+        let tokens = usage.unwrap_or(estimate);
+        let step = StepUsage {
+            calls,
+            tokens,
+            reported: usage.is_some(),
+            images: images.len() as u32,
+            pixels: images.iter().map(|(w, h)| *w as u64 * *h as u64).sum(),
+            estimated_image_tokens: images.iter().map(|(w, h)| estimate_image_tokens(*w, *h)).sum(),
+            latency,
+            cost: self.pricing.map(|p| p.cost(&tokens)),
+        };
+        log::debug!("model usage: {:?}", step);
+        self.steps.push(step);
+        self.steps.last().unwrap()
+    }
+
+    pub fn steps(&self) -> &[StepUsage] {
+        &self.steps
+    }
+
+    pub fn total(&self) -> RunUsage {
+        let mut total = RunUsage {
+            steps: self.steps.len(),
+            cost: self.pricing.map(|_| 0.0),
+            wall_time: self.started.elapsed(),
+            ..Default::default()
+        };
+        for step in &self.steps {
+            total.calls += step.calls;
+            total.tokens.prompt_tokens += step.tokens.prompt_tokens;
+            total.tokens.completion_tokens += step.tokens.completion_tokens;
+            total.images += step.images;
+            total.pixels += step.pixels;
+            total.latency += step.latency;
+            if let (Some(sum), Some(cost)) = (total.cost.as_mut(), step.cost) {
+                *sum += cost;
+            }
+        }
+        total
+    }
+
+    pub fn check_budget(&self) -> Result<(), BudgetExceeded> {
+        let total = self.total();
+        if let Some(limit) = self.budget.max_tokens {
+            let used = total.tokens.prompt_tokens + total.tokens.completion_tokens;
+            if used >= limit {
+                return Err(BudgetExceeded::Tokens { used, limit });
+            }
+        }
+        if let (Some(limit), Some(used)) = (self.budget.max_cost, total.cost) {
+            if used >= limit {
+                return Err(BudgetExceeded::Cost { used, limit });
+            }
+        }
+        if let Some(limit) = self.budget.max_wall_time {
+            if total.wall_time >= limit {
+                return Err(BudgetExceeded::WallTime { elapsed: total.wall_time, limit });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use openai_api_rs::v1::chat_completion::ChatCompletionRequest;
+
+use crate::action_parser::PredictionParsed;
+use crate::client::{LlmClient, LlmError};
+use crate::usage::TokenUsage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleMode {
+    /// one request asking for `n` choices, topped up sequentially when the server returns fewer
+    Choices,
+    /// `samples` separate requests
+    Sequential,
+}
+
+/// Self-consistency: sample several answers and only act on one most of them agree with.
+#[derive(Debug, Clone)]
+pub struct VotingConfig {
+    pub samples: usize,
+    pub mode: SampleMode,
+    /// sampling settings for the candidates, greedy decoding would give the same answer every time
+    pub temperature: f64,
+    pub top_p: f64,
+    /// max distance between box centers of the same target, in normalised 0..1 coordinates
+    pub radius: f32,
+    /// share of the samples the winning cluster needs
+    pub min_agreement: f32,
+    /// new rounds of samples before giving up and calling the user
+    pub retries: usize,
+}
+
+impl Default for VotingConfig {
+    fn default() -> Self {
+        Self {
+            samples: 5,
+            mode: SampleMode::Choices,
+            temperature: 0.7,
+            top_p: 0.9,
+            radius: 0.03,
+            min_agreement: 0.6,
+            retries: 1,
+        }
+    }
+}
+
+/// Raw completions of one sampling round.
+pub struct Samples {
+    pub texts: Vec<String>,
+    pub usage: TokenUsage,
+    /// requests sent, each one carries the screenshot
+    pub requests: usize,
+}
+
+pub async fn sample_completions(client: &LlmClient, request: &ChatCompletionRequest, config: &VotingConfig) -> Result<Samples, LlmError> {
+    let request = request.clone().temperature(config.temperature).top_p(config.top_p);
+    let mut samples = Samples {
+        texts: Vec::new(),
+        usage: TokenUsage::default(),
+        requests: 0,
+    };
+    if config.mode == SampleMode::Choices && config.samples > 1 {
+        let response = client.chat_completion(&request.clone().n(config.samples as i64)).await?;
+        samples.texts.extend(response.choices.iter().filter_map(|choice| choice.message.content.clone()));
+        samples.usage += TokenUsage::from(&response.usage);
+        samples.requests += 1;
+        if samples.texts.len() < config.samples {
+            log::debug!("server returned {} of {} choices, sampling the rest", samples.texts.len(), config.samples);
+        }
+    }
+    while samples.texts.len() < config.samples {
+        let response = client.chat_completion(&request).await?;
+        samples.texts.push(response.choices.first().and_then(|choice| choice.message.content.clone()).unwrap_or_default());
+        samples.usage += TokenUsage::from(&response.usage);
+        samples.requests += 1;
+    }
+    Ok(samples)
+}
+
+#[derive(Debug, Clone)]
+pub struct Vote {
+    /// the member of the winning cluster closest to the others
+    pub predictions: Vec<PredictionParsed>,
+    /// index of that candidate
+    pub index: usize,
+    pub support: usize,
+    pub total: usize,
+}
+
+impl Vote {
+    pub fn agreement(&self) -> f32 {
+        self.support as f32 / self.total.max(1) as f32
+    }
+}
+
+/// Clusters the candidates by action type, text arguments and box proximity and returns the largest cluster.
+/// Candidates that didn't parse still count towards `total`.
+pub fn vote(candidates: &[Vec<PredictionParsed>], radius: f32) -> Option<Vote> {
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        if candidate.is_empty() || candidate.iter().any(|p| p.action_parsed.action_type.is_empty()) {
+            continue;
+        }
+        match clusters.iter_mut().find(|cluster| same_actions(&candidates[cluster[0]], candidate, radius)) {
+            Some(cluster) => cluster.push(i),
+            None => clusters.push(vec![i]),
+        }
+    }
+    // first cluster wins a tie, the earliest samples are the least random ones
+    let cluster = clusters.iter().fold(None::<&Vec<usize>>, |best, cluster| match best {
+        Some(best) if best.len() >= cluster.len() => Some(best),
+        _ => Some(cluster),
+    })?;
+    let index = *cluster
+        .iter()
+        .min_by(|a, b| {
+            let spread = |i: usize| cluster.iter().map(|&j| distance(&candidates[i], &candidates[j])).sum::<f32>();
+            spread(**a).total_cmp(&spread(**b))
+        })
+        .unwrap_or(&cluster[0]);
+    Some(Vote {
+        predictions: candidates[index].clone(),
+        index,
+        support: cluster.len(),
+        total: candidates.len(),
+    })
+}
+
+fn same_actions(a: &[PredictionParsed], b: &[PredictionParsed], radius: f32) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_action(a, b, radius))
+}
+
+fn same_action(a: &PredictionParsed, b: &PredictionParsed, radius: f32) -> bool {
+    let (a, b) = (&a.action_parsed, &b.action_parsed);
+    if a.action_type != b.action_type || a.action_inputs.len() != b.action_inputs.len() {
+        return false;
+    }
+    a.action_inputs.iter().all(|(name, value)| match (b.action_inputs.get(name), box_center(value)) {
+        (Some(other), Some(center)) => box_center(other).is_some_and(|other| point_distance(center, other) <= radius),
+        (Some(other), None) => value == other,
+        (None, _) => false,
+    })
+}
+
+/// sum of the box center distances of two candidates
+fn distance(a: &[PredictionParsed], b: &[PredictionParsed]) -> f32 {
+    a.iter()
+        .zip(b)
+        .flat_map(|(a, b)| a.action_parsed.action_inputs.iter().filter_map(move |(name, value)| Some(point_distance(box_center(value)?, box_center(b.action_parsed.action_inputs.get(name)?)?))))
+        .sum()
+}
+
+/// center of a `[x1,y1,x2,y2]` box as produced by `parse_action_vlm`
+fn box_center(value: &str) -> Option<(f32, f32)> {
+    let inner = value.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let numbers: Vec<f32> = inner.split(',').map(|s| s.trim().parse().ok()).collect::<Option<_>>()?;
+    match numbers.as_slice() {
+        [x1, y1, x2, y2] => Some(((x1 + x2) / 2.0, (y1 + y2) / 2.0)),
+        [x, y] => Some((*x, *y)),
+        _ => None,
+    }
+}
+
+fn point_distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use small_target_llm::{parse_action_vlm, parse_call, promps::FACTOR, ArgValue};
+
+    /// the inputs of `action_parser_test.rs`, reused as seeds
+    const CASES: &[&str] = &[
+        "Thought: I need to click this button\nAction: click(start_box='(100,200)')",
+        "Reflection: This is a reflection\nAction_Summary: This is a summary\nAction: type(text='Hello', start_box='(300,400)')",
+        "Thought: Perform multiple actions\nAction: click(start_box='(100,200)')\n\ntype(text='Hello', start_box='(300,400)')",
+        r#"click(start_box="(100,200)")"#,
+        "Thought: Empty action\nAction:",
+    ];
+
+    fn quote(content: &str, quote: char) -> String {
+        let mut quoted = String::from(quote);
+        for c in content.chars() {
+            match c {
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                c if c == quote => {
+                    quoted.push('\\');
+                    quoted.push(c);
+                }
+                c => quoted.push(c),
+            }
+        }
+        quoted.push(quote);
+        quoted
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(text in "\\PC*", mode in prop_oneof![Just("bc"), Just("o1")]) {
+            let _ = parse_action_vlm(&text, FACTOR, mode);
+            let _ = parse_call(&text);
+        }
+
+        #[test]
+        fn never_panics_on_call_like_text(text in "[a-z_]{1,8}\\(([a-z_]{1,6}=)?['\"(\\[\\]),\\\\ 0-9a-z]{0,20}\\)?.{0,5}") {
+            let _ = parse_action_vlm(&format!("Action: {}", text), FACTOR, "bc");
+        }
+
+        #[test]
+        fn any_string_content_round_trips(content in "\\PC*", q in prop_oneof![Just('\''), Just('"')]) {
+            let text = format!("Thought: type it\nAction: type(content={})", quote(&content, q));
+            let result = parse_action_vlm(&text, FACTOR, "bc");
+            prop_assert_eq!(result.len(), 1);
+            prop_assert_eq!(&result[0].action_parsed.action_type, "type");
+            prop_assert_eq!(&result[0].action_parsed.action_inputs["content"], &content);
+        }
+
+        #[test]
+        fn whitespace_and_trailing_text_dont_change_seed_results(
+            case in 0..CASES.len(),
+            padding in "[ \t]{0,3}",
+            trailing in "[ \t]{0,3}[a-zA-Z .]{0,12}",
+        ) {
+            let original = parse_action_vlm(CASES[case], FACTOR, "bc");
+            let padded = CASES[case].replace("(start_box", &format!("({}start_box", padding)).replace("')", &format!("'{})", padding));
+            let varied = parse_action_vlm(&format!("{}{}", padded, trailing), FACTOR, "bc");
+            prop_assert_eq!(original, varied);
+        }
+
+        #[test]
+        fn boxes_parse_in_any_bracket_style(x in 0u32..1000, y in 0u32..1000, style in 0usize..4) {
+            let box_text = match style {
+                0 => format!("'({},{})'", x, y),
+                1 => format!("'[{}, {}]'", x, y),
+                2 => format!("({}, {})", x, y),
+                _ => format!("[{},{}]", x, y),
+            };
+            let (call, _) = parse_call(&format!("click(start_box={})", box_text)).unwrap();
+            let value = match &call.args[0].1 {
+                ArgValue::Str(s) => s.clone(),
+                other => other.to_arg_string(),
+            };
+            let result = parse_action_vlm(&format!("Action: click(start_box={})", box_text), FACTOR, "bc");
+            let expected = serde_json::to_string(&[x as f32 / 1000.0, y as f32 / 1000.0, x as f32 / 1000.0, y as f32 / 1000.0]).unwrap();
+            prop_assert!(value.contains(&x.to_string()));
+            prop_assert_eq!(&result[0].action_parsed.action_inputs["start_box"], &expected);
+        }
+    }
+}
@@ -161,6 +161,7 @@ mod tests {
 
     mod edge_cases {
         use small_target_llm::promps::FACTOR;
+        use small_target_schema::{ParamKind, ACTION_SCHEMA};
 
         use super::*;
 
@@ -199,5 +200,62 @@ mod tests {
             }];
             assert_eq!(result, expected);
         }
+
+        fn inputs(result: &[PredictionParsed]) -> &HashMap<String, String> {
+            &result[0].action_parsed.action_inputs
+        }
+
+        #[test]
+        fn should_keep_commas_and_escaped_quotes_in_strings() {
+            let result = parse_action_vlm(r"Action: type(content='Hello, it\'s me')", FACTOR, "bc");
+            assert_eq!(inputs(&result)["content"], "Hello, it's me");
+
+            let result = parse_action_vlm(r#"Action: type(content="C:\Users\n\"x\"")"#, FACTOR, "bc");
+            assert_eq!(inputs(&result)["content"], "C:\\Users\n\"x\"");
+        }
+
+        #[test]
+        fn should_keep_newlines_and_ignore_trailing_text() {
+            let result = parse_action_vlm("Thought: submit\nAction: type(content='line one\nline two\n') and then wait", FACTOR, "bc");
+            assert_eq!(result.len(), 1);
+            assert_eq!(inputs(&result)["content"], "line one\nline two\n");
+        }
+
+        #[test]
+        fn should_accept_positional_and_unquoted_arguments() {
+            let result = parse_action_vlm("Thought: drag\nAction: drag((100, 200), [300,400])", FACTOR, "bc");
+            assert_eq!(inputs(&result)["start_box"], "[0.1,0.2,0.1,0.2]");
+            assert_eq!(inputs(&result)["end_box"], "[0.3,0.4,0.3,0.4]");
+
+            let result = parse_action_vlm("Thought: scroll\nAction: scroll(start_box=(500,500), direction=down, length=3)", FACTOR, "bc");
+            assert_eq!(inputs(&result)["direction"], "down");
+            assert_eq!(inputs(&result)["length"], "3");
+        }
+
+        #[test]
+        fn should_name_positional_arguments_for_every_action_and_alias() {
+            for spec in ACTION_SCHEMA {
+                let values: Vec<&str> = spec
+                    .params
+                    .iter()
+                    .map(|param| match param.kind {
+                        ParamKind::Box => "(100,200)",
+                        ParamKind::Text => "'hello'",
+                        ParamKind::Keys => "'enter'",
+                        ParamKind::Direction => "'down'",
+                        ParamKind::Integer => "3",
+                        ParamKind::Button => "'right'",
+                        ParamKind::ScrollUnit => "'lines'",
+                    })
+                    .collect();
+                let expected: Vec<&str> = spec.params.iter().map(|param| param.name).collect();
+                for name in std::iter::once(&spec.name).chain(spec.aliases) {
+                    let result = parse_action_vlm(&format!("Thought: t\nAction: {}({})", name, values.join(", ")), FACTOR, "bc");
+                    let mut names: Vec<&str> = inputs(&result).keys().map(String::as_str).collect();
+                    names.sort_by_key(|n| expected.iter().position(|e| e == n));
+                    assert_eq!(names, expected, "positional arguments of {}", name);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use openai_api_rs::v1::chat_completion::MessageRole;
+    use small_target_image::{dhash, hamming_distance, image_from_path, image_resize};
+    use small_target_llm::openai_request::{image_message, text_message};
+    use small_target_llm::{digest_messages, CacheConfig, CacheKey, CacheMode, ResponseCache};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("small-target-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn key(screen_hash: u64, history: &str) -> CacheKey {
+        CacheKey {
+            model: "ui-tars".to_string(),
+            prompt_version: "computer@v1".to_string(),
+            screen_hash,
+            history_digest: digest_messages(&[text_message(MessageRole::user, history.to_string())]),
+        }
+    }
+
+    #[test]
+    fn should_keep_hashes_of_the_same_screen_close() {
+        let screen = image_from_path(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/screen_shot_macos.png")).unwrap();
+        let resized = image_resize(screen.clone(), 1350 * 28 * 28).unwrap();
+        assert!(hamming_distance(dhash(&screen), dhash(&resized)) <= 4);
+        assert!(hamming_distance(dhash(&screen), dhash(&screen.fliph())) > 16);
+    }
+
+    #[test]
+    fn should_ignore_images_in_the_history_digest() {
+        let a = vec![text_message(MessageRole::user, "open the menu".to_string()), image_message("data:image/png;base64,AAAA".to_string())];
+        let b = vec![text_message(MessageRole::user, "open the menu".to_string()), image_message("data:image/png;base64,BBBB".to_string())];
+        assert_eq!(digest_messages(&a), digest_messages(&b));
+        assert_ne!(digest_messages(&a), digest_messages(&a[..1]));
+    }
+
+    #[test]
+    fn should_serve_near_screens_and_match_history_exactly() {
+        let dir = temp_dir("rw");
+        let cache = ResponseCache::new(CacheConfig::new(&dir, CacheMode::ReadWrite)).unwrap();
+        cache.put(&key(0xff00, "task"), "Action: click(start_box='(1,2)')").unwrap();
+
+        assert_eq!(cache.get(&key(0xff00, "task")).unwrap().text, "Action: click(start_box='(1,2)')");
+        assert!(cache.get(&key(0xff03, "task")).is_some(), "two bits off is the same screen");
+        assert!(cache.get(&key(0x00ff, "task")).is_none());
+        assert!(cache.get(&key(0xff00, "other task")).is_none());
+
+        let read_only = ResponseCache::new(CacheConfig::new(&dir, CacheMode::ReadOnly)).unwrap();
+        read_only.put(&key(0x1234, "task"), "never written").unwrap();
+        assert!(read_only.get(&key(0x1234, "task")).is_none());
+        assert!(read_only.get(&key(0xff00, "task")).is_some());
+
+        let off = ResponseCache::new(CacheConfig::new(&dir, CacheMode::Off)).unwrap();
+        assert!(off.get(&key(0xff00, "task")).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn should_evict_least_recently_used() {
+        let dir = temp_dir("evict");
+        let config = CacheConfig {
+            max_entries: 2,
+            ..CacheConfig::new(&dir, CacheMode::ReadWrite)
+        };
+        let cache = ResponseCache::new(config).unwrap();
+        cache.put(&key(1, "a"), "first").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.put(&key(1, "b"), "second").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        // reading refreshes "first", so "second" is the oldest now
+        assert!(cache.get(&key(1, "a")).is_some());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.put(&key(1, "c"), "third").unwrap();
+
+        assert!(cache.get(&key(1, "a")).is_some());
+        assert!(cache.get(&key(1, "b")).is_none());
+        assert!(cache.get(&key(1, "c")).is_some());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
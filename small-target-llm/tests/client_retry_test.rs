@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, ChatCompletionRequest, Content, MessageRole};
+    use small_target_llm::{ClientConfig, LlmClient, LlmError};
+
+    use small_target_mock::{MockResponse, MockScript, MockVlmServer};
+
+    fn request() -> ChatCompletionRequest {
+        ChatCompletionRequest::new(
+            "stub".to_string(),
+            vec![ChatCompletionMessage {
+                role: MessageRole::user,
+                content: Content::Text("hello".to_string()),
+                name: None,
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+        )
+    }
+
+    fn config(server: &MockVlmServer) -> ClientConfig {
+        let mut config = ClientConfig::new(&server.base_url(), "test-key");
+        config.initial_backoff = Duration::from_millis(10);
+        config.max_backoff = Duration::from_millis(50);
+        config
+    }
+
+    #[tokio::test]
+    async fn should_retry_retryable_statuses() {
+        let script = MockScript::new()
+            .then(MockResponse::error(503, "overloaded"))
+            .then(MockResponse::error(429, "slow down"))
+            .then(MockResponse::text("Action: wait()"));
+        let server = MockVlmServer::start(script).await.unwrap();
+        let client = LlmClient::new(config(&server)).unwrap();
+
+        let response = client.chat_completion(&request()).await.unwrap();
+        assert_eq!(response.choices[0].message.content.as_deref(), Some("Action: wait()"));
+        assert_eq!(server.request_count(), 3);
+        assert_eq!(server.requests()[0].texts, ["hello"]);
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_client_errors() {
+        let script = MockScript::new().then(MockResponse::error(400, "bad request")).then(MockResponse::text("unreachable"));
+        let server = MockVlmServer::start(script).await.unwrap();
+        let client = LlmClient::new(config(&server)).unwrap();
+
+        match client.chat_completion(&request()).await {
+            Err(LlmError::Client { status, body }) => {
+                assert_eq!(status, 400);
+                assert!(body.contains("bad request"));
+            }
+            other => panic!("unexpected result {:?}", other.map(|r| r.choices.len())),
+        }
+        assert_eq!(server.request_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_give_up_after_max_retries() {
+        let server = MockVlmServer::start(MockScript::new().then(MockResponse::error(502, "bad gateway"))).await.unwrap();
+        let mut config = config(&server);
+        config.max_retries = 2;
+        let client = LlmClient::new(config).unwrap();
+
+        assert!(matches!(client.chat_completion(&request()).await, Err(LlmError::Server { status: 502, .. })));
+        assert_eq!(server.request_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn should_honor_retry_after() {
+        let script = MockScript::new()
+            .then(MockResponse::error(429, "slow down").with_header("retry-after", "1"))
+            .then(MockResponse::text("ok"));
+        let server = MockVlmServer::start(script).await.unwrap();
+        let mut config = config(&server);
+        config.max_backoff = Duration::from_secs(2);
+        let client = LlmClient::new(config).unwrap();
+
+        let start = Instant::now();
+        client.chat_completion(&request()).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn should_cap_retry_after_at_max_backoff() {
+        let script = MockScript::new()
+            .then(MockResponse::error(429, "slow down").with_header("retry-after", "3600"))
+            .then(MockResponse::text("ok"));
+        let server = MockVlmServer::start(script).await.unwrap();
+        let client = LlmClient::new(config(&server)).unwrap();
+
+        let start = Instant::now();
+        client.chat_completion(&request()).await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(server.request_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_time_out() {
+        let script = MockScript::new().then(MockResponse::text("late").with_delay(Duration::from_secs(5)));
+        let server = MockVlmServer::start(script).await.unwrap();
+        let mut config = config(&server);
+        config.timeout = Duration::from_millis(200);
+        config.max_retries = 0;
+        let client = LlmClient::new(config).unwrap();
+
+        assert!(matches!(client.chat_completion(&request()).await, Err(LlmError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn should_limit_concurrency() {
+        let script = MockScript::new().then(MockResponse::text("ok").with_delay(Duration::from_millis(100)));
+        let server = MockVlmServer::start(script).await.unwrap();
+        let mut config = config(&server);
+        config.max_concurrency = 1;
+        let client = LlmClient::new(config).unwrap();
+
+        let request = request();
+        let (a, b, c) = tokio::join!(client.chat_completion(&request), client.chat_completion(&request), client.chat_completion(&request));
+        assert!(a.is_ok() && b.is_ok() && c.is_ok());
+        assert_eq!(server.max_in_flight(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_space_requests_with_min_interval() {
+        let server = MockVlmServer::start(MockScript::new().then(MockResponse::text("ok"))).await.unwrap();
+        let mut config = config(&server);
+        config.min_interval = Duration::from_millis(150);
+        let client = LlmClient::new(config).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            client.chat_completion(&request()).await.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+}
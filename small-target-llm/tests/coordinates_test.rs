@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use small_target_llm::coordinates::{BoxTokenDecoder, DecoderChain, PlainDecoder, PointTagDecoder};
+    use small_target_llm::{parse_action_vlm, parse_action_vlm_with, CoordinateDecoder, CoordinateScale, ModelProfile};
+
+    const RELATIVE: CoordinateScale = CoordinateScale::Relative(1000.0, 1000.0);
+
+    fn start_box(text: &str, decoder: &dyn CoordinateDecoder) -> String {
+        parse_action_vlm_with(text, decoder, "bc")[0].action_parsed.action_inputs["start_box"].clone()
+    }
+
+    #[test]
+    fn should_decode_plain_numbers() {
+        let decoder = PlainDecoder::relative((1000.0, 1000.0));
+        assert_eq!(decoder.decode("(100,200)"), Some([0.1, 0.2, 0.1, 0.2]));
+        assert_eq!(decoder.decode("[100, 200, 300, 400]"), Some([0.1, 0.2, 0.3, 0.4]));
+        assert_eq!(decoder.decode("<|box_start|>(100,200)<|box_end|>"), None);
+        assert_eq!(decoder.decode("(100)"), None);
+    }
+
+    #[test]
+    fn should_decode_box_tokens() {
+        let decoder = BoxTokenDecoder { scale: RELATIVE };
+        assert_eq!(decoder.decode("<|box_start|>(100,200)<|box_end|>"), Some([0.1, 0.2, 0.1, 0.2]));
+        assert_eq!(decoder.decode("<|box_start|>(100,200),(300,400)<|box_end|>"), Some([0.1, 0.2, 0.3, 0.4]));
+        assert_eq!(decoder.decode("(100,200)"), None);
+
+        let text = "Thought: open it\nAction: click(start_box='<|box_start|>(100,200)<|box_end|>')";
+        assert_eq!(start_box(text, &decoder), "[0.1,0.2,0.1,0.2]");
+        // the legacy entry point understands the tokens too
+        assert_eq!(parse_action_vlm(text, (1000.0, 1000.0), "bc")[0].action_parsed.action_inputs["start_box"], "[0.1,0.2,0.1,0.2]");
+    }
+
+    #[test]
+    fn should_decode_point_tags() {
+        let decoder = PointTagDecoder { scale: RELATIVE };
+        assert_eq!(decoder.decode("<point>100 200</point>"), Some([0.1, 0.2, 0.1, 0.2]));
+        assert_eq!(decoder.decode("<point>(100,200)</point>"), Some([0.1, 0.2, 0.1, 0.2]));
+        assert_eq!(decoder.decode("<bbox>100 200 300 400</bbox>"), Some([0.1, 0.2, 0.3, 0.4]));
+        assert_eq!(decoder.decode("<point>100</point>"), None);
+    }
+
+    #[test]
+    fn should_decode_absolute_pixels_of_the_resized_image() {
+        let decoder = PlainDecoder::absolute((1280, 720));
+        assert_eq!(decoder.decode("(640,360)"), Some([0.5, 0.5, 0.5, 0.5]));
+
+        let profile = ModelProfile::for_model("ByteDance-Seed/UI-TARS-1.5-7B");
+        assert_eq!(profile, ModelProfile::ui_tars_1_5());
+        let text = "Thought: open it\nAction: click(start_box='<|box_start|>(320,180)<|box_end|>')";
+        assert_eq!(start_box(text, &profile.decoder((1280, 720))), "[0.25,0.25,0.25,0.25]");
+
+        let qwen = ModelProfile::for_model("Qwen/Qwen2.5-VL-72B-Instruct");
+        assert_eq!(start_box("Action: click(start_box='<point>640 180</point>')", &qwen.decoder((1280, 720))), "[0.5,0.25,0.5,0.25]");
+        assert_eq!(qwen.encode_point((0.5, 0.25), (1280, 720)), (640, 180));
+        assert_eq!(ModelProfile::ui_tars().encode_point((0.5, 0.25), (1280, 720)), (500, 250));
+    }
+
+    #[test]
+    fn should_fall_back_to_ui_tars_and_chain_formats() {
+        assert_eq!(ModelProfile::for_model("ui-tars-7b-sft"), ModelProfile::ui_tars());
+        let chain = DecoderChain::all_formats(RELATIVE);
+        for raw in ["(500,500)", "<|box_start|>(500,500)<|box_end|>", "<point>500 500</point>"] {
+            assert_eq!(chain.decode(raw), Some([0.5, 0.5, 0.5, 0.5]), "{}", raw);
+        }
+        assert_eq!(start_box("Action: click(start_box='somewhere')", &chain), "[]");
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use small_target_llm::pipeline::parse_plan;
+    use small_target_llm::coordinates::CoordinateSpace;
+    use small_target_llm::{Grounder, PlannedStep, Planner, ProviderConfig};
+
+    use small_target_mock::{MockResponse, MockScript, MockVlmServer};
+
+    #[test]
+    fn should_parse_plans() {
+        assert_eq!(
+            parse_plan("Thought: The file is open.\nStep: click the Save button"),
+            PlannedStep::Act {
+                thought: "The file is open.".to_string(),
+                step: "click the Save button".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_plan("Thought: Saved.\nStep: `finished()`"),
+            PlannedStep::Finished {
+                thought: "Saved.".to_string()
+            }
+        );
+        assert_eq!(parse_plan("Step: call_user"), PlannedStep::CallUser { thought: String::new() });
+        // no `Step:` at all, the whole reply is the step
+        assert_eq!(
+            parse_plan("press control+s"),
+            PlannedStep::Act {
+                thought: String::new(),
+                step: "press control+s".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn should_plan_then_ground() {
+        let script = MockScript::new()
+            .then(MockResponse::text("Thought: The editor is focused.\nStep: click the Save button"))
+            .then(MockResponse::text("Thought: Save button\nAction: click(start_box='<|box_start|>(100,50)<|box_end|>')"))
+            .then(MockResponse::text("Thought: Saved.\nStep: finished"));
+        let server = MockVlmServer::start(script).await.unwrap();
+
+        let mut planner = Planner::new(ProviderConfig::new(&server.base_url(), "planner", "key")).unwrap();
+        let mut grounder_config = ProviderConfig::new(&server.base_url(), "ui-tars-1.5-7b", "key");
+        assert_eq!(grounder_config.profile.space, CoordinateSpace::Absolute);
+        grounder_config.max_tokens = 200;
+        let grounder = Grounder::new(grounder_config).unwrap();
+
+        let planned = planner.plan("Plan: ", "save the file", None).await.unwrap();
+        let PlannedStep::Act { step, .. } = planned.step else {
+            panic!("expected a step, got {:?}", planned.step);
+        };
+        assert_eq!(step, "click the Save button");
+
+        let grounded = grounder.ground("Ground: ", &step, "data:image/png;base64,".to_string(), (200, 100)).await.unwrap();
+        assert_eq!(grounded.predictions.len(), 1);
+        let action = &grounded.predictions[0].action_parsed;
+        assert_eq!(action.action_type, "click");
+        assert_eq!(action.action_inputs["start_box"], "[0.5,0.5,0.5,0.5]");
+
+        planner.note("The Save button was clicked.");
+        let planned = planner.plan("Plan: ", "save the file", None).await.unwrap();
+        assert!(matches!(planned.step, PlannedStep::Finished { .. }));
+        assert_eq!(server.request_count(), 3);
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use small_target_llm::promps::GROUNDING_ACTIONS;
+    use small_target_llm::{get_system_prompt, PromptRegistry, PromptVars};
+    use small_target_schema::ACTION_SCHEMA;
+
+    /// Compares with `tests/snapshots/<name>.txt`, `UPDATE_SNAPSHOTS=1` rewrites the file.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("snapshots").join(format!("{}.txt", name));
+        if std::env::var("UPDATE_SNAPSHOTS").is_ok() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| panic!("missing snapshot {:?}, run with UPDATE_SNAPSHOTS=1", path));
+        assert_eq!(actual, expected, "prompt {} changed, run with UPDATE_SNAPSHOTS=1 and review the diff", name);
+    }
+
+    fn vars(language: &str, platform: &str) -> PromptVars {
+        let mut vars = PromptVars::new(language);
+        vars.platform = platform.to_string();
+        vars
+    }
+
+    #[test]
+    fn should_render_builtin_templates() {
+        let registry = PromptRegistry::builtin();
+        assert_eq!(registry.names(), vec!["browser", "computer", "grounding", "mobile", "planner"]);
+
+        let computer = registry.render("computer", &vars("zh", "macOS")).unwrap();
+        assert!(computer.version_id.starts_with("computer@v3#"), "{}", computer.version_id);
+        // the same template and action space in another language keep their id
+        assert_eq!(registry.render("computer", &vars("en", "Linux")).unwrap().version_id, computer.version_id);
+        let mut fewer_actions = vars("zh", "macOS");
+        fewer_actions.action_space = Some("click(start_box='[x1, y1, x2, y2]')".to_string());
+        assert_ne!(registry.render("computer", &fewer_actions).unwrap().version_id, computer.version_id);
+        assert!(computer.text.lines().all(|line| !line.starts_with(' ')), "indentation leaked into the prompt");
+        assert_snapshot("computer_zh_macos", &computer.text);
+
+        let mut browser = vars("en", "Windows");
+        browser.extra_notes = vec!["Never submit payment forms.".to_string()];
+        assert_snapshot("browser_en_windows", &registry.render("browser", &browser).unwrap().text);
+        assert_snapshot("mobile_ja_android", &registry.render("mobile", &vars("ja", "Android")).unwrap().text);
+
+        let mut grounding = vars("en", "Linux");
+        grounding.action_space = Some("click(start_box='[x1, y1, x2, y2]')".to_string());
+        assert_snapshot("grounding_click_only", &registry.render("grounding", &grounding).unwrap().text);
+        assert_snapshot("planner_en_linux", &registry.render("planner", &vars("en", "Linux")).unwrap().text);
+    }
+
+    #[test]
+    fn should_document_the_schema_actions_by_default() {
+        let legacy = get_system_prompt("en");
+        for spec in ACTION_SCHEMA {
+            assert!(legacy.contains(&spec.prompt_line()), "{} missing from the default prompt", spec.name);
+        }
+        let grounding = PromptRegistry::builtin().render("grounding", &vars("en", "Linux")).unwrap().text;
+        for spec in ACTION_SCHEMA {
+            assert_eq!(grounding.contains(&spec.signature()), GROUNDING_ACTIONS.contains(&spec.name), "{} in the grounding prompt", spec.name);
+        }
+    }
+
+    #[test]
+    fn should_load_templates_from_files() {
+        let dir = std::env::temp_dir().join(format!("small-target-prompts-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("computer.md"), "<!-- version: team-3 -->\nAct on {{platform}} in {{language}}.\n{{action_space}}\n").unwrap();
+        fs::write(dir.join("custom.txt"), "Only {{language}}.\n{{extra_notes}}\n").unwrap();
+        fs::write(dir.join("broken.md"), "Uses {{unknown}}.\n").unwrap();
+
+        let mut registry = PromptRegistry::builtin();
+        assert_eq!(registry.load_dir(&dir).unwrap(), vec!["broken", "computer", "custom"]);
+
+        // replaced template keeps the built-in default action space
+        let computer = registry.render("computer", &vars("Portuguese", "Linux")).unwrap();
+        assert!(computer.version_id.starts_with("computer@team-3#"));
+        assert!(computer.text.starts_with("Act on Linux in Portuguese.\nclick(start_box="));
+
+        let custom = registry.render("custom", &vars("zh", "Linux")).unwrap();
+        assert_eq!(custom.text, "Only Chinese.\n");
+        assert_eq!(custom.version_id.len(), "custom@#".len() + 16);
+
+        assert!(registry.render("broken", &vars("en", "Linux")).is_err());
+        assert!(registry.render("missing", &vars("en", "Linux")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, ChatCompletionRequest, Content, MessageRole};
+    use small_target_llm::{promps::FACTOR, stream_prediction, ClientConfig, LlmClient, StreamEvent, StreamingActionParser};
+    use tokio::sync::broadcast;
+
+    use small_target_mock::{MockResponse, MockScript, MockVlmServer};
+
+    fn request() -> ChatCompletionRequest {
+        ChatCompletionRequest::new(
+            "stub".to_string(),
+            vec![ChatCompletionMessage {
+                role: MessageRole::user,
+                content: Content::Text("open the menu".to_string()),
+                name: None,
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+        )
+    }
+
+    #[test]
+    fn should_emit_thought_and_actions_incrementally() {
+        let mut parser = StreamingActionParser::new(FACTOR, "bc");
+        let mut thought = String::new();
+        for delta in ["Thought: I need", " to click the bu", "tton\nAct", "ion: click(start_box='(1", "00,200)'", ")", "\n"] {
+            let (thought_delta, ready) = parser.push(delta);
+            thought.push_str(&thought_delta.unwrap_or_default());
+            // another call may follow the click
+            assert!(ready.is_none());
+        }
+        assert_eq!(thought, "I need to click the button");
+        assert!(parser.call_line_ended());
+        let actions = parser.finish().unwrap();
+        assert!(parser.finish().is_none(), "actions emitted twice");
+        assert_eq!(actions[0].action_parsed.action_type, "click");
+        assert_eq!(actions[0].action_parsed.action_inputs, HashMap::from([("start_box".to_string(), "[0.1,0.2,0.1,0.2]".to_string())]));
+    }
+
+    #[test]
+    fn should_wait_for_closing_paren_outside_quotes() {
+        let mut parser = StreamingActionParser::new(FACTOR, "bc");
+        assert!(parser.push("Thought: done\nAction: finished(content='a) b").1.is_none());
+        assert!(parser.push("')").1.is_some());
+    }
+
+    #[test]
+    fn should_complete_when_text_other_than_a_call_follows() {
+        let mut parser = StreamingActionParser::new(FACTOR, "bc");
+        assert!(parser.push("Thought: t\nAction: click(start_box='(1,2)')\n\nty").1.is_none());
+        assert!(!parser.call_line_ended());
+        assert!(parser.push("pe(content='a')\n").1.is_none());
+        let actions = parser.push("<|im_end|>").1.unwrap();
+        let types: Vec<_> = actions.iter().map(|p| p.action_parsed.action_type.as_str()).collect();
+        assert_eq!(types, ["click", "type"]);
+    }
+
+    #[tokio::test]
+    async fn should_return_before_stream_ends() {
+        let reply = MockResponse::stream(&[
+            (Duration::ZERO, "Thought: The menu"),
+            (Duration::from_millis(20), " is at the top"),
+            (Duration::from_millis(20), "\nAction: finished(content='opened')"),
+            // a slow tail the agent should not wait for
+            (Duration::from_secs(3), "\n"),
+        ]);
+        let server = MockVlmServer::start(MockScript::new().then(reply)).await.unwrap();
+        let client = LlmClient::new(ClientConfig::new(&server.base_url(), "test-key")).unwrap();
+        let (events, mut receiver) = broadcast::channel(64);
+
+        let start = Instant::now();
+        let result = stream_prediction(&client, &request(), FACTOR, "bc", &events).await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(result.predictions[0].action_parsed.action_type, "finished");
+        assert_eq!(result.predictions[0].thought, "The menu is at the top");
+        assert!(server.requests()[0].stream);
+
+        let mut thought = String::new();
+        let mut ready = false;
+        while let Ok(event) = receiver.try_recv() {
+            match event {
+                StreamEvent::Thought(text) => thought.push_str(&text),
+                StreamEvent::ActionsReady(actions) => ready = actions.len() == 1,
+                StreamEvent::Done(text) => assert!(text.ends_with("opened')")),
+            }
+        }
+        assert_eq!(thought, "The menu is at the top");
+        assert!(ready);
+    }
+
+    #[tokio::test]
+    async fn should_keep_every_action_of_a_multi_action_response() {
+        let reply = MockResponse::stream(&[
+            (Duration::ZERO, "Thought: Fill the field\nAction: click(start_box='(500,20)')"),
+            (Duration::from_millis(50), "\n\ntype(content='hello')"),
+        ]);
+        let server = MockVlmServer::start(MockScript::new().then(reply)).await.unwrap();
+        let client = LlmClient::new(ClientConfig::new(&server.base_url(), "test-key")).unwrap();
+        let (events, _receiver) = broadcast::channel(64);
+
+        let result = stream_prediction(&client, &request(), FACTOR, "bc", &events).await.unwrap();
+        let types: Vec<_> = result.predictions.iter().map(|p| p.action_parsed.action_type.as_str()).collect();
+        assert_eq!(types, ["click", "type"]);
+        assert!(result.text.ends_with("hello')"));
+    }
+
+    #[tokio::test]
+    async fn should_return_shortly_after_a_call_line_ends() {
+        let reply = MockResponse::stream(&[
+            (Duration::ZERO, "Thought: The menu button\nAction: click(start_box='(100,200)')\n"),
+            // the model winding down, the agent should not wait for it
+            (Duration::from_secs(3), "<|im_end|>"),
+        ]);
+        let server = MockVlmServer::start(MockScript::new().then(reply)).await.unwrap();
+        let client = LlmClient::new(ClientConfig::new(&server.base_url(), "test-key")).unwrap();
+        let (events, _receiver) = broadcast::channel(64);
+
+        let start = Instant::now();
+        let result = stream_prediction(&client, &request(), FACTOR, "bc", &events).await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(result.predictions[0].action_parsed.action_type, "click");
+    }
+
+    #[tokio::test]
+    async fn should_decode_characters_split_across_chunks() {
+        let reply = MockResponse::stream(&[(Duration::ZERO, "Thought: 点击微信图标"), (Duration::ZERO, "\nAction: type(content='你好')")]).with_split(5);
+        let server = MockVlmServer::start(MockScript::new().then(reply)).await.unwrap();
+        let client = LlmClient::new(ClientConfig::new(&server.base_url(), "test-key")).unwrap();
+        let (events, _receiver) = broadcast::channel(64);
+
+        let result = stream_prediction(&client, &request(), FACTOR, "bc", &events).await.unwrap();
+        assert!(!result.text.contains('\u{FFFD}'));
+        assert_eq!(result.predictions[0].thought, "点击微信图标");
+        assert_eq!(result.predictions[0].action_parsed.action_inputs["content"], "你好");
+    }
+}
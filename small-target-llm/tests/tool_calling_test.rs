@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+    use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, ChatCompletionRequest, Content, MessageRole};
+    use serde_json::json;
+    use small_target_llm::{parse_tool_calls, ClientConfig, LlmClient, ModelProfile, ToolSpec};
+
+    use small_target_mock::{MockResponse, MockScript, MockVlmServer};
+
+    fn click_tool() -> ToolSpec {
+        ToolSpec {
+            name: "click".to_string(),
+            description: "Left click the element.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": { "start_box": { "type": "array", "items": { "type": "number" } } },
+                "required": ["start_box"],
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_send_tools_and_parse_tool_calls() {
+        let reply = MockResponse::tool_calls_with_content(
+            "The search box is at the top.",
+            &[("click", r#"{"start_box": [100, 200, 300, 400]}"#), ("type", r#"{"content": "rust\n"}"#), ("scroll", r#"{"start_box": "(500,500)", "direction": "down", "length": 3}"#)],
+        );
+        let server = MockVlmServer::start(MockScript::new().then(reply)).await.unwrap();
+        let client = LlmClient::new(ClientConfig::new(&server.base_url(), "key")).unwrap();
+        let request = ChatCompletionRequest::new(
+            "stub".to_string(),
+            vec![ChatCompletionMessage {
+                role: MessageRole::user,
+                content: Content::Text("search for rust".to_string()),
+                name: None,
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+        );
+
+        let response = client.chat_completion_with_tools(&request, &[click_tool()]).await.unwrap();
+        let sent = &server.requests()[0].body;
+        assert_eq!(sent["tools"][0]["function"]["name"], "click");
+        assert_eq!(sent["tools"][0]["function"]["parameters"]["required"][0], "start_box");
+
+        let predictions = parse_tool_calls(&response.choices[0].message, &ModelProfile::ui_tars().decoder((1000, 1000))).unwrap();
+        assert_eq!(predictions.len(), 3);
+        assert_eq!(predictions[0].thought, "The search box is at the top.");
+        assert_eq!(predictions[0].action_parsed.action_type, "click");
+        assert_eq!(predictions[0].action_parsed.action_inputs["start_box"], "[0.1,0.2,0.3,0.4]");
+        assert_eq!(predictions[1].action_parsed.action_inputs["content"], "rust\n");
+        // a point and a non-string argument
+        assert_eq!(predictions[2].action_parsed.action_inputs["start_box"], "[0.5,0.5,0.5,0.5]");
+        assert_eq!(predictions[2].action_parsed.action_inputs["length"], "3");
+    }
+
+    #[tokio::test]
+    async fn should_reject_malformed_arguments() {
+        let script = MockScript::new().then(MockResponse::tool_calls(&[("click", "{\"start_box\": [1]}")]));
+        let server = MockVlmServer::start(script).await.unwrap();
+        let client = LlmClient::new(ClientConfig::new(&server.base_url(), "key")).unwrap();
+        let request = ChatCompletionRequest::new("stub".to_string(), vec![]);
+        let response = client.chat_completion_with_tools(&request, &[click_tool()]).await.unwrap();
+        assert!(parse_tool_calls(&response.choices[0].message, &ModelProfile::ui_tars().decoder((1000, 1000))).is_err());
+    }
+}
@@ -4,6 +4,8 @@ mod tests {
     use small_target_llm::{get_system_prompt, openai_request, parse_action_vlm, promps::FACTOR, OpenAiProtocalCallPayload, MAX_PIXELS};
     use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, Content, ContentType, ImageUrl, ImageUrlType, MessageRole};
     use small_target_image::{image_from_path, image_resize, image_to_base64};
+    use small_target_llm::openai_request::{image_message, text_message};
+    use small_target_mock::{MockResponse, MockScript, MockVlmServer};
     use std::{fs::create_dir_all, path::PathBuf};
 
     #[ignore]
@@ -86,4 +88,26 @@ mod tests {
         }
         Ok(())
     }
+
+    /// same call against the in-process mock instead of a live server
+    #[tokio::test]
+    async fn test_call_mock_model() -> Result<()> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("screen_shot_macos.png");
+        let resized_img = image_resize(image_from_path(path.to_str().unwrap())?, MAX_PIXELS)?;
+        let size = (resized_img.width(), resized_img.height());
+        let script = MockScript::new().then(MockResponse::text("Thought: 微信图标在程序坞里。\nAction: click(start_box='(235,966)')"));
+        let server = MockVlmServer::start(script).await?;
+
+        let messages = vec![text_message(MessageRole::user, format!("{}{}", get_system_prompt("zh"), "打开微信app")), image_message(image_to_base64(resized_img)?)];
+        let payload = OpenAiProtocalCallPayload::new(server.base_url(), "ui-tars".to_string(), "api_token".to_string(), messages, vec![], 0.0, 0.7, 1000);
+        let result = openai_request(payload).await?;
+        let predictions = parse_action_vlm(result.choices[0].message.content.as_ref().unwrap(), FACTOR, "bc");
+        assert_eq!(predictions[0].action_parsed.action_type, "click");
+        assert_eq!(predictions[0].action_parsed.action_inputs["start_box"], "[0.235,0.966,0.235,0.966]");
+
+        let request = &server.requests()[0];
+        assert!(request.texts[0].ends_with("打开微信app"));
+        assert_eq!((request.images[0].width(), request.images[0].height()), size);
+        Ok(())
+    }
 }
@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use openai_api_rs::v1::chat_completion::{ChatCompletionMessage, ChatCompletionRequest, Content, MessageRole};
+    use small_target_llm::usage::estimate_image_tokens;
+    use small_target_llm::{Budget, BudgetExceeded, ClientConfig, LlmClient, ModelPricing, TokenUsage, UsageTracker};
+
+    use small_target_mock::{MockResponse, MockScript, MockVlmServer};
+
+    fn tokens(prompt_tokens: u64, completion_tokens: u64) -> TokenUsage {
+        TokenUsage { prompt_tokens, completion_tokens }
+    }
+
+    #[test]
+    fn should_estimate_image_tokens_in_28px_patches() {
+        assert_eq!(estimate_image_tokens(28, 28), 1);
+        assert_eq!(estimate_image_tokens(29, 28), 2);
+        assert_eq!(estimate_image_tokens(1120, 700), 40 * 25);
+    }
+
+    #[test]
+    fn should_sum_steps_and_cost() {
+        let pricing = ModelPricing {
+            prompt_per_million: 1.0,
+            completion_per_million: 4.0,
+        };
+        let mut tracker = UsageTracker::new(Some(pricing), Budget::default());
+        tracker.record(Some(tokens(1000, 100)), tokens(0, 0), &[(1120, 700)], Duration::from_millis(300));
+        // nothing reported, the estimate is used
+        let step = tracker.record(None, tokens(2000, 50), &[(1120, 700)], Duration::from_millis(200)).clone();
+        assert!(!step.reported);
+        assert_eq!(step.tokens, tokens(2000, 50));
+
+        let total = tracker.total();
+        assert_eq!(total.steps, 2);
+        assert_eq!(total.calls, 2);
+        assert_eq!(total.tokens, tokens(3000, 150));
+        assert_eq!(total.images, 2);
+        assert_eq!(total.pixels, 2 * 1120 * 700);
+        assert_eq!(total.latency, Duration::from_millis(500));
+        assert!((total.cost.unwrap() - 0.0036).abs() < 1e-9);
+        assert!(tracker.check_budget().is_ok());
+    }
+
+    #[test]
+    fn should_count_calls_of_a_step_once_as_a_step() {
+        let mut tracker = UsageTracker::new(None, Budget::default());
+        // a planner without vision and a grounder
+        tracker.record_step(2, Some(tokens(1500, 80)), tokens(0, 0), &[(1120, 700)], Duration::from_millis(400));
+        // a cached response
+        tracker.record_step(0, Some(tokens(0, 0)), tokens(0, 0), &[], Duration::ZERO);
+
+        let total = tracker.total();
+        assert_eq!(total.steps, 2);
+        assert_eq!(total.calls, 2);
+        assert_eq!(total.images, 1);
+        assert_eq!(tracker.steps()[0].tokens, tokens(1500, 80));
+    }
+
+    #[test]
+    fn should_stop_at_budget_limits() {
+        let budget = Budget {
+            max_tokens: Some(1000),
+            ..Default::default()
+        };
+        let mut tracker = UsageTracker::new(None, budget);
+        tracker.record(Some(tokens(900, 99)), tokens(0, 0), &[], Duration::ZERO);
+        assert!(tracker.check_budget().is_ok());
+        tracker.record(Some(tokens(1, 0)), tokens(0, 0), &[], Duration::ZERO);
+        assert_eq!(tracker.check_budget(), Err(BudgetExceeded::Tokens { used: 1000, limit: 1000 }));
+
+        let budget = Budget {
+            max_cost: Some(0.01),
+            ..Default::default()
+        };
+        let pricing = ModelPricing {
+            prompt_per_million: 10.0,
+            completion_per_million: 10.0,
+        };
+        let mut tracker = UsageTracker::new(Some(pricing), budget);
+        tracker.record(Some(tokens(1000, 0)), tokens(0, 0), &[], Duration::ZERO);
+        assert!(matches!(tracker.check_budget(), Err(BudgetExceeded::Cost { .. })));
+
+        let budget = Budget {
+            max_wall_time: Some(Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let tracker = UsageTracker::new(None, budget);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(matches!(tracker.check_budget(), Err(BudgetExceeded::WallTime { .. })));
+    }
+
+    #[tokio::test]
+    async fn should_read_reported_usage_from_response() {
+        let server = MockVlmServer::start(MockScript::new().then(MockResponse::text("Thought: done\nAction: finished()"))).await.unwrap();
+        let client = LlmClient::new(ClientConfig::new(&server.base_url(), "key")).unwrap();
+        let request = ChatCompletionRequest::new(
+            "stub".to_string(),
+            vec![ChatCompletionMessage {
+                role: MessageRole::user,
+                content: Content::Text("hi".to_string()),
+                name: None,
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+        );
+        let response = client.chat_completion(&request).await.unwrap();
+        assert_eq!(TokenUsage::from(&response.usage), tokens(10, 5));
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use small_target_llm::{parse_action_vlm, sample_completions, vote, ClientConfig, LlmClient, PredictionParsed, SampleMode, VotingConfig};
+    use small_target_llm::openai_request::text_message;
+    use openai_api_rs::v1::chat_completion::{ChatCompletionRequest, MessageRole};
+
+    use small_target_mock::{MockResponse, MockScript, MockVlmServer};
+
+    fn candidate(action: &str) -> Vec<PredictionParsed> {
+        parse_action_vlm(&format!("Thought: t\nAction: {}", action), (1000.0, 1000.0), "bc")
+    }
+
+    #[test]
+    fn should_pick_the_largest_cluster() {
+        let candidates = vec![
+            candidate("click(start_box='(500,500)')"),
+            candidate("click(start_box='(900,100)')"),
+            candidate("click(start_box='(510,495)')"),
+            candidate("right_single(start_box='(505,500)')"),
+            candidate("click(start_box='(505,502)')"),
+        ];
+        let winner = vote(&candidates, 0.03).unwrap();
+        assert_eq!((winner.support, winner.total), (3, 5));
+        // the member in the middle of the cluster
+        assert_eq!(winner.index, 4);
+        assert_eq!(winner.predictions[0].action_parsed.action_inputs["start_box"], "[0.505,0.502,0.505,0.502]");
+    }
+
+    #[test]
+    fn should_compare_text_arguments_exactly() {
+        let candidates = vec![candidate("type(content='hello')"), candidate("type(content='hello\\n')"), candidate("type(content='hello')")];
+        let winner = vote(&candidates, 0.03).unwrap();
+        assert_eq!((winner.index, winner.support), (0, 2));
+    }
+
+    #[test]
+    fn should_report_disagreement() {
+        let candidates = vec![candidate("click(start_box='(100,100)')"), candidate("click(start_box='(800,800)')"), vec![], candidate("scroll(start_box='(100,100)', direction='down')")];
+        let winner = vote(&candidates, 0.03).unwrap();
+        assert_eq!(winner.support, 1);
+        assert!(winner.agreement() < 0.6);
+        assert!(vote(&[vec![], vec![]], 0.03).is_none());
+    }
+
+    #[tokio::test]
+    async fn should_top_up_missing_choices() {
+        let script = MockScript::new()
+            .then(MockResponse::text("Thought: a\nAction: click(start_box='(500,500)')"))
+            .then(MockResponse::text("Thought: b\nAction: click(start_box='(501,500)')"))
+            .then(MockResponse::text("Thought: c\nAction: click(start_box='(100,100)')"));
+        let server = MockVlmServer::start(script).await.unwrap();
+        let client = LlmClient::new(ClientConfig::new(&server.base_url(), "key")).unwrap();
+        let request = ChatCompletionRequest::new("stub".to_string(), vec![text_message(MessageRole::user, "click it".to_string())]);
+        let config = VotingConfig {
+            samples: 3,
+            mode: SampleMode::Choices,
+            ..VotingConfig::default()
+        };
+
+        // the mock answers with a single choice, the other two are sampled one by one
+        let samples = sample_completions(&client, &request, &config).await.unwrap();
+        assert_eq!(samples.texts.len(), 3);
+        assert_eq!(samples.requests, 3);
+        assert_eq!(server.request_count(), 3);
+
+        let candidates: Vec<_> = samples.texts.iter().map(|text| parse_action_vlm(text, (1000.0, 1000.0), "bc")).collect();
+        let winner = vote(&candidates, config.radius).unwrap();
+        assert_eq!(winner.support, 2);
+        assert!(samples.texts[winner.index].starts_with("Thought: a") || samples.texts[winner.index].starts_with("Thought: b"));
+    }
+}
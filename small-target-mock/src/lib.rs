@@ -0,0 +1,2 @@
+pub mod server;
+pub use server::{Matcher, MockReply, MockResponse, MockScript, MockVlmServer, RecordedRequest};
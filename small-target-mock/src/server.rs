@@ -0,0 +1,446 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::DynamicImage;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone)]
+pub enum MockReply {
+    /// assistant message, streamed as one delta when the request asks for a stream
+    Text(String),
+    /// content deltas, each streamed after its pause, joined into one message for requests without `stream`
+    Stream(Vec<(Duration, String)>),
+    /// function calls, each `(name, arguments json)`, with optional text content
+    ToolCalls { content: Option<String>, calls: Vec<(String, String)> },
+    /// error status with an OpenAI style error body
+    Error { status: u16, message: String },
+    /// close the connection without answering
+    Disconnect,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub reply: MockReply,
+    pub delay: Duration,
+    /// extra response headers, e.g. `retry-after`
+    pub headers: Vec<(String, String)>,
+    /// write the body in pieces of this many bytes, 0 writes it at once
+    pub split: usize,
+}
+
+impl MockResponse {
+    pub fn text(content: &str) -> Self {
+        Self::new(MockReply::Text(content.to_string()))
+    }
+
+    pub fn stream(deltas: &[(Duration, &str)]) -> Self {
+        Self::new(MockReply::Stream(deltas.iter().map(|(pause, content)| (*pause, content.to_string())).collect()))
+    }
+
+    pub fn tool_calls(calls: &[(&str, &str)]) -> Self {
+        Self::new(MockReply::ToolCalls {
+            content: None,
+            calls: calls.iter().map(|(name, arguments)| (name.to_string(), arguments.to_string())).collect(),
+        })
+    }
+
+    pub fn tool_calls_with_content(content: &str, calls: &[(&str, &str)]) -> Self {
+        Self::new(MockReply::ToolCalls {
+            content: Some(content.to_string()),
+            calls: calls.iter().map(|(name, arguments)| (name.to_string(), arguments.to_string())).collect(),
+        })
+    }
+
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::new(MockReply::Error {
+            status,
+            message: message.to_string(),
+        })
+    }
+
+    pub fn disconnect() -> Self {
+        Self::new(MockReply::Disconnect)
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sends the body a few bytes at a time, so clients see UTF-8 characters and SSE lines cut in half.
+    pub fn with_split(mut self, bytes: usize) -> Self {
+        self.split = bytes;
+        self
+    }
+
+    fn new(reply: MockReply) -> Self {
+        Self {
+            reply,
+            delay: Duration::ZERO,
+            headers: Vec::new(),
+            split: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    /// the n-th request the server receives, from 0
+    Step(usize),
+    /// any text part of the request contains this
+    TextContains(String),
+    /// the `model` field
+    Model(String),
+}
+
+impl Matcher {
+    fn matches(&self, request: &RecordedRequest) -> bool {
+        match self {
+            Matcher::Step(step) => request.step == *step,
+            Matcher::TextContains(text) => request.texts.iter().any(|t| t.contains(text.as_str())),
+            Matcher::Model(model) => request.model == *model,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    matcher: Matcher,
+    response: MockResponse,
+    /// left uses, `None` for unlimited
+    times: Option<usize>,
+}
+
+/// Rules are tried first, in the order added, then the sequence.
+/// The sequence repeats its last response once it runs out, an empty script answers `finished()`.
+#[derive(Debug, Clone, Default)]
+pub struct MockScript {
+    rules: Vec<Rule>,
+    sequence: VecDeque<MockResponse>,
+}
+
+impl MockScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// appends to the sequence of responses
+    pub fn then(mut self, response: MockResponse) -> Self {
+        self.sequence.push_back(response);
+        self
+    }
+
+    pub fn on(mut self, matcher: Matcher, response: MockResponse) -> Self {
+        self.rules.push(Rule { matcher, response, times: None });
+        self
+    }
+
+    /// like `on` but only for the first `times` matching requests
+    pub fn on_times(mut self, matcher: Matcher, times: usize, response: MockResponse) -> Self {
+        self.rules.push(Rule {
+            matcher,
+            response,
+            times: Some(times),
+        });
+        self
+    }
+
+    fn next(&mut self, request: &RecordedRequest) -> MockResponse {
+        if let Some(rule) = self.rules.iter_mut().find(|rule| rule.times != Some(0) && rule.matcher.matches(request)) {
+            if let Some(times) = rule.times.as_mut() {
+                *times -= 1;
+            }
+            return rule.response.clone();
+        }
+        if self.sequence.len() > 1 {
+            return self.sequence.pop_front().unwrap();
+        }
+        self.sequence.front().cloned().unwrap_or_else(|| MockResponse::text("Thought: Nothing scripted.\nAction: finished()"))
+    }
+}
+
+/// A chat completion request as the server saw it.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub step: usize,
+    pub model: String,
+    pub stream: bool,
+    /// text parts of all messages, in order
+    pub texts: Vec<String>,
+    /// decoded `data:` url images of all messages, in order
+    pub images: Vec<DynamicImage>,
+    pub body: Value,
+}
+
+impl RecordedRequest {
+    fn parse(step: usize, body: &str) -> Self {
+        let body: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+        let mut texts = Vec::new();
+        let mut images = Vec::new();
+        for message in body["messages"].as_array().into_iter().flatten() {
+            match &message["content"] {
+                Value::String(text) => texts.push(text.clone()),
+                Value::Array(parts) => {
+                    for part in parts {
+                        if let Some(text) = part["text"].as_str() {
+                            texts.push(text.to_string());
+                        }
+                        if let Some(image) = part["image_url"]["url"].as_str().and_then(decode_data_url) {
+                            images.push(image);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            step,
+            model: body["model"].as_str().unwrap_or_default().to_string(),
+            stream: body["stream"].as_bool().unwrap_or(false),
+            texts,
+            images,
+            body,
+        }
+    }
+}
+
+fn decode_data_url(url: &str) -> Option<DynamicImage> {
+    let (_, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+    image::load_from_memory(&STANDARD.decode(data).ok()?).ok()
+}
+
+/// In-process OpenAI compatible server answering `POST .../chat/completions` from a script.
+/// Other methods and paths get a 404, so a client calling the wrong endpoint fails.
+pub struct MockVlmServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+impl MockVlmServer {
+    pub async fn start(script: MockScript) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let script = Arc::new(Mutex::new(script));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let server = Self {
+            addr,
+            requests: requests.clone(),
+            max_in_flight: max_in_flight.clone(),
+        };
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else { break };
+                let script = script.clone();
+                let requests = requests.clone();
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                tokio::spawn(async move {
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    handle_connection(stream, script, requests).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Ok(server)
+    }
+
+    /// the `base_url` for `ClientConfig`, ends with `/v1`
+    pub fn base_url(&self) -> String {
+        format!("http://{}/v1", self.addr)
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+
+    /// most requests the server was answering at the same time
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight.load(Ordering::SeqCst)
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, script: Arc<Mutex<MockScript>>, requests: Arc<Mutex<Vec<RecordedRequest>>>) {
+    let Some((request_line, body)) = read_request(&mut stream).await else { return };
+    if !is_chat_completions(&request_line) {
+        log::warn!("mock server got {:?}, only POST .../chat/completions is served", request_line);
+        write_response(&mut stream, 404, "application/json", &[], &[(Duration::ZERO, error_body(&format!("no route for {}", request_line)))], 0).await;
+        return;
+    }
+    let (request, response) = {
+        let mut requests = requests.lock().unwrap();
+        let request = RecordedRequest::parse(requests.len(), &body);
+        let response = script.lock().unwrap().next(&request);
+        requests.push(request.clone());
+        (request, response)
+    };
+    log::debug!("mock request {} answered with {:?}", request.step, response.reply);
+
+    tokio::time::sleep(response.delay).await;
+    let (status, content_type, parts) = match response.reply {
+        MockReply::Disconnect => return,
+        MockReply::Error { status, message } => (status, "application/json", vec![(Duration::ZERO, error_body(&message))]),
+        MockReply::Text(content) if request.stream => (200, "text/event-stream", sse_parts(&request.model, &[(Duration::ZERO, content)])),
+        MockReply::Stream(deltas) if request.stream => (200, "text/event-stream", sse_parts(&request.model, &deltas)),
+        MockReply::Stream(deltas) => {
+            let content: String = deltas.into_iter().map(|(_, delta)| delta).collect();
+            (200, "application/json", vec![(Duration::ZERO, completion_body(&request.model, MockReply::Text(content)))])
+        }
+        reply => (200, "application/json", vec![(Duration::ZERO, completion_body(&request.model, reply))]),
+    };
+    write_response(&mut stream, status, content_type, &response.headers, &parts, response.split).await;
+}
+
+/// `POST` to a path ending in `/chat/completions`, the query string aside
+fn is_chat_completions(request_line: &str) -> bool {
+    let mut words = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (words.next(), words.next()) else {
+        return false;
+    };
+    let path = target.split('?').next().unwrap_or_default();
+    method == "POST" && path.ends_with("/chat/completions")
+}
+
+/// Writes the body parts, each after its pause. A single part gets a `content-length`, several are
+/// delimited by closing the connection like a stream.
+async fn write_response(stream: &mut TcpStream, status: u16, content_type: &str, headers: &[(String, String)], parts: &[(Duration, String)], split: usize) {
+    let mut head = format!("HTTP/1.1 {} MOCK\r\nconnection: close\r\ncontent-type: {}\r\n", status, content_type);
+    if let [(_, body)] = parts {
+        head.push_str(&format!("content-length: {}\r\n", body.len()));
+    }
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+    for (pause, part) in parts {
+        tokio::time::sleep(*pause).await;
+        let piece_len = if split == 0 { part.len().max(1) } else { split };
+        for piece in part.as_bytes().chunks(piece_len) {
+            if stream.write_all(piece).await.is_err() || stream.flush().await.is_err() {
+                return;
+            }
+            if split > 0 {
+                // give the client a chance to read each piece on its own
+                tokio::time::sleep(SPLIT_PAUSE).await;
+            }
+        }
+    }
+    let _ = stream.shutdown().await;
+}
+
+fn error_body(message: &str) -> String {
+    json!({ "error": { "message": message, "type": "mock_error" } }).to_string()
+}
+
+/// between the pieces of a split body
+const SPLIT_PAUSE: Duration = Duration::from_millis(5);
+
+fn usage() -> Value {
+    json!({ "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 })
+}
+
+fn completion_body(model: &str, reply: MockReply) -> String {
+    let (message, finish_reason) = match reply {
+        MockReply::ToolCalls { content, calls } => {
+            let tool_calls: Vec<Value> = calls
+                .iter()
+                .enumerate()
+                .map(|(index, (name, arguments))| json!({ "id": format!("call_{}", index), "type": "function", "function": { "name": name, "arguments": arguments } }))
+                .collect();
+            (json!({ "role": "assistant", "content": content, "tool_calls": tool_calls }), "tool_calls")
+        }
+        MockReply::Text(content) => (json!({ "role": "assistant", "content": content }), "stop"),
+        MockReply::Stream(_) | MockReply::Error { .. } | MockReply::Disconnect => unreachable!("answered before building a completion"),
+    };
+    json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 0,
+        "model": model,
+        "choices": [{ "index": 0, "message": message, "finish_reason": finish_reason }],
+        "usage": usage(),
+        "system_fingerprint": null
+    })
+    .to_string()
+}
+
+/// one event per content delta after its pause, then a usage chunk and `[DONE]`
+fn sse_parts(model: &str, deltas: &[(Duration, String)]) -> Vec<(Duration, String)> {
+    let mut parts: Vec<(Duration, String)> = deltas
+        .iter()
+        .map(|(pause, content)| {
+            let delta = json!({
+                "id": "chatcmpl-mock",
+                "object": "chat.completion.chunk",
+                "created": 0,
+                "model": model,
+                "choices": [{ "index": 0, "delta": { "content": content }, "finish_reason": null }]
+            });
+            (*pause, format!("data: {}\n\n", delta))
+        })
+        .collect();
+    let usage = json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": model,
+        "choices": [],
+        "usage": usage()
+    });
+    parts.push((Duration::ZERO, format!("data: {}\n\ndata: [DONE]\n\n", usage)));
+    parts
+}
+
+/// The request line, e.g. `POST /v1/chat/completions HTTP/1.1`, and the body.
+async fn read_request(stream: &mut TcpStream) -> Option<(String, String)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 16384];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let request_line = head.lines().next().unwrap_or_default().to_string();
+    let content_length = head
+        .to_lowercase()
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buffer.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+    Some((request_line, String::from_utf8_lossy(&buffer[header_end..]).to_string()))
+}
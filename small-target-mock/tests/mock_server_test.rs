@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::{Duration, Instant};
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use image::{DynamicImage, GenericImageView};
+    use serde_json::{json, Value};
+    use small_target_mock::{Matcher, MockResponse, MockScript, MockVlmServer};
+
+    fn png_data_url(width: u32, height: u32) -> String {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(width, height).write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png).unwrap();
+        format!("data:image/png;base64,{}", STANDARD.encode(bytes))
+    }
+
+    fn request(text: &str) -> Value {
+        json!({
+            "model": "ui-tars",
+            "messages": [
+                { "role": "user", "content": text },
+                { "role": "user", "content": [{ "type": "image_url", "image_url": { "url": png_data_url(64, 32) } }] }
+            ]
+        })
+    }
+
+    async fn post(server: &MockVlmServer, body: &Value) -> reqwest::Result<(u16, Value)> {
+        let response = reqwest::Client::new().post(format!("{}/chat/completions", server.base_url())).json(body).send().await?;
+        let status = response.status().as_u16();
+        Ok((status, response.json().await?))
+    }
+
+    fn content(body: &Value) -> &str {
+        body["choices"][0]["message"]["content"].as_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_answer_rules_before_the_sequence() {
+        let script = MockScript::new()
+            .then(MockResponse::text("first"))
+            .then(MockResponse::text("second"))
+            .on(Matcher::TextContains("calculator".to_string()), MockResponse::text("calculator rule"))
+            .on_times(Matcher::Step(3), 1, MockResponse::error(503, "overloaded"));
+        let server = MockVlmServer::start(script).await.unwrap();
+
+        assert_eq!(content(&post(&server, &request("open notes")).await.unwrap().1), "first");
+        assert_eq!(content(&post(&server, &request("open the calculator")).await.unwrap().1), "calculator rule");
+        assert_eq!(content(&post(&server, &request("open notes")).await.unwrap().1), "second");
+        let (status, body) = post(&server, &request("open notes")).await.unwrap();
+        assert_eq!(status, 503);
+        assert_eq!(body["error"]["message"], "overloaded");
+        // the sequence repeats its last response
+        assert_eq!(content(&post(&server, &request("open notes")).await.unwrap().1), "second");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[1].texts, vec!["open the calculator"]);
+        assert_eq!(requests[1].images.len(), 1);
+        assert_eq!(requests[1].images[0].dimensions(), (64, 32));
+    }
+
+    #[tokio::test]
+    async fn should_delay_and_disconnect() {
+        let script = MockScript::new().then(MockResponse::text("slow").with_delay(Duration::from_millis(200))).then(MockResponse::disconnect());
+        let server = MockVlmServer::start(script).await.unwrap();
+
+        let started = Instant::now();
+        assert_eq!(content(&post(&server, &request("wait")).await.unwrap().1), "slow");
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(post(&server, &request("wait")).await.is_err());
+        assert_eq!(server.request_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_stream_text_when_asked() {
+        let server = MockVlmServer::start(MockScript::new().then(MockResponse::text("Action: finished()"))).await.unwrap();
+        let mut body = request("done?");
+        body["stream"] = json!(true);
+        let response = reqwest::Client::new().post(format!("{}/chat/completions", server.base_url())).json(&body).send().await.unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let text = response.text().await.unwrap();
+        assert!(text.contains(r#""content":"Action: finished()""#));
+        assert!(text.ends_with("data: [DONE]\n\n"));
+        assert!(server.requests()[0].stream);
+    }
+
+    #[tokio::test]
+    async fn should_refuse_other_paths() {
+        let server = MockVlmServer::start(MockScript::new()).await.unwrap();
+        let client = reqwest::Client::new();
+        let response = client.post(format!("{}/completions", server.base_url())).json(&request("wrong")).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 404);
+        let response = client.get(format!("{}/chat/completions", server.base_url())).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(server.request_count(), 0);
+
+        assert_eq!(post(&server, &request("right")).await.unwrap().0, 200);
+        assert_eq!(server.request_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_pace_stream_deltas_and_join_them_without_stream() {
+        let deltas = [(Duration::ZERO, "Thought: 点"), (Duration::from_millis(200), "击\nAction: finished()")];
+        let script = MockScript::new().then(MockResponse::stream(&deltas).with_header("x-mock", "yes").with_split(3));
+        let server = MockVlmServer::start(script).await.unwrap();
+        let mut body = request("go");
+        body["stream"] = json!(true);
+
+        let started = Instant::now();
+        let response = reqwest::Client::new().post(format!("{}/chat/completions", server.base_url())).json(&body).send().await.unwrap();
+        assert_eq!(response.headers()["x-mock"], "yes");
+        let text = response.text().await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(text.contains(r#""content":"Thought: 点""#));
+        assert!(text.ends_with("data: [DONE]\n\n"));
+
+        assert_eq!(content(&post(&server, &request("go")).await.unwrap().1), "Thought: 点击\nAction: finished()");
+    }
+}
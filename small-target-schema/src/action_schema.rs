@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+/// What the executor builds from a model action, `Finished` and `CallUser` end the run instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActionKind {
+    MouseLeftClick,
+    MouseLeftDoubleClick,
+    MouseRightClick,
+    MouseMiddleClick,
+    MouseMove,
+    Drag,
+    Select,
+    TripleClick,
+    SelectAll,
+    Scroll,
+    ScrollUntil,
+    Hotkey,
+    KeyClick,
+    KeyDown,
+    KeyUp,
+    KeyHold,
+    ButtonDown,
+    ButtonUp,
+    WriteText,
+    SetClipboard,
+    GetClipboard,
+    Wait,
+    Finished,
+    CallUser,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    /// `[x1, y1, x2, y2]` or `[x, y]`
+    Box,
+    Text,
+    /// one key or a `+` separated combination
+    Keys,
+    Direction,
+    Integer,
+    /// left, right or middle
+    Button,
+    /// notches, lines, pixels or pages
+    ScrollUnit,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    /// shown in the prompt signature, `None` for required parameters
+    pub default: Option<&'static str>,
+    pub placeholder: &'static str,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ActionSpec {
+    /// the name documented to the model
+    pub name: &'static str,
+    /// other names the parser accepts, never shown in the prompt
+    pub aliases: &'static [&'static str],
+    pub kind: ActionKind,
+    pub params: &'static [ParamSpec],
+    /// appended to the prompt line as a `#` comment
+    pub note: Option<&'static str>,
+}
+
+const fn required(name: &'static str, kind: ParamKind, placeholder: &'static str) -> ParamSpec {
+    ParamSpec {
+        name,
+        kind,
+        default: None,
+        placeholder,
+    }
+}
+
+const fn optional(name: &'static str, kind: ParamKind, default: &'static str) -> ParamSpec {
+    ParamSpec {
+        name,
+        kind,
+        default: Some(default),
+        placeholder: "",
+    }
+}
+
+const START_BOX: ParamSpec = required("start_box", ParamKind::Box, "[x1, y1, x2, y2]");
+const END_BOX: ParamSpec = required("end_box", ParamKind::Box, "[x3, y3, x4, y4]");
+
+/// Every action the model may emit, the parser and the prompt's action space both come from here.
+pub const ACTION_SCHEMA: &[ActionSpec] = &[
+    ActionSpec {
+        name: "click",
+        aliases: &["click_left"],
+        kind: ActionKind::MouseLeftClick,
+        params: &[START_BOX],
+        note: None,
+    },
+    ActionSpec {
+        name: "left_double",
+        aliases: &["left_double_click", "double_click"],
+        kind: ActionKind::MouseLeftDoubleClick,
+        params: &[START_BOX],
+        note: None,
+    },
+    ActionSpec {
+        name: "right_single",
+        aliases: &["right_click"],
+        kind: ActionKind::MouseRightClick,
+        params: &[START_BOX],
+        note: None,
+    },
+    ActionSpec {
+        name: "middle_click",
+        aliases: &["middle_single"],
+        kind: ActionKind::MouseMiddleClick,
+        params: &[START_BOX],
+        note: None,
+    },
+    ActionSpec {
+        name: "mouse_move",
+        aliases: &["hover"],
+        kind: ActionKind::MouseMove,
+        params: &[START_BOX],
+        note: Some("Move the mouse without clicking, e.g. to open a hover menu."),
+    },
+    ActionSpec {
+        name: "drag",
+        aliases: &[],
+        kind: ActionKind::Drag,
+        params: &[START_BOX, END_BOX],
+        note: None,
+    },
+    ActionSpec {
+        name: "select",
+        aliases: &["select_text"],
+        kind: ActionKind::Select,
+        params: &[START_BOX, END_BOX],
+        note: Some("Select the text from the start to the end element."),
+    },
+    ActionSpec {
+        name: "triple_click",
+        aliases: &["left_triple"],
+        kind: ActionKind::TripleClick,
+        params: &[START_BOX],
+        note: Some("Select the whole line or paragraph."),
+    },
+    ActionSpec {
+        name: "select_all",
+        aliases: &[],
+        kind: ActionKind::SelectAll,
+        params: &[],
+        note: None,
+    },
+    ActionSpec {
+        name: "hotkey",
+        aliases: &[],
+        kind: ActionKind::Hotkey,
+        params: &[required("key", ParamKind::Keys, "")],
+        note: None,
+    },
+    ActionSpec {
+        name: "key_click",
+        aliases: &["press"],
+        kind: ActionKind::KeyClick,
+        params: &[required("key", ParamKind::Keys, "")],
+        note: Some("Press and release a single key."),
+    },
+    ActionSpec {
+        name: "key_down",
+        aliases: &[],
+        kind: ActionKind::KeyDown,
+        params: &[required("key", ParamKind::Keys, "")],
+        note: Some("Hold a key down until key_up, e.g. shift while clicking several items."),
+    },
+    ActionSpec {
+        name: "key_up",
+        aliases: &[],
+        kind: ActionKind::KeyUp,
+        params: &[required("key", ParamKind::Keys, "")],
+        note: None,
+    },
+    ActionSpec {
+        name: "key_hold",
+        aliases: &["hold_key"],
+        kind: ActionKind::KeyHold,
+        params: &[required("key", ParamKind::Keys, ""), optional("milliseconds", ParamKind::Integer, "1000")],
+        note: Some("Hold a key for a while, 1s unless milliseconds is given."),
+    },
+    ActionSpec {
+        name: "mouse_down",
+        aliases: &[],
+        kind: ActionKind::ButtonDown,
+        params: &[START_BOX, optional("button", ParamKind::Button, "left")],
+        note: Some("Press a mouse button without releasing it, until mouse_up."),
+    },
+    ActionSpec {
+        name: "mouse_up",
+        aliases: &[],
+        kind: ActionKind::ButtonUp,
+        params: &[optional("button", ParamKind::Button, "left")],
+        note: None,
+    },
+    ActionSpec {
+        name: "type",
+        aliases: &[],
+        kind: ActionKind::WriteText,
+        params: &[required("content", ParamKind::Text, "")],
+        note: Some("If you want to submit your input, use \"\\n\" at the end of `content`."),
+    },
+    ActionSpec {
+        name: "set_clipboard",
+        aliases: &["copy_text"],
+        kind: ActionKind::SetClipboard,
+        params: &[required("content", ParamKind::Text, "")],
+        note: Some("Put text on the clipboard without typing it."),
+    },
+    ActionSpec {
+        name: "get_clipboard",
+        aliases: &["read_clipboard"],
+        kind: ActionKind::GetClipboard,
+        params: &[],
+        note: Some("Read the clipboard, its text is shown to you in the next step."),
+    },
+    ActionSpec {
+        name: "scroll",
+        aliases: &[],
+        kind: ActionKind::Scroll,
+        params: &[
+            START_BOX,
+            required("direction", ParamKind::Direction, "down or up or right or left"),
+            optional("length", ParamKind::Integer, "auto"),
+            optional("unit", ParamKind::ScrollUnit, "notches"),
+        ],
+        note: None,
+    },
+    ActionSpec {
+        name: "scroll_until",
+        aliases: &["scroll_to_end"],
+        kind: ActionKind::ScrollUntil,
+        params: &[
+            START_BOX,
+            required("direction", ParamKind::Direction, "down or up or right or left"),
+            optional("max_scrolls", ParamKind::Integer, "20"),
+            optional("target", ParamKind::Text, ""),
+        ],
+        note: Some("Keep scrolling until the content stops moving, or until `target`, a text or an image file, is visible."),
+    },
+    ActionSpec {
+        name: "wait",
+        aliases: &[],
+        kind: ActionKind::Wait,
+        params: &[optional("milliseconds", ParamKind::Integer, "5000")],
+        note: Some("Sleep for 5s and take a screenshot to check for any changes."),
+    },
+    ActionSpec {
+        name: "finished",
+        aliases: &[],
+        kind: ActionKind::Finished,
+        params: &[],
+        note: None,
+    },
+    ActionSpec {
+        name: "call_user",
+        aliases: &[],
+        kind: ActionKind::CallUser,
+        params: &[],
+        note: Some("Submit the task and call the user when the task is unsolvable, or when you need the user's help."),
+    },
+];
+
+impl ActionKind {
+    /// ends the run, there is nothing to execute
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, ActionKind::Finished | ActionKind::CallUser)
+    }
+}
+
+impl ActionSpec {
+    pub fn is_terminal(&self) -> bool {
+        self.kind.is_terminal()
+    }
+
+    pub fn param(&self, name: &str) -> Option<&ParamSpec> {
+        self.params.iter().find(|p| p.name == name)
+    }
+
+    /// `click(start_box='[x1, y1, x2, y2]')`, optional parameters are left out
+    pub fn signature(&self) -> String {
+        let params: Vec<String> = self.params.iter().filter(|p| p.default.is_none()).map(|p| format!("{}='{}'", p.name, p.placeholder)).collect();
+        format!("{}({})", self.name, params.join(", "))
+    }
+
+    pub fn prompt_line(&self) -> String {
+        match self.note {
+            Some(note) => format!("{} # {}", self.signature(), note),
+            None => self.signature(),
+        }
+    }
+
+    /// The input value, or the default for optional parameters.
+    pub fn value<'a>(&self, name: &str, inputs: &'a HashMap<String, String>) -> Result<&'a str> {
+        let param = self.param(name).ok_or_else(|| anyhow!("{} has no parameter {}", self.name, name))?;
+        match (inputs.get(name), param.default) {
+            (Some(value), _) => Ok(value.as_str()),
+            (None, Some(default)) => Ok(default),
+            (None, None) => Err(anyhow!("missing {} in inputs: {:?}", name, inputs)),
+        }
+    }
+}
+
+/// Looks an action up by name or alias.
+pub fn lookup_action(name: &str) -> Option<&'static ActionSpec> {
+    ACTION_SCHEMA.iter().find(|spec| spec.name == name || spec.aliases.contains(&name))
+}
+
+/// The `## Action Space` section of the system prompt.
+pub fn action_space_prompt() -> String {
+    ACTION_SCHEMA.iter().map(ActionSpec::prompt_line).collect::<Vec<_>>().join("\n")
+}
+
+/// `action_space_prompt` limited to the named actions, in the given order. Names outside the schema are skipped.
+pub fn action_space_prompt_for(names: &[&str]) -> String {
+    names.iter().filter_map(|name| lookup_action(name)).map(ActionSpec::prompt_line).collect::<Vec<_>>().join("\n")
+}
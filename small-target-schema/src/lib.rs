@@ -0,0 +1,2 @@
+pub mod action_schema;
+pub use action_schema::{action_space_prompt, action_space_prompt_for, lookup_action, ActionKind, ActionSpec, ParamKind, ParamSpec, ACTION_SCHEMA};
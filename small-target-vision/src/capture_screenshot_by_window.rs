@@ -189,6 +189,14 @@ impl WindowFilters {
     }
 }
 
+/// App name and title of the window with keyboard focus, `None` when no window has it.
+pub fn focused_window() -> Result<Option<(String, String)>, Box<dyn Error>> {
+    Ok(Window::all()?
+        .into_iter()
+        .find(|window| window.is_focused())
+        .map(|window| (window.app_name().to_string(), window.title().to_string())))
+}
+
 pub async fn capture_all_visible_windows(
     monitor: &SafeMonitor,
     window_filters: &WindowFilters,
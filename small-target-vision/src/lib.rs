@@ -3,5 +3,5 @@ pub use monitor::{MonitorData, SafeMonitor};
 
 pub mod capture_screenshot_by_window;
 pub use capture_screenshot_by_window::{
-    capture_all_visible_windows, CapturedWindow, WindowFilters,
+    capture_all_visible_windows, focused_window, CapturedWindow, WindowFilters,
 };
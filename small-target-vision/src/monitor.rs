@@ -11,6 +11,9 @@ pub struct SafeMonitor {
 
 #[derive(Clone)]
 pub struct MonitorData {
+    /// top left corner in the virtual desktop
+    pub x: i32,
+    pub y: i32,
     pub width: u32,
     pub height: u32,
     pub name: String,
@@ -21,6 +24,8 @@ impl SafeMonitor {
     pub fn new(monitor: Monitor) -> Self {
         let monitor_id = monitor.id();
         let monitor_data = Arc::new(MonitorData {
+            x: monitor.x(),
+            y: monitor.y(),
             width: monitor.width(),
             height: monitor.height(),
             name: monitor.name().to_string(),
@@ -34,6 +39,11 @@ impl SafeMonitor {
     }
 
     pub async fn capture_image(&self) -> Result<DynamicImage> {
+        self.capture_image_blocking()
+    }
+
+    /// Same as `capture_image`, for callers that aren't async.
+    pub fn capture_image_blocking(&self) -> Result<DynamicImage> {
         let monitor_id = self.monitor_id;
         
         let image = std::thread::spawn(move || -> Result<DynamicImage> {
@@ -65,6 +75,10 @@ impl SafeMonitor {
         (self.monitor_data.width, self.monitor_data.height)
     }
 
+    pub fn position(&self) -> (i32, i32) {
+        (self.monitor_data.x, self.monitor_data.y)
+    }
+
     pub fn name(&self) -> &str {
         &self.monitor_data.name
     }